@@ -5,15 +5,21 @@ impl AstBuilder {
     pub fn new() -> Self {
         Self {
             current_file: ProtoFile {
+                name: String::new(),
                 syntax: "proto3".to_string(),
+                edition: None,
                 package: String::new(),
+                imports: Vec::new(),
                 options: Vec::new(),
+                features: None,
                 messages: Vec::new(),
                 services: Vec::new(),
                 enums: Vec::new(),
+                extensions: Vec::new(),
                 dmxp_channels: Vec::new(),
             },
             current_message: None,
+            current_oneof: None,
             current_service: None,
             current_enum: None,
             message_stack: Vec::new(),
@@ -24,10 +30,19 @@ impl AstBuilder {
         self.current_file.syntax = syntax;
     }
 
+    pub fn set_edition(&mut self, edition: String) {
+        self.current_file.syntax = "editions".to_string();
+        self.current_file.edition = Some(edition);
+    }
+
     pub fn set_package(&mut self, package: String) {
         self.current_file.package = package;
     }
 
+    pub fn add_import(&mut self, import: Import) {
+        self.current_file.imports.push(import);
+    }
+
     pub fn add_option(&mut self, option: ProtoOption) {
         self.current_file.options.push(option);
     }
@@ -43,11 +58,30 @@ impl AstBuilder {
             fields: Vec::new(),
             nested_messages: Vec::new(),
             nested_enums: Vec::new(),
+            oneofs: Vec::new(),
+            reserved_ranges: Vec::new(),
+            reserved_names: Vec::new(),
+            extension_ranges: Vec::new(),
             options: Vec::new(),
+            features: None,
             dmxp_options: None,
+            comments: Comments::default(),
+            span: Span::default(),
         });
     }
 
+    pub fn set_message_comments(&mut self, comments: Comments) {
+        if let Some(current_msg) = self.current_message.as_mut() {
+            current_msg.comments = comments;
+        }
+    }
+
+    pub fn set_message_span(&mut self, span: Span) {
+        if let Some(current_msg) = self.current_message.as_mut() {
+            current_msg.span = span;
+        }
+    }
+
     pub fn end_message(&mut self) {
         let Some(finished) = self.current_message.take() else {
             eprintln!("Warning: end_message() called with no active message");
@@ -63,11 +97,49 @@ impl AstBuilder {
     }
     
     pub fn add_field(&mut self, field: Field) {
-        if let Some(current_msg) = self.current_message.as_mut() {
+        if let Some(current_oneof) = self.current_oneof.as_mut() {
+            current_oneof.fields.push(field);
+        } else if let Some(current_msg) = self.current_message.as_mut() {
             current_msg.fields.push(field);
         }
     }
 
+    pub fn start_oneof(&mut self, name: String, comments: Comments) {
+        self.current_oneof = Some(Oneof {
+            name,
+            fields: Vec::new(),
+            options: Vec::new(),
+            comments,
+        });
+    }
+
+    pub fn add_oneof_option(&mut self, option: ProtoOption) {
+        if let Some(current_oneof) = self.current_oneof.as_mut() {
+            current_oneof.options.push(option);
+        }
+    }
+
+    pub fn end_oneof(&mut self) {
+        if let Some(oneof) = self.current_oneof.take()
+            && let Some(current_msg) = self.current_message.as_mut()
+        {
+            current_msg.oneofs.push(oneof);
+        }
+    }
+
+    pub fn add_message_reserved(&mut self, ranges: Vec<ReservedRange>, names: Vec<String>) {
+        if let Some(current_msg) = self.current_message.as_mut() {
+            current_msg.reserved_ranges.extend(ranges);
+            current_msg.reserved_names.extend(names);
+        }
+    }
+
+    pub fn add_message_extension_ranges(&mut self, ranges: Vec<ReservedRange>) {
+        if let Some(current_msg) = self.current_message.as_mut() {
+            current_msg.extension_ranges.extend(ranges);
+        }
+    }
+
     pub fn add_message_option(&mut self, option: ProtoOption) {
         if let Some(current_msg) = self.current_message.as_mut() {
             current_msg.options.push(option);
@@ -90,9 +162,23 @@ impl AstBuilder {
             methods: Vec::new(),
             options: Vec::new(),
             dmxp_options: None,
+            comments: Comments::default(),
+            span: Span::default(),
         });
     }
 
+    pub fn set_service_comments(&mut self, comments: Comments) {
+        if let Some(current_svc) = self.current_service.as_mut() {
+            current_svc.comments = comments;
+        }
+    }
+
+    pub fn set_service_span(&mut self, span: Span) {
+        if let Some(current_svc) = self.current_service.as_mut() {
+            current_svc.span = span;
+        }
+    }
+
     pub fn end_service(&mut self) {
         if let Some(service) = self.current_service.take() {
             self.current_file.services.push(service);
@@ -129,10 +215,27 @@ impl AstBuilder {
         self.current_enum = Some(Enum {
             name,
             values: Vec::new(),
+            reserved_ranges: Vec::new(),
+            reserved_names: Vec::new(),
             options: Vec::new(),
+            features: None,
+            comments: Comments::default(),
+            span: Span::default(),
         });
     }
 
+    pub fn set_enum_comments(&mut self, comments: Comments) {
+        if let Some(current_enum) = self.current_enum.as_mut() {
+            current_enum.comments = comments;
+        }
+    }
+
+    pub fn set_enum_span(&mut self, span: Span) {
+        if let Some(current_enum) = self.current_enum.as_mut() {
+            current_enum.span = span;
+        }
+    }
+
     pub fn end_enum(&mut self) {
         if let Some(enum_def) = self.current_enum.take() {
             if let Some(current_msg) = self.current_message.as_mut() {
@@ -143,12 +246,39 @@ impl AstBuilder {
         }
     }
 
+    pub fn add_enum_option(&mut self, option: ProtoOption) {
+        if let Some(current_enum) = self.current_enum.as_mut() {
+            current_enum.options.push(option);
+        }
+    }
+
     pub fn add_enum_value(&mut self, value: EnumValue) {
         if let Some(current_enum) = self.current_enum.as_mut() {
             current_enum.values.push(value);
         }
     }
 
+    pub fn add_enum_reserved(&mut self, ranges: Vec<ReservedRange>, names: Vec<String>) {
+        if let Some(current_enum) = self.current_enum.as_mut() {
+            current_enum.reserved_ranges.extend(ranges);
+            current_enum.reserved_names.extend(names);
+        }
+    }
+
+    pub fn add_extension(&mut self, extension: Extension) {
+        self.current_file.extensions.push(extension);
+    }
+
+    /// Dotted path of the messages currently being built (e.g. "Outer.Inner")
+    pub fn current_scope(&self) -> String {
+        self.message_stack
+            .iter()
+            .chain(self.current_message.iter())
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
     pub fn add_dmxp_channel(&mut self, channel: DmxpChannel) {
         self.current_file.dmxp_channels.push(channel);
     }
@@ -181,6 +311,49 @@ impl ProtoFile {
         self.enums.iter().find(|e| e.name == name)
     }
 
+    /// Find a file-level option by name (e.g. "go_package" or "(acme.owner)")
+    pub fn find_option(&self, name: &str) -> Option<&OptionValue> {
+        self.options.iter().find(|o| o.name == name).map(|o| &o.value)
+    }
+
+    /// Get the Go package from the `go_package` option
+    ///
+    /// Accepts both "import/path;name" and a bare import path, in which case
+    /// the name is the last path element.
+    pub fn go_package(&self) -> Option<GoPackage> {
+        let Some(OptionValue::String(value)) = self.find_option("go_package") else {
+            return None;
+        };
+        let (import_path, name) = match value.split_once(';') {
+            Some((path, name)) => (path, name.to_string()),
+            None => (value.as_str(), value.rsplit('/').next().unwrap_or_default().replace(['-', '.'], "_")),
+        };
+        Some(GoPackage {
+            import_path: import_path.to_string(),
+            name,
+        })
+    }
+
+    /// Find the extension declaring custom option `option_name` (e.g.
+    /// "(dmxp_channel)" or "(acme.audit)") on the options message `extendee`
+    /// (e.g. "google.protobuf.MessageOptions")
+    pub fn find_option_extension(&self, extendee: &str, option_name: &str) -> Option<&Extension> {
+        let name = option_name
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .trim_start_matches('.');
+        self.extensions.iter().find(|ext| {
+            let full_name = [self.package.as_str(), ext.scope.as_str(), ext.name.as_str()]
+                .iter()
+                .filter(|part| !part.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+                .join(".");
+            ext.extendee.trim_start_matches('.') == extendee
+                && (full_name == name || ext.scope.is_empty() && ext.name == name)
+        })
+    }
+
     /// Get all DMXP channels
     pub fn get_dmxp_channels(&self) -> &Vec<DmxpChannel> {
         &self.dmxp_channels
@@ -218,6 +391,238 @@ impl Message {
             .as_ref()
             .and_then(|opts| opts.channel.as_ref())
     }
+
+    /// All fields of the message, including those inside oneofs
+    pub fn all_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().chain(self.oneofs.iter().flat_map(|o| o.fields.iter()))
+    }
+
+    /// Check if a field number falls in one of the message's reserved ranges
+    pub fn is_reserved_number(&self, number: i32) -> bool {
+        self.reserved_ranges.iter().any(|r| r.contains(number))
+    }
+
+    /// Check if a field name is reserved
+    pub fn is_reserved_name(&self, name: &str) -> bool {
+        self.reserved_names.iter().any(|n| n == name)
+    }
+
+    /// Check if a field number falls in one of the message's extension ranges
+    pub fn is_extension_number(&self, number: i32) -> bool {
+        self.extension_ranges.iter().any(|r| r.contains(number))
+    }
+}
+
+impl Enum {
+    /// Find an enum option by name (e.g. "allow_alias")
+    pub fn find_option(&self, name: &str) -> Option<&OptionValue> {
+        self.options.iter().find(|o| o.name == name).map(|o| &o.value)
+    }
+
+    /// Check if `option allow_alias = true;` is set
+    pub fn allows_alias(&self) -> bool {
+        matches!(self.find_option("allow_alias"), Some(OptionValue::Boolean(true)))
+    }
+
+    /// The first value declared with `value`'s number, which `value` is an
+    /// alias of unless it is that value itself
+    pub fn canonical_value<'a>(&'a self, value: &'a EnumValue) -> &'a EnumValue {
+        self.values.iter().find(|v| v.number == value.number).unwrap_or(value)
+    }
+
+    /// Values that are not aliases of an earlier value
+    pub fn canonical_values(&self) -> impl Iterator<Item = &EnumValue> {
+        self.values.iter().filter(|v| std::ptr::eq(self.canonical_value(v), *v))
+    }
+
+    /// Aliases paired with the value they alias
+    pub fn aliases(&self) -> impl Iterator<Item = (&EnumValue, &EnumValue)> {
+        self.values
+            .iter()
+            .map(|v| (v, self.canonical_value(v)))
+            .filter(|(v, canonical)| !std::ptr::eq(*v, *canonical))
+    }
+
+    /// Check if a value number falls in one of the enum's reserved ranges
+    pub fn is_reserved_number(&self, number: i32) -> bool {
+        self.reserved_ranges.iter().any(|r| r.contains(number))
+    }
+
+    /// Check if a value name is reserved
+    pub fn is_reserved_name(&self, name: &str) -> bool {
+        self.reserved_names.iter().any(|n| n == name)
+    }
+
+    /// Check if generators emit the enum as open, i.e. with a variant or
+    /// constant that keeps numbers it does not declare
+    ///
+    /// Only editions files resolve `enum_type`, so this is false for proto3
+    /// enums too, even though they are open on the wire: generators keep
+    /// their classic behavior outside editions.
+    pub fn generates_open_enum(&self) -> bool {
+        self.features.is_some_and(|f| f.enum_type == EnumType::Open)
+    }
+}
+
+impl EnumValue {
+    /// Find a value option by name (e.g. "deprecated")
+    pub fn find_option(&self, name: &str) -> Option<&OptionValue> {
+        self.options.iter().find(|o| o.name == name).map(|o| &o.value)
+    }
+
+    /// Check if the value is marked `[deprecated = true]`
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self.find_option("deprecated"), Some(OptionValue::Boolean(true)))
+    }
+}
+
+impl ReservedRange {
+    /// Check if `number` lies within the range
+    pub fn contains(&self, number: i32) -> bool {
+        (self.start..=self.end).contains(&number)
+    }
+}
+
+impl Field {
+    /// Find an option by name (e.g. "deprecated" or "(dmxp_key)")
+    pub fn find_option(&self, name: &str) -> Option<&OptionValue> {
+        self.options
+            .iter()
+            .find(|opt| opt.name == name)
+            .map(|opt| &opt.value)
+    }
+
+    /// Check if the field is marked `[deprecated = true]`
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self.find_option("deprecated"), Some(OptionValue::Boolean(true)))
+    }
+
+    /// Get the JSON key for the field: `json_name` if set, the field name otherwise
+    pub fn json_name(&self) -> &str {
+        match self.find_option("json_name") {
+            Some(OptionValue::String(name)) => name,
+            _ => &self.name,
+        }
+    }
+
+    /// Check if the field can use packed encoding (repeated scalar numeric field)
+    pub fn is_packable(&self) -> bool {
+        matches!(self.label, FieldLabel::Repeated)
+            && !matches!(
+                self.field_type,
+                FieldType::String | FieldType::Bytes | FieldType::Message(_) | FieldType::Map(_, _)
+            )
+    }
+
+    /// Check if the field is encoded packed: in editions files this is the
+    /// resolved `repeated_field_encoding`; otherwise an explicit `packed`
+    /// option wins, and packable fields are packed by default in proto3 only
+    pub fn is_packed(&self, syntax: &str) -> bool {
+        if !self.is_packable() {
+            return false;
+        }
+        if let Some(features) = &self.features {
+            return features.repeated_field_encoding == RepeatedFieldEncoding::Packed;
+        }
+        match self.find_option("packed") {
+            Some(OptionValue::Boolean(packed)) => *packed,
+            _ => syntax == "proto3",
+        }
+    }
+
+    /// Check if the field has implicit presence, i.e. an unset field reads as
+    /// its zero value and is not wrapped in `Option`
+    ///
+    /// Repeated fields and message fields always track presence themselves,
+    /// whatever `presence` says.
+    pub fn has_implicit_presence(&self) -> bool {
+        self.presence == FieldPresence::Implicit
+            && !matches!(self.label, FieldLabel::Repeated)
+            && !matches!(self.field_type, FieldType::Message(_) | FieldType::Map(_, _))
+    }
+}
+
+impl FeatureSet {
+    /// Editions this crate knows the feature defaults of
+    pub const SUPPORTED_EDITIONS: [&'static str; 1] = ["2023"];
+
+    /// Feature defaults of an edition (e.g. "2023"), or `None` if the edition
+    /// is not supported
+    pub fn for_edition(edition: &str) -> Option<Self> {
+        match edition {
+            "2023" => Some(Self {
+                field_presence: FieldPresence::Explicit,
+                enum_type: EnumType::Open,
+                repeated_field_encoding: RepeatedFieldEncoding::Packed,
+                utf8_validation: Utf8Validation::Verify,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::Allow,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Set a single feature (e.g. `field_presence` to `IMPLICIT`)
+    ///
+    /// # Returns
+    /// An error message if the feature or its value is unknown
+    pub fn set(&mut self, feature: &str, value: &OptionValue) -> Result<(), String> {
+        let OptionValue::Identifier(value) = value else {
+            return Err(format!("Feature '{}' must be set to an enum value", feature));
+        };
+        match (feature, value.as_str()) {
+            ("field_presence", "EXPLICIT") => self.field_presence = FieldPresence::Explicit,
+            ("field_presence", "IMPLICIT") => self.field_presence = FieldPresence::Implicit,
+            ("field_presence", "LEGACY_REQUIRED") => self.field_presence = FieldPresence::LegacyRequired,
+            ("enum_type", "OPEN") => self.enum_type = EnumType::Open,
+            ("enum_type", "CLOSED") => self.enum_type = EnumType::Closed,
+            ("repeated_field_encoding", "PACKED") => self.repeated_field_encoding = RepeatedFieldEncoding::Packed,
+            ("repeated_field_encoding", "EXPANDED") => self.repeated_field_encoding = RepeatedFieldEncoding::Expanded,
+            ("utf8_validation", "VERIFY") => self.utf8_validation = Utf8Validation::Verify,
+            ("utf8_validation", "NONE") => self.utf8_validation = Utf8Validation::None,
+            ("message_encoding", "LENGTH_PREFIXED") => self.message_encoding = MessageEncoding::LengthPrefixed,
+            ("message_encoding", "DELIMITED") => self.message_encoding = MessageEncoding::Delimited,
+            ("json_format", "ALLOW") => self.json_format = JsonFormat::Allow,
+            ("json_format", "LEGACY_BEST_EFFORT") => self.json_format = JsonFormat::LegacyBestEffort,
+            ("field_presence" | "enum_type" | "repeated_field_encoding" | "utf8_validation" | "message_encoding"
+            | "json_format", _) => return Err(format!("Invalid value '{}' for feature '{}'", value, feature)),
+            _ => return Err(format!("Unknown feature '{}'", feature)),
+        }
+        Ok(())
+    }
+
+    /// The features with every feature setting in `options` applied, ignoring
+    /// settings that are not valid
+    pub fn with_options(&self, options: &[ProtoOption]) -> Self {
+        let mut features = *self;
+        for (feature, value) in options.iter().flat_map(Self::settings) {
+            let _ = features.set(&feature, value);
+        }
+        features
+    }
+
+    /// The feature settings made by an option: `features.field_presence = IMPLICIT`
+    /// sets one feature and `features = { field_presence: IMPLICIT }` sets each
+    /// listed feature
+    ///
+    /// Language-specific features (e.g. `features.(pb.cpp).string_type`) are not
+    /// resolved and yield nothing, as do options that are not features.
+    pub fn settings(option: &ProtoOption) -> Vec<(String, &OptionValue)> {
+        match (option.name.strip_prefix("features"), &option.value) {
+            (Some(""), OptionValue::Aggregate(entries)) => {
+                entries
+                    .iter()
+                    .filter(|entry| !entry.name.starts_with('['))
+                    .map(|entry| (entry.name.clone(), &entry.value))
+                    .collect()
+            }
+            (Some(rest), value) => match rest.strip_prefix('.') {
+                Some(feature) if !feature.starts_with('(') => vec![(feature.to_string(), value)],
+                _ => Vec::new(),
+            },
+            (None, _) => Vec::new(),
+        }
+    }
 }
 
 impl Service {
@@ -236,4 +641,43 @@ impl Service {
             .map(|opts| opts.channels.iter().collect())
             .unwrap_or_default()
     }
+
+    /// Get the DMXP channel backing a method: its own `dmxp_method_channel`
+    /// if set, otherwise `<first service channel>.<method>`, or
+    /// `<service>.<method>` when the service declares no channels
+    pub fn method_channel(&self, method: &Method) -> String {
+        if let Some(channel) = method.dmxp_options.as_ref().and_then(|opts| opts.channel.clone()) {
+            return channel;
+        }
+        let base = self.get_dmxp_channels()
+            .first()
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| self.name.clone());
+        format!("{}.{}", base, method.name)
+    }
+
+    /// Get the timeout for calls to a method: its own `dmxp_method_timeout_ms` if set,
+    /// otherwise the service-wide one
+    pub fn method_timeout_ms(&self, method: &Method) -> Option<u32> {
+        method.dmxp_options
+            .as_ref()
+            .and_then(|opts| opts.timeout_ms)
+            .or_else(|| self.dmxp_options.as_ref().and_then(|opts| opts.timeout_ms))
+    }
+}
+
+impl Method {
+    /// Check if either side of the method streams
+    pub fn is_streaming(&self) -> bool {
+        self.client_streaming || self.server_streaming
+    }
+
+    /// Check if the method is generated as async, falling back to `default`
+    /// when it has no `dmxp_async` option
+    pub fn is_async(&self, default: bool) -> bool {
+        self.dmxp_options
+            .as_ref()
+            .and_then(|opts| opts.is_async)
+            .unwrap_or(default)
+    }
 }
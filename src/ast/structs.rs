@@ -3,30 +3,106 @@ use serde::{Deserialize, Serialize};
 /// Root AST node representing the entire protobuf file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProtoFile {
+    /// Name of the file as it is imported (e.g. "acme/common.proto"); empty
+    /// when the file was not loaded through `ProtoLoader`
+    pub name: String,
+    /// "proto2", "proto3", or "editions" for files declaring an edition
     pub syntax: String,
+    /// Edition from `edition = "2023";`, if the file uses editions
+    pub edition: Option<String>,
     pub package: String,
+    pub imports: Vec<Import>,
     pub options: Vec<ProtoOption>,
+    /// Features resolved from the edition defaults and the file's
+    /// `features.*` options; only set for editions files
+    pub features: Option<FeatureSet>,
     pub messages: Vec<Message>,
     pub services: Vec<Service>,
     pub enums: Vec<Enum>,
+    /// Every `extend` declared in the file, including those nested in messages
+    pub extensions: Vec<Extension>,
     pub dmxp_channels: Vec<DmxpChannel>,
 }
 
 
+/// Import statement (e.g. `import public "acme/common.proto";`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Import {
+    pub path: String,
+    pub kind: ImportKind,
+}
+
+/// Import kind
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImportKind {
+    Default,
+    Public,
+    Weak,
+}
+
 /// Option definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProtoOption {
     pub name: String,
     pub value: OptionValue,
+    pub span: Span,
 }
 
 /// Option value types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OptionValue {
     String(String),
+    /// String literal that is not valid UTF-8 (e.g. `"\xff"`), which only a
+    /// `bytes` field or option accepts
+    Bytes(Vec<u8>),
+    /// Non-negative integer literal, kept exact (e.g. `18446744073709551615`)
+    UInt(u64),
+    /// Negative integer literal, kept exact
+    Int(i64),
+    /// Floating point literal, `inf` or `nan`
     Number(f64),
     Boolean(bool),
     Identifier(String),
+    /// Message literal in text format (e.g. `{ name: "events" size: 10 }`),
+    /// with one entry per field in source order
+    Aggregate(Vec<ProtoOption>),
+    /// List of values inside an aggregate (e.g. `tags: ["a", "b"]`)
+    List(Vec<OptionValue>),
+}
+
+/// Where a declaration starts in its source file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    /// Name of the file, as passed to the parser or loader (empty when unnamed)
+    pub file: String,
+    /// Byte offset from the start of the file
+    pub offset: usize,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+}
+
+/// Comments attached to a declaration, following protoc's conventions
+///
+/// `leading` is the comment block directly above the declaration, `trailing`
+/// is the comment on the same line after it, and `detached` holds earlier
+/// comment blocks separated from the declaration by a blank line.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Comments {
+    pub leading: Option<String>,
+    pub trailing: Option<String>,
+    pub detached: Vec<String>,
+}
+
+/// Go package a file is generated into, from its `go_package` option
+/// (e.g. "github.com/acme/events;events")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoPackage {
+    /// Import path (e.g. "github.com/acme/events")
+    pub import_path: String,
+    /// Package name (e.g. "events")
+    pub name: String,
 }
 
 /// Message definition
@@ -36,23 +112,65 @@ pub struct Message {
     pub fields: Vec<Field>,
     pub nested_messages: Vec<Message>,
     pub nested_enums: Vec<Enum>,
+    pub oneofs: Vec<Oneof>,
+    pub reserved_ranges: Vec<ReservedRange>,
+    pub reserved_names: Vec<String>,
+    /// Field numbers left for extensions (e.g. `extensions 100 to 199;`)
+    pub extension_ranges: Vec<ReservedRange>,
     pub options: Vec<ProtoOption>,
+    /// Features inherited from the file and enclosing messages, with the
+    /// message's own `features.*` options applied; only set for editions files
+    pub features: Option<FeatureSet>,
     pub dmxp_options: Option<DmxpMessageOptions>,
+    pub comments: Comments,
+    pub span: Span,
+}
+
+/// Inclusive range of reserved field or enum value numbers (e.g. `9 to 11`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReservedRange {
+    pub start: i32,
+    pub end: i32,
+}
+
+/// Oneof definition: at most one of `fields` is set at a time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Oneof {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub options: Vec<ProtoOption>,
+    pub comments: Comments,
 }
 
 /// Field definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Field {
     pub name: String,
+    /// The type as written; message and enum names keep their written form
+    /// (e.g. `Outer.Inner` or `.acme.v1.User`)
     pub field_type: FieldType,
+    /// Fully qualified name, with a leading dot, of the message or enum type
+    /// (the map value type for maps), e.g. ".acme.v1.Outer.Inner"; filled in
+    /// by the parser for types declared in the same file, and by the loader
+    /// once references are resolved across files
+    pub resolved_type: Option<String>,
     pub number: i32,
     pub label: FieldLabel,
+    /// Whether the field tracks if it is set: implicit for proto3 fields not
+    /// written `optional` (outside oneofs), explicit otherwise, and in
+    /// editions files the resolved `features.field_presence`
+    pub presence: FieldPresence,
     pub options: Vec<ProtoOption>,
+    /// Features inherited from the enclosing message, with the field's own
+    /// `[features.* = ...]` options applied; only set for editions files
+    pub features: Option<FeatureSet>,
     pub default_value: Option<OptionValue>,
+    pub comments: Comments,
+    pub span: Span,
 }
 
 /// Field type
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FieldType {
     // Scalar types
     Double,
@@ -85,6 +203,69 @@ pub enum FieldLabel {
     Repeated,
 }
 
+/// Editions features that have been resolved for a declaration
+///
+/// Each edition fixes defaults for every feature (see
+/// `FeatureSet::for_edition`), which `features.*` options then override for a
+/// file, message, field or enum and everything nested inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureSet {
+    pub field_presence: FieldPresence,
+    pub enum_type: EnumType,
+    pub repeated_field_encoding: RepeatedFieldEncoding,
+    pub utf8_validation: Utf8Validation,
+    pub message_encoding: MessageEncoding,
+    pub json_format: JsonFormat,
+}
+
+/// `features.field_presence`: whether singular fields track if they are set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldPresence {
+    /// Set and unset are distinguished, like proto2 `optional`
+    Explicit,
+    /// An unset field is its zero value, like a plain proto3 field
+    Implicit,
+    /// The field must be set, like proto2 `required`
+    LegacyRequired,
+}
+
+/// `features.enum_type`: whether an enum accepts numbers it does not declare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnumType {
+    /// Unknown numbers are kept, like proto3 enums
+    Open,
+    /// Unknown numbers are rejected, like proto2 enums
+    Closed,
+}
+
+/// `features.repeated_field_encoding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepeatedFieldEncoding {
+    Packed,
+    Expanded,
+}
+
+/// `features.utf8_validation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Utf8Validation {
+    Verify,
+    None,
+}
+
+/// `features.message_encoding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageEncoding {
+    LengthPrefixed,
+    Delimited,
+}
+
+/// `features.json_format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsonFormat {
+    Allow,
+    LegacyBestEffort,
+}
+
 /// Service definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Service {
@@ -92,16 +273,31 @@ pub struct Service {
     pub methods: Vec<Method>,
     pub options: Vec<ProtoOption>,
     pub dmxp_options: Option<DmxpServiceOptions>,
+    pub comments: Comments,
+    pub span: Span,
 }
 
 /// Service method
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Method {
     pub name: String,
+    /// Request type as written (e.g. "GetRequest" or ".acme.v1.GetRequest")
     pub input_type: String,
+    /// Response type as written
     pub output_type: String,
+    /// Fully qualified name of the request type, with a leading dot; filled
+    /// in like `Field::resolved_type`
+    pub resolved_input_type: Option<String>,
+    /// Fully qualified name of the response type, with a leading dot
+    pub resolved_output_type: Option<String>,
+    /// `rpc X(stream A) ...`: the client sends a stream of requests
+    pub client_streaming: bool,
+    /// `rpc X(A) returns (stream B)`: the server sends a stream of responses
+    pub server_streaming: bool,
     pub options: Vec<ProtoOption>,
     pub dmxp_options: Option<DmxpMethodOptions>,
+    pub comments: Comments,
+    pub span: Span,
 }
 
 /// Enum definition
@@ -109,7 +305,14 @@ pub struct Method {
 pub struct Enum {
     pub name: String,
     pub values: Vec<EnumValue>,
+    pub reserved_ranges: Vec<ReservedRange>,
+    pub reserved_names: Vec<String>,
     pub options: Vec<ProtoOption>,
+    /// Features inherited from the enclosing scope, with the enum's own
+    /// `features.*` options applied; only set for editions files
+    pub features: Option<FeatureSet>,
+    pub comments: Comments,
+    pub span: Span,
 }
 
 /// Enum value
@@ -118,15 +321,31 @@ pub struct EnumValue {
     pub name: String,
     pub number: i32,
     pub options: Vec<ProtoOption>,
+    pub comments: Comments,
+    pub span: Span,
 }
 
-/// Extension definition
+/// Extension definition: a field declared in an `extend` block
+///
+/// Custom options are extensions of the descriptor option messages, e.g.
+/// `extend google.protobuf.MessageOptions { optional string dmxp_channel = 50001; }`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Extension {
     pub name: String,
+    /// The extended message as written (e.g. "google.protobuf.MessageOptions")
+    pub extendee: String,
     pub field_type: FieldType,
+    /// Fully qualified name of the message or enum type, with a leading dot;
+    /// filled in like `Field::resolved_type`
+    pub resolved_type: Option<String>,
     pub number: i32,
+    pub label: FieldLabel,
+    /// Dotted path of the enclosing messages for an `extend` nested in a
+    /// message (e.g. "Outer.Inner"); empty at the top level
+    pub scope: String,
     pub options: Vec<ProtoOption>,
+    pub comments: Comments,
+    pub span: Span,
 }
 
 /// DMXP-specific message options
@@ -188,6 +407,7 @@ pub struct DmxpChannelOptions {
 pub struct AstBuilder {
     pub current_file: ProtoFile,
     pub current_message: Option<Message>,
+    pub current_oneof: Option<Oneof>,
     pub current_service: Option<Service>,
     pub current_enum: Option<Enum>,
     pub message_stack: Vec<Message>,
@@ -1,3 +1,6 @@
+#![allow(non_snake_case)]
+#![allow(clippy::module_inception)]
+
 pub mod ast;
 pub mod parser;
 pub mod utils;
@@ -1,20 +1,48 @@
-mod ast;
-mod parser;
-mod utils;
-mod templateGen;
-
-use crate::parser::parse_proto_file;
-use crate::templateGen::{TemplateGenerator, Language};
+use DMXP_Protobuf_Plugin::parser::{Diagnostic, ParseError, ProtoLoader};
+use DMXP_Protobuf_Plugin::templateGen::{TemplateGenerator, Language};
 use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use std::collections::HashMap;
+
+/// Command line arguments
+#[derive(Debug, Parser)]
+#[command(name = "protoc-gen-dmxp", about = "Code generation tool for DMXP")]
+struct Args {
+    /// How to print parse errors and warnings
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+}
+
+/// Output format for diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ErrorFormat {
+    /// rustc-style messages with a code frame
+    Human,
+    /// `ParseError::to_json`, for editors and CI
+    Json,
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
     println!("DMXP Protobuf Plugin - Parsing test.proto");
     
-    // Load the protofile to string from the file
-    let proto_file_string = utils::LoadFile::LoadFile("test.proto")?;
-    
-    // Parse the test.proto file into AST
-    let proto_file = parse_proto_file("test.proto")?;
+    // Parse the test.proto file and its imports into ASTs, showing a code
+    // frame for parse errors
+    let mut sources = HashMap::new();
+    let proto_set = match ProtoLoader::new().load_with_sources("test.proto", &mut sources) {
+        Ok(proto_set) => proto_set,
+        Err(err) => {
+            if let Some(parse_error) = err.downcast_ref::<ParseError>() {
+                eprint!("{}", render_diagnostics(&parse_error.diagnostics, &sources, args.error_format));
+                std::process::exit(1);
+            }
+            return Err(err);
+        }
+    };
+    if !proto_set.warnings.is_empty() {
+        eprint!("{}", render_diagnostics(&proto_set.warnings, &sources, args.error_format));
+    }
+    let proto_file = proto_set.root().expect("the loader always returns the root file").clone();
     
     // Display the parsed AST
     println!("\n=== PARSED PROTOBUF FILE ===");
@@ -29,10 +57,8 @@ fn main() -> Result<()> {
             println!("    - {}: {:?} (number: {})", field.name, field.field_type, field.number);
         }
         
-        if let Some(dmxp_opts) = &message.dmxp_options {
-            if let Some(channel) = &dmxp_opts.channel {
-                println!("  DMXP Channel: {}", channel);
-            }
+        if let Some(channel) = message.get_dmxp_channel() {
+            println!("  DMXP Channel: {}", channel);
         }
         println!();
     }
@@ -95,3 +121,24 @@ fn main() -> Result<()> {
     println!("\nTemplate generation completed successfully!");
     Ok(())
 }
+
+/// Render diagnostics in `format`: as JSON, or each with an excerpt of the
+/// loaded file it points into
+fn render_diagnostics(diagnostics: &[Diagnostic], sources: &HashMap<String, String>, format: ErrorFormat) -> String {
+    match format {
+        ErrorFormat::Json => {
+            let error = ParseError { diagnostics: diagnostics.to_vec() };
+            format!("{}\n", error.to_json())
+        }
+        ErrorFormat::Human => diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let source = diagnostic.span
+                    .as_ref()
+                    .and_then(|span| sources.get(&span.file))
+                    .map_or("", String::as_str);
+                diagnostic.render(source)
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,44 @@
+use crate::ast::*;
+use crate::parser::parser::ProtoParser;
+use std::sync::OnceLock;
+
+/// Source of `dmxp/options.proto`, which declares the DMXP custom options
+pub const DMXP_OPTIONS_PROTO: &str = include_str!("../../proto/dmxp/options.proto");
+
+/// Well-known type definitions (e.g. `google.protobuf.Timestamp`), by import
+/// name, plus the options messages of `descriptor.proto` that custom options extend
+pub const WELL_KNOWN_TYPES: [(&str, &str); 7] = [
+    ("google/protobuf/any.proto", include_str!("../../proto/google/protobuf/any.proto")),
+    ("google/protobuf/descriptor.proto", include_str!("../../proto/google/protobuf/descriptor.proto")),
+    ("google/protobuf/duration.proto", include_str!("../../proto/google/protobuf/duration.proto")),
+    ("google/protobuf/empty.proto", include_str!("../../proto/google/protobuf/empty.proto")),
+    ("google/protobuf/struct.proto", include_str!("../../proto/google/protobuf/struct.proto")),
+    ("google/protobuf/timestamp.proto", include_str!("../../proto/google/protobuf/timestamp.proto")),
+    ("google/protobuf/wrappers.proto", include_str!("../../proto/google/protobuf/wrappers.proto")),
+];
+
+/// Source of the bundled file imported as `name` (e.g.
+/// "google/protobuf/timestamp.proto" or "dmxp/options.proto"), if any
+///
+/// `ProtoLoader` falls back to these when an import is not found, so they
+/// resolve without protoc's include directory.
+pub fn source(name: &str) -> Option<&'static str> {
+    if name == "dmxp/options.proto" {
+        return Some(DMXP_OPTIONS_PROTO);
+    }
+    WELL_KNOWN_TYPES
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, content)| *content)
+}
+
+/// The bundled `dmxp/options.proto`, parsed once on first use
+pub fn dmxp_options() -> &'static ProtoFile {
+    static FILE: OnceLock<ProtoFile> = OnceLock::new();
+    FILE.get_or_init(|| {
+        ProtoParser::new(DMXP_OPTIONS_PROTO.to_string())
+            .with_file_name("dmxp/options.proto")
+            .parse()
+            .expect("bundled dmxp/options.proto must parse")
+    })
+}
@@ -0,0 +1,245 @@
+use crate::ast::Span;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    /// Lowercase name used when rendering (e.g. "error")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// Stable code identifying the kind of problem a diagnostic reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Text that is not a valid token (bad characters, numbers or strings)
+    InvalidToken,
+    /// A token that does not fit the grammar, including end of file
+    UnexpectedToken,
+    /// A name, number or option defined more than once
+    Duplicate,
+    /// A field or enum value that collides with a reservation
+    Reserved,
+    /// A label that is not allowed where it is written
+    InvalidLabel,
+    /// A field number, enum value or integer that is out of range
+    InvalidNumber,
+    /// An option whose value has the wrong type or is not allowed
+    InvalidOption,
+    /// A `[default = ...]` that is not allowed or does not match the field
+    InvalidDefault,
+    /// An enum that breaks the zero-value or alias rules
+    InvalidEnum,
+    /// A type that cannot be used where it is written (map keys, groups, ...)
+    InvalidType,
+    /// A type reference that does not resolve
+    UnresolvedType,
+    /// An import that cannot be found or that forms a cycle
+    Import,
+    /// An unsupported edition, or an editions feature that is unknown or not
+    /// allowed where it is set
+    InvalidFeature,
+}
+
+impl ErrorCode {
+    /// The code as written in diagnostics (e.g. "E0002")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidToken => "E0001",
+            ErrorCode::UnexpectedToken => "E0002",
+            ErrorCode::Duplicate => "E0003",
+            ErrorCode::Reserved => "E0004",
+            ErrorCode::InvalidLabel => "E0005",
+            ErrorCode::InvalidNumber => "E0006",
+            ErrorCode::InvalidOption => "E0007",
+            ErrorCode::InvalidDefault => "E0008",
+            ErrorCode::InvalidEnum => "E0009",
+            ErrorCode::InvalidType => "E0010",
+            ErrorCode::UnresolvedType => "E0011",
+            ErrorCode::Import => "E0012",
+            ErrorCode::InvalidFeature => "E0013",
+        }
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// A single problem found in a proto file, with where it is and how to fix it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub message: String,
+    /// Where the problem starts, if it can be tied to the source
+    pub span: Option<Span>,
+    /// Number of bytes to underline, starting at `span` (at least one caret is drawn)
+    pub length: usize,
+    /// Suggestion for fixing the problem
+    pub help: Option<String>,
+    /// Extra context about the problem
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Create an error diagnostic with no location
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+            length: 0,
+            help: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Create a warning diagnostic with no location
+    pub fn warning(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    /// Point the diagnostic at `length` bytes of source starting at `span`
+    pub fn with_span(mut self, span: Span, length: usize) -> Self {
+        self.span = Some(span);
+        self.length = length;
+        self
+    }
+
+    /// Attach a suggestion for fixing the problem
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Attach a note with extra context
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Render the diagnostic rustc-style, with an excerpt of `source` and a
+    /// caret underline (e.g. "error[E0002]: Expected ';', found '}'")
+    ///
+    /// # Arguments
+    /// * `source` - Content of the file the diagnostic's span points into
+    ///
+    /// # Returns
+    /// The rendered diagnostic, ending in a newline
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("{}[{}]: {}\n", self.severity.as_str(), self.code.as_str(), self.message);
+
+        let Some(span) = &self.span else {
+            self.render_footer(&mut out, "");
+            return out;
+        };
+        let file = if span.file.is_empty() { "<input>" } else { span.file.as_str() };
+        let gutter = " ".repeat(span.line.to_string().len());
+        out.push_str(&format!("{}--> {}:{}:{}\n", gutter, file, span.line, span.column));
+
+        if let Some(line) = source.lines().nth(span.line.saturating_sub(1)) {
+            let start = line.char_indices().nth(span.column.saturating_sub(1)).map_or(line.len(), |(i, _)| i);
+            let indent: String = line[..start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let underlined = line[start..]
+                .char_indices()
+                .take_while(|(i, _)| *i < self.length)
+                .count()
+                .max(1);
+
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{} | {}\n", span.line, line));
+            out.push_str(&format!("{} | {}{}\n", gutter, indent, "^".repeat(underlined)));
+        }
+        self.render_footer(&mut out, &gutter);
+        out
+    }
+
+    fn render_footer(&self, out: &mut String, gutter: &str) {
+        if self.help.is_none() && self.notes.is_empty() {
+            return;
+        }
+        out.push_str(&format!("{} |\n", gutter));
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{} = help: {}\n", gutter, help));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// One-line form: "file: message at line L, column C"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) if !span.file.is_empty() => write!(
+                f,
+                "{}: {} at line {}, column {}",
+                span.file, self.message, span.line, span.column
+            ),
+            Some(span) => write!(f, "{} at line {}, column {}", self.message, span.line, span.column),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Error returned when parsing or loading proto files fails, holding one or
+/// more diagnostics
+///
+/// It travels inside `anyhow::Error`; use `downcast_ref::<ParseError>()` to
+/// get at the diagnostics.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseError {
+    /// Render every diagnostic with `render`, separated by blank lines
+    ///
+    /// # Arguments
+    /// * `source` - Content of the file the diagnostics point into
+    pub fn render(&self, source: &str) -> String {
+        self.diagnostics.iter().map(|d| d.render(source)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Machine-readable form for editors and CI, e.g.
+    /// `{"diagnostics":[{"code":"E0002","severity":"error",...}]}`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("diagnostics always serialize")
+    }
+}
+
+impl From<Diagnostic> for ParseError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self { diagnostics: vec![diagnostic] }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self.diagnostics.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,158 @@
+use crate::ast::*;
+use crate::parser::diagnostic::{Diagnostic, ErrorCode};
+
+/// Check every `features.*` option in `file` and, for editions files, resolve
+/// the features of the file and of each message, field and enum in it
+///
+/// Feature options are only allowed in editions files and must name a known
+/// feature and value. Resolving also applies the rules that depend on the
+/// resolved features: `LEGACY_REQUIRED` fields become required, fields with
+/// implicit presence cannot declare a default, and open enums must start at
+/// zero.
+///
+/// # Arguments
+/// * `file` - The parsed file, whose `features` are filled in
+///
+/// # Returns
+/// The problems found, one diagnostic each
+pub fn resolve_features(file: &mut ProtoFile) -> Vec<Diagnostic> {
+    let mut context = FeatureContext {
+        editions: file.edition.is_some(),
+        diagnostics: Vec::new(),
+    };
+    let defaults = file.edition.as_deref().and_then(FeatureSet::for_edition);
+
+    context.check(&file.options, defaults);
+    file.features = defaults.map(|features| features.with_options(&file.options));
+    for message in &mut file.messages {
+        context.resolve_message(message, file.features);
+    }
+    for enum_def in &mut file.enums {
+        context.resolve_enum(enum_def, file.features);
+    }
+    for extension in &file.extensions {
+        context.check(&extension.options, file.features);
+    }
+    for service in &file.services {
+        context.check(&service.options, file.features);
+        for method in &service.methods {
+            context.check(&method.options, file.features);
+        }
+    }
+    context.diagnostics
+}
+
+struct FeatureContext {
+    /// Whether the file declares an edition
+    editions: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl FeatureContext {
+    fn resolve_message(&mut self, message: &mut Message, parent: Option<FeatureSet>) {
+        self.check(&message.options, parent);
+        message.features = parent.map(|features| features.with_options(&message.options));
+
+        for field in &mut message.fields {
+            self.resolve_field(field, message.features, false);
+        }
+        for oneof in &mut message.oneofs {
+            self.check(&oneof.options, message.features);
+            let features = message.features.map(|features| features.with_options(&oneof.options));
+            for field in &mut oneof.fields {
+                self.resolve_field(field, features, true);
+            }
+        }
+        for nested in &mut message.nested_messages {
+            self.resolve_message(nested, message.features);
+        }
+        for enum_def in &mut message.nested_enums {
+            self.resolve_enum(enum_def, message.features);
+        }
+    }
+
+    fn resolve_field(&mut self, field: &mut Field, parent: Option<FeatureSet>, in_oneof: bool) {
+        self.check(&field.options, parent);
+        let Some(parent) = parent else {
+            return;
+        };
+        let features = parent.with_options(&field.options);
+        let sets_presence = field.options
+            .iter()
+            .flat_map(FeatureSet::settings)
+            .any(|(feature, _)| feature == "field_presence");
+        let repeated = matches!(field.label, FieldLabel::Repeated);
+
+        // Presence only applies to singular fields outside oneofs
+        if sets_presence && (repeated || in_oneof) {
+            let kind = if repeated { "Repeated" } else { "Oneof" };
+            let message = format!("{} field '{}' cannot set features.field_presence", kind, field.name);
+            self.report(ErrorCode::InvalidFeature, message, &field.span);
+        } else if !repeated && !in_oneof {
+            field.presence = features.field_presence;
+            match features.field_presence {
+                FieldPresence::LegacyRequired => field.label = FieldLabel::Required,
+                FieldPresence::Implicit if field.default_value.is_some() => {
+                    let message = format!("Field '{}' has implicit presence and cannot have a default value", field.name);
+                    self.diagnostics.push(
+                        Diagnostic::error(ErrorCode::InvalidDefault, message)
+                            .with_span(field.span.clone(), 1)
+                            .with_help("set [features.field_presence = EXPLICIT] on the field"),
+                    );
+                }
+                _ => {}
+            }
+        }
+        field.features = Some(features);
+    }
+
+    fn resolve_enum(&mut self, enum_def: &mut Enum, parent: Option<FeatureSet>) {
+        self.check(&enum_def.options, parent);
+        for value in &enum_def.values {
+            self.check(&value.options, parent);
+        }
+        enum_def.features = parent.map(|features| features.with_options(&enum_def.options));
+
+        if let (true, Some(first)) = (enum_def.generates_open_enum(), enum_def.values.first())
+            && first.number != 0
+        {
+            let message = format!("The first value of open enum '{}' must be zero", enum_def.name);
+            self.diagnostics.push(
+                Diagnostic::error(ErrorCode::InvalidEnum, message)
+                    .with_span(first.span.clone(), first.name.len())
+                    .with_note("the first value is the enum's default")
+                    .with_help("set 'option features.enum_type = CLOSED;' to allow a non-zero first value"),
+            );
+        }
+    }
+
+    /// Check the feature options among `options`, set in a scope whose
+    /// inherited features are `scope` (`None` outside editions)
+    fn check(&mut self, options: &[ProtoOption], scope: Option<FeatureSet>) {
+        for option in options {
+            if option.name != "features" && !option.name.starts_with("features.") {
+                continue;
+            }
+            let Some(mut features) = scope else {
+                // An unsupported edition is reported where it is declared
+                if !self.editions {
+                    self.diagnostics.push(
+                        Diagnostic::error(ErrorCode::InvalidFeature, "Features are only allowed in editions files")
+                            .with_span(option.span.clone(), 1)
+                            .with_help("declare 'edition = \"2023\";' instead of a syntax"),
+                    );
+                }
+                continue;
+            };
+            for (feature, value) in FeatureSet::settings(option) {
+                if let Err(message) = features.set(&feature, value) {
+                    self.report(ErrorCode::InvalidFeature, message, &option.span);
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, code: ErrorCode, message: String, span: &Span) {
+        self.diagnostics.push(Diagnostic::error(code, message).with_span(span.clone(), 1));
+    }
+}
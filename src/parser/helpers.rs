@@ -1,56 +1,62 @@
-use anyhow::Result;
+use crate::ast::OptionValue;
 
 /// Extract string values from option declarations
-pub fn extract_string_value(line: &str, key: &str) -> Option<String> {
-    if let Some(start) = line.find(&format!("{} = ", key)) {
-        let value_start = start + key.len() + 3;
-        let value_part = &line[value_start..];
-        if let Some(end) = value_part.find(';') {
-            let value = &value_part[..end];
-            return value.trim_matches('"').to_string().into();
-        }
+pub fn extract_string_value(value: &OptionValue) -> Option<String> {
+    match value {
+        OptionValue::String(s) | OptionValue::Identifier(s) => Some(s.clone()),
+        _ => None,
     }
-    None
 }
 
 /// Extract number values from option declarations
-pub fn extract_number_value<T>(line: &str, key: &str) -> Option<T>
+///
+/// Numeric strings (e.g. `"5000"`) are accepted as well.
+pub fn extract_number_value<T>(value: &OptionValue) -> Option<T>
 where
     T: std::str::FromStr,
 {
-    if let Some(start) = line.find(&format!("{} = ", key)) {
-        let value_start = start + key.len() + 3;
-        let value_part = &line[value_start..];
-        if let Some(end) = value_part.find(';') {
-            let value = &value_part[..end];
-            return value.trim().parse().ok();
-        }
+    match value {
+        OptionValue::UInt(n) => n.to_string().parse().ok(),
+        OptionValue::Int(n) => n.to_string().parse().ok(),
+        OptionValue::Number(n) => n.to_string().parse().ok(),
+        OptionValue::String(s) => s.trim().parse().ok(),
+        _ => None,
     }
-    None
 }
 
 /// Extract boolean values from option declarations
-pub fn extract_bool_value(line: &str, key: &str) -> Option<bool> {
-    if let Some(start) = line.find(&format!("{} = ", key)) {
-        let value_start = start + key.len() + 3;
-        let value_part = &line[value_start..];
-        if let Some(end) = value_part.find(';') {
-            let value = &value_part[..end];
-            return value.trim().parse().ok();
-        }
+pub fn extract_bool_value(value: &OptionValue) -> Option<bool> {
+    match value {
+        OptionValue::Boolean(b) => Some(*b),
+        OptionValue::String(s) | OptionValue::Identifier(s) => s.trim().parse().ok(),
+        _ => None,
     }
-    None
 }
 
-/// Check if a line represents a field declaration
-pub fn is_field_line(line: &str) -> bool {
-    // Simple heuristic: field lines contain a type, name, and number
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    parts.len() >= 3 && 
-    !line.starts_with("message") && 
-    !line.starts_with("service") && 
-    !line.starts_with("enum") &&
-    !line.starts_with("option") &&
-    !line.starts_with("rpc") &&
-    (parts[2].contains('=') || (parts.len() > 3 && parts[3].contains('=')))
+/// The candidate closest to `name`, if one is close enough to be a likely
+/// typo (e.g. "dmxp_buffer_size" for "dmxp_bufer_size")
+pub fn closest_match<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Number of single-character insertions, deletions and substitutions that
+/// turn `a` into `b` (Levenshtein distance)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
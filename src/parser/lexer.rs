@@ -0,0 +1,420 @@
+use crate::ast::Span;
+use crate::parser::diagnostic::{Diagnostic, ErrorCode, ParseError};
+use anyhow::{Error, Result};
+
+/// Kinds of tokens produced by the lexer
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Identifier or keyword (e.g. `message`, `user_id`, `int32`)
+    Ident(String),
+    /// Integer literal
+    Int(u64),
+    /// Floating point literal
+    Float(f64),
+    /// String literal with the surrounding quotes removed and escapes
+    /// decoded; `\xff` and the like may leave bytes that are not UTF-8
+    Str(Vec<u8>),
+    /// Single punctuation character (`{`, `}`, `=`, `;`, `.`, ...)
+    Symbol(char),
+    /// End of input
+    Eof,
+}
+
+/// A single token together with its position in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte offset of the first character in the source
+    pub offset: usize,
+    /// Length of the token's text in bytes
+    pub length: usize,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    /// Comments found between the previous token and this one
+    pub comments: Vec<Comment>,
+}
+
+/// A `//` or `/* */` comment with its markers stripped
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    /// Line the comment starts on
+    pub start_line: usize,
+    /// Line the comment ends on (differs from `start_line` for block comments)
+    pub end_line: usize,
+    /// Whether this is a `/* ... */` comment
+    pub is_block: bool,
+}
+
+/// Lexer that turns protobuf source text into a flat list of tokens
+///
+/// Whitespace is skipped and comments are attached to the token that follows
+/// them, so the parser never sees the layout of the file, only the sequence of
+/// meaningful tokens.
+pub struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    offset: usize,
+    line: usize,
+    column: usize,
+    pending_comments: Vec<Comment>,
+    file_name: String,
+}
+
+impl<'a> Lexer<'a> {
+    /// Create a new lexer over the given source text
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            pending_comments: Vec::new(),
+            file_name: String::new(),
+        }
+    }
+
+    /// Set the file name recorded in the spans of lexer errors
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = file_name.into();
+        self
+    }
+
+    /// Tokenize the entire input
+    ///
+    /// # Returns
+    /// * `Result<Vec<Token>>` - All tokens, always terminated by `TokenKind::Eof`
+    ///
+    /// # Errors
+    /// Returns a [`ParseError`] on unterminated string literals or block
+    /// comments, malformed numbers or characters that cannot start any token
+    pub fn tokenize(mut self) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next_token()?;
+            let is_eof = token.kind == TokenKind::Eof;
+            tokens.push(token);
+            if is_eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut ahead = self.chars.clone();
+        ahead.next();
+        ahead.next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Skip whitespace, `//` line comments and `/* ... */` block comments
+    ///
+    /// Comment bodies are never tokenized, so braces, `=` or `;` inside a
+    /// comment cannot affect parsing in any scope. Their text is kept in
+    /// `pending_comments` until the next token is produced.
+    fn skip_trivia(&mut self) -> Result<()> {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.peek_second() == Some('/') {
+                let line = self.line;
+                self.bump();
+                self.bump();
+                let mut text = String::new();
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    text.push(c);
+                    self.bump();
+                }
+                self.pending_comments.push(Comment {
+                    text: clean_line_comment(&text),
+                    start_line: line,
+                    end_line: line,
+                    is_block: false,
+                });
+            } else if c == '/' && self.peek_second() == Some('*') {
+                let start = self.span();
+                let line = self.line;
+                self.bump();
+                self.bump();
+                let mut text = String::new();
+                loop {
+                    match self.bump() {
+                        Some('*') if self.peek() == Some('/') => {
+                            self.bump();
+                            break;
+                        }
+                        Some(c) => text.push(c),
+                        None => {
+                            return Err(self.error(&start, 2, "Unterminated block comment"));
+                        }
+                    }
+                }
+                self.pending_comments.push(Comment {
+                    text: clean_block_comment(&text),
+                    start_line: line,
+                    end_line: self.line,
+                    is_block: true,
+                });
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<Token> {
+        self.skip_trivia()?;
+
+        let start = self.span();
+        let comments = std::mem::take(&mut self.pending_comments);
+        let kind = match self.peek() {
+            None => TokenKind::Eof,
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => TokenKind::Ident(self.lex_ident()),
+            Some(c) if c.is_ascii_digit() || (c == '.' && self.peek_second().is_some_and(|n| n.is_ascii_digit())) => {
+                self.lex_number(&start)?
+            }
+            Some('"' | '\'') => TokenKind::Str(self.lex_string(&start)?),
+            Some(c) if "{}[]()<>;=,.-+:".contains(c) => {
+                self.bump();
+                TokenKind::Symbol(c)
+            }
+            Some(c) => {
+                return Err(self.error(&start, c.len_utf8(), &format!("Unexpected character '{}'", c)));
+            }
+        };
+
+        Ok(Token {
+            kind,
+            offset: start.offset,
+            length: self.offset - start.offset,
+            line: start.line,
+            column: start.column,
+            comments,
+        })
+    }
+
+    /// Span of the next character
+    fn span(&self) -> Span {
+        Span {
+            file: self.file_name.clone(),
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// Build an `InvalidToken` error underlining `length` bytes from `start`
+    fn error(&self, start: &Span, length: usize, message: &str) -> Error {
+        ParseError::from(Diagnostic::error(ErrorCode::InvalidToken, message).with_span(start.clone(), length)).into()
+    }
+
+    fn lex_ident(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                ident.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        ident
+    }
+
+    fn lex_number(&mut self, start: &Span) -> Result<TokenKind> {
+        if self.peek() == Some('0') && matches!(self.peek_second(), Some('x' | 'X')) {
+            return self.lex_hex(start);
+        }
+
+        let mut text = String::new();
+        let mut is_float = false;
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                text.push(c);
+            } else if c == '.' && !is_float {
+                is_float = true;
+                text.push(c);
+            } else if c == 'e' || c == 'E' {
+                is_float = true;
+                text.push(c);
+                self.bump();
+                if let Some(sign @ ('+' | '-')) = self.peek() {
+                    text.push(sign);
+                    self.bump();
+                }
+                continue;
+            } else {
+                break;
+            }
+            self.bump();
+        }
+
+        if is_float {
+            text.parse::<f64>()
+                .map(TokenKind::Float)
+                .map_err(|_| self.error(start, text.len(), &format!("Invalid number '{}'", text)))
+        } else if text.len() > 1 && text.starts_with('0') {
+            // A leading zero makes an integer octal (e.g. `017`)
+            u64::from_str_radix(&text[1..], 8)
+                .map(TokenKind::Int)
+                .map_err(|_| self.error(start, text.len(), &format!("Invalid octal number '{}'", text)))
+        } else {
+            text.parse::<u64>()
+                .map(TokenKind::Int)
+                .map_err(|_| self.error(start, text.len(), &format!("Invalid number '{}'", text)))
+        }
+    }
+
+    fn lex_hex(&mut self, start: &Span) -> Result<TokenKind> {
+        self.bump();
+        self.bump();
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphanumeric() {
+                break;
+            }
+            digits.push(c);
+            self.bump();
+        }
+
+        u64::from_str_radix(&digits, 16)
+            .map(TokenKind::Int)
+            .map_err(|_| self.error(start, digits.len() + 2, &format!("Invalid hex number '0x{}'", digits)))
+    }
+
+    /// Lex a single- or double-quoted string literal, decoding its escape
+    /// sequences (`\n`, `\'`, `\x41`, `\101`, `\u00e9`, `\U0001F600`, ...)
+    ///
+    /// The bytes are kept as they are: whether they must be UTF-8 depends on
+    /// where the literal is used (a `bytes` default need not be).
+    fn lex_string(&mut self, start: &Span) -> Result<Vec<u8>> {
+        let quote = self.bump().unwrap_or('"');
+        let mut bytes = Vec::new();
+        let unterminated = |lexer: &Self| lexer.error(start, lexer.offset - start.offset, "Unterminated string literal");
+
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(unterminated(self)),
+                Some('\\') => {
+                    let escaped = self.bump().ok_or_else(|| unterminated(self))?;
+                    self.lex_escape(escaped, &mut bytes, start)?;
+                }
+                Some(c) if c == quote => break,
+                Some(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decode the escape sequence that starts with `escaped` (the character
+    /// after the backslash), appending the bytes it stands for
+    fn lex_escape(&mut self, escaped: char, bytes: &mut Vec<u8>, start: &Span) -> Result<()> {
+        let simple = match escaped {
+            'a' => Some(0x07),
+            'b' => Some(0x08),
+            'f' => Some(0x0c),
+            'n' => Some(b'\n'),
+            'r' => Some(b'\r'),
+            't' => Some(b'\t'),
+            'v' => Some(0x0b),
+            '\\' | '\'' | '"' | '?' => Some(escaped as u8),
+            _ => None,
+        };
+        if let Some(byte) = simple {
+            bytes.push(byte);
+            return Ok(());
+        }
+
+        let sequence = match escaped {
+            'x' | 'X' => {
+                let digits = self.take_digits(16, 2);
+                if let Ok(byte) = u8::from_str_radix(&digits, 16) {
+                    bytes.push(byte);
+                    return Ok(());
+                }
+                format!("{}{}", escaped, digits)
+            }
+            '0'..='7' => {
+                let digits = format!("{}{}", escaped, self.take_digits(8, 2));
+                if let Ok(byte) = u8::from_str_radix(&digits, 8) {
+                    bytes.push(byte);
+                    return Ok(());
+                }
+                digits
+            }
+            'u' | 'U' => {
+                let width = if escaped == 'u' { 4 } else { 8 };
+                let digits = self.take_digits(16, width);
+                let decoded = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == width)
+                    .and_then(char::from_u32);
+                if let Some(c) = decoded {
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    return Ok(());
+                }
+                format!("{}{}", escaped, digits)
+            }
+            _ => escaped.to_string(),
+        };
+        Err(self.error(
+            start,
+            self.offset - start.offset,
+            &format!("Invalid escape sequence '\\{}' in string literal", sequence),
+        ))
+    }
+
+    /// Consume up to `max` digits in the given radix
+    fn take_digits(&mut self, radix: u32, max: usize) -> String {
+        let mut digits = String::new();
+        while digits.len() < max
+            && let Some(c) = self.peek().filter(|c| c.is_digit(radix))
+        {
+            digits.push(c);
+            self.bump();
+        }
+        digits
+    }
+}
+
+/// Strip the single space conventionally written after `//`
+fn clean_line_comment(text: &str) -> String {
+    text.strip_prefix(' ').unwrap_or(text).trim_end().to_string()
+}
+
+/// Strip the leading `*` decoration of each line and surrounding blank lines
+fn clean_block_comment(text: &str) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix('*').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect();
+
+    let start = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|l| !l.is_empty()).map_or(start, |i| i + 1);
+    lines[start..end].join("\n")
+}
@@ -0,0 +1,239 @@
+use crate::ast::*;
+use crate::parser::bundled;
+use crate::parser::diagnostic::{Diagnostic, ErrorCode, ParseError, Severity};
+use crate::parser::parser::ProtoParser;
+use crate::parser::resolver;
+use crate::parser::source::{FileSystemProvider, SourceProvider};
+use crate::utils::LoadFile;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A root proto file together with everything it imports
+#[derive(Debug, Clone)]
+pub struct ProtoSet {
+    /// All loaded files in dependency order: every file comes after the files it imports
+    pub files: Vec<ProtoFile>,
+    /// Warnings from parsing the files (e.g. DMXP option problems when
+    /// `with_strict_dmxp(false)`), in the order the files were parsed
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl ProtoSet {
+    /// Find a loaded file by its import name
+    pub fn find_file(&self, name: &str) -> Option<&ProtoFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// The file that was passed to `ProtoLoader::load`
+    pub fn root(&self) -> Option<&ProtoFile> {
+        self.files.last()
+    }
+}
+
+/// Import-aware loader that parses a proto file and the transitive closure of
+/// its imports, looking them up in `-I`-style include paths or in a
+/// [`SourceProvider`]
+///
+/// Imports that are not found fall back to the bundled files (the well-known
+/// types such as "google/protobuf/timestamp.proto", and "dmxp/options.proto").
+/// Missing and cyclic imports are reported as errors, and once every file is
+/// parsed all type references are resolved across files.
+#[derive(Debug, Clone, Default)]
+pub struct ProtoLoader {
+    include_paths: Vec<PathBuf>,
+    provider: Option<Arc<dyn SourceProvider>>,
+    /// Report DMXP option problems as warnings rather than errors
+    lenient_dmxp: bool,
+}
+
+impl ProtoLoader {
+    /// Create a loader with no include paths
+    ///
+    /// Without include paths, imports are looked up relative to the directory
+    /// of the root file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an include path (like protoc's `-I`); paths are searched in the order added
+    pub fn with_include_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Read every file, the root included, from `provider` instead of the
+    /// filesystem; include paths are then ignored
+    ///
+    /// # Arguments
+    /// * `provider` - Source of files by import name (e.g. an `InMemoryProvider`)
+    ///
+    /// # Returns
+    /// The loader, for chaining after `new`
+    pub fn with_source_provider(mut self, provider: impl SourceProvider + 'static) -> Self {
+        self.provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Choose whether problems with DMXP options are errors, the default, or
+    /// warnings (see `ProtoParser::with_strict_dmxp`)
+    pub fn with_strict_dmxp(mut self, strict: bool) -> Self {
+        self.lenient_dmxp = !strict;
+        self
+    }
+
+    /// Load `file_path` and everything it imports
+    ///
+    /// With a source provider, `file_path` is the root file's import name
+    /// (e.g. "acme/events.proto") and is read from the provider.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the root protobuf file
+    ///
+    /// # Returns
+    /// * `Result<ProtoSet>` - All parsed files with type references resolved,
+    ///   and the warnings reported while parsing them
+    ///
+    /// # Errors
+    /// Returns an error if a file cannot be read or parsed, if an import cannot
+    /// be found in the include paths, if imports form a cycle, or if a type
+    /// reference does not resolve
+    pub fn load(&self, file_path: &str) -> Result<ProtoSet> {
+        self.load_with_sources(file_path, &mut HashMap::new())
+    }
+
+    /// Load `file_path` and everything it imports like `load`, recording the
+    /// text of every file read, bundled ones included, so diagnostics can be
+    /// rendered with a code frame even when loading fails
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the root protobuf file
+    /// * `sources` - Filled with each file's content, keyed by the import name
+    ///   its diagnostics' spans use
+    ///
+    /// # Returns
+    /// * `Result<ProtoSet>` - As for `load`
+    pub fn load_with_sources(&self, file_path: &str, sources: &mut HashMap<String, String>) -> Result<ProtoSet> {
+        let (files, warnings) = match &self.provider {
+            Some(provider) => {
+                let content = provider
+                    .read(file_path)
+                    .with_context(|| format!("Failed to read '{}'", file_path))?
+                    .ok_or_else(|| {
+                        let message = format!("File '{}' not found in {}", file_path, provider.describe());
+                        ParseError::from(Diagnostic::error(ErrorCode::Import, message))
+                    })?;
+                LoadState::new(provider.as_ref(), !self.lenient_dmxp, sources).load(file_path, content)?
+            }
+            None => {
+                let include_paths = if self.include_paths.is_empty() {
+                    let parent = Path::new(file_path).parent().unwrap_or(Path::new(""));
+                    vec![parent.to_path_buf()]
+                } else {
+                    self.include_paths.clone()
+                };
+
+                let root_name = self.import_name(file_path, &include_paths);
+                let content = LoadFile::LoadFile(file_path)
+                    .with_context(|| format!("Failed to read '{}'", file_path))?;
+                let provider = FileSystemProvider::new(include_paths);
+                LoadState::new(&provider, !self.lenient_dmxp, sources).load(&root_name, content)?
+            }
+        };
+
+        let mut set = ProtoSet { files, warnings };
+        resolver::resolve_references(&mut set.files)?;
+        Ok(set)
+    }
+
+    /// Name under which the root file is known to the files it imports:
+    /// its path relative to the first include path that contains it
+    fn import_name(&self, file_path: &str, include_paths: &[PathBuf]) -> String {
+        let path = Path::new(file_path);
+        include_paths
+            .iter()
+            .filter(|include| !include.as_os_str().is_empty())
+            .find_map(|include| path.strip_prefix(include).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
+
+/// Bookkeeping for a single `ProtoLoader::load` call
+struct LoadState<'a> {
+    provider: &'a dyn SourceProvider,
+    strict_dmxp: bool,
+    files: Vec<ProtoFile>,
+    warnings: Vec<Diagnostic>,
+    /// Files currently being loaded, used to detect import cycles
+    stack: Vec<String>,
+    /// Content of every file read, by import name
+    sources: &'a mut HashMap<String, String>,
+}
+
+impl<'a> LoadState<'a> {
+    fn new(provider: &'a dyn SourceProvider, strict_dmxp: bool, sources: &'a mut HashMap<String, String>) -> Self {
+        Self {
+            provider,
+            strict_dmxp,
+            files: Vec::new(),
+            warnings: Vec::new(),
+            stack: Vec::new(),
+            sources,
+        }
+    }
+
+    /// Load the root file and its imports, returning them in dependency order
+    /// together with the warnings reported while parsing them
+    fn load(mut self, name: &str, content: String) -> Result<(Vec<ProtoFile>, Vec<Diagnostic>)> {
+        self.load_file(name, content)?;
+        Ok((self.files, self.warnings))
+    }
+
+    fn load_file(&mut self, name: &str, content: String) -> Result<()> {
+        self.sources.insert(name.to_string(), content.clone());
+        let (file, diagnostics) = ProtoParser::new(content)
+            .with_file_name(name)
+            .with_strict_dmxp(self.strict_dmxp)
+            .parse_partial();
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(ParseError { diagnostics }).with_context(|| format!("Failed to parse '{}'", name));
+        }
+        self.warnings.extend(diagnostics);
+
+        self.stack.push(name.to_string());
+        for import in &file.imports {
+            if self.files.iter().any(|f| f.name == import.path) {
+                continue;
+            }
+            if let Some(start) = self.stack.iter().position(|n| *n == import.path) {
+                let mut cycle = self.stack[start..].to_vec();
+                cycle.push(import.path.clone());
+                let message = format!("Import cycle detected: {}", cycle.join(" -> "));
+                return Err(ParseError::from(Diagnostic::error(ErrorCode::Import, message)).into());
+            }
+
+            // Files from the provider take precedence over the bundled ones
+            let content = self.provider
+                .read(&import.path)
+                .with_context(|| format!("Failed to read '{}'", import.path))?
+                .or_else(|| bundled::source(&import.path).map(str::to_string))
+                .ok_or_else(|| {
+                    let message = format!(
+                        "Import '{}' (imported from '{}') not found in {}",
+                        import.path,
+                        name,
+                        self.provider.describe()
+                    );
+                    ParseError::from(Diagnostic::error(ErrorCode::Import, message))
+                })?;
+            self.load_file(&import.path, content)?;
+        }
+        self.stack.pop();
+
+        self.files.push(file);
+        Ok(())
+    }
+}
@@ -1,6 +1,16 @@
+pub mod lexer;
 pub mod parser;
 pub mod parse;
 pub mod helpers;
+pub mod loader;
+pub mod resolver;
+pub mod bundled;
+pub mod diagnostic;
+pub mod features;
+pub mod source;
 
 // Re-export the main parsing function for easy access
-pub use parse::parse_proto_file;
\ No newline at end of file
+pub use parse::{parse_bytes, parse_proto_file, parse_reader, parse_str};
+pub use loader::{ProtoLoader, ProtoSet};
+pub use source::{FileSystemProvider, InMemoryProvider, SourceProvider};
+pub use diagnostic::{Diagnostic, ErrorCode, ParseError, Severity};
@@ -1,23 +1,66 @@
 use crate::parser::parser;
 use crate::ast::structs::ProtoFile;
-use anyhow::{Error, Result};
+use anyhow::{Context, Error, Result};
 use crate::utils::LoadFile;
+use std::io::Read;
 
 /// Parse a protobuf file from disk into an AST
-/// 
+///
 /// This is a convenience function that reads a protobuf file from the filesystem
 /// and parses it into a structured AST representation.
-/// 
+///
 /// # Arguments
 /// * `file_path` - Path to the protobuf file to parse
-/// 
+///
 /// # Returns
 /// * `Result<ProtoFile>` - The parsed AST or an error if parsing fails
-/// 
+///
 /// # Errors
 /// Returns an error if the file cannot be read or if parsing fails
 pub fn parse_proto_file(file_path: &str) -> Result<ProtoFile, Error> {
     let content = LoadFile::LoadFile(file_path)?;
-    let mut parser = parser::ProtoParser::new(content);
+    parse_str(file_path, &content)
+}
+
+/// Parse protobuf source held in memory into an AST
+///
+/// Imports are not followed; use `ProtoLoader` with an `InMemoryProvider`
+/// to load a virtual file tree.
+///
+/// # Arguments
+/// * `name` - Name of the file (e.g. "acme/events.proto"), recorded in the AST and in diagnostics
+/// * `content` - The protobuf source
+///
+/// # Returns
+/// * `Result<ProtoFile>` - The parsed AST or an error if parsing fails
+pub fn parse_str(name: &str, content: &str) -> Result<ProtoFile, Error> {
+    let mut parser = parser::ProtoParser::new(content.to_string()).with_file_name(name);
     parser.parse()
 }
+
+/// Parse protobuf source given as raw bytes, which must be UTF-8
+///
+/// # Arguments
+/// * `name` - Name of the file, recorded in the AST and in diagnostics
+/// * `bytes` - The protobuf source
+///
+/// # Returns
+/// * `Result<ProtoFile>` - The parsed AST or an error if the bytes are not UTF-8 or parsing fails
+pub fn parse_bytes(name: &str, bytes: &[u8]) -> Result<ProtoFile, Error> {
+    let content = std::str::from_utf8(bytes).with_context(|| format!("'{}' is not valid UTF-8", name))?;
+    parse_str(name, content)
+}
+
+/// Parse protobuf source read to the end from `reader` (e.g. a socket or pipe)
+///
+/// # Arguments
+/// * `name` - Name of the file, recorded in the AST and in diagnostics
+/// * `reader` - Source of the protobuf text, which must be UTF-8
+///
+/// # Returns
+/// * `Result<ProtoFile>` - The parsed AST or an error if reading or parsing fails
+pub fn parse_reader(name: &str, mut reader: impl Read) -> Result<ProtoFile, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).with_context(|| format!("Failed to read '{}'", name))?;
+    parse_bytes(name, &bytes)
+}
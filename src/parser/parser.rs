@@ -1,201 +1,385 @@
 use crate::ast::*;
-use anyhow::{Result};
-use crate::parser::helpers::{extract_string_value, extract_number_value, extract_bool_value, is_field_line};
-use regex::Regex;
+use anyhow::{Error, Result};
+use crate::parser::bundled;
+use crate::parser::diagnostic::{Diagnostic, ErrorCode, ParseError, Severity};
+use crate::parser::features;
+use crate::parser::helpers::{closest_match, extract_string_value, extract_number_value, extract_bool_value};
+use crate::parser::lexer::{Comment, Lexer, Token, TokenKind};
+use crate::parser::resolver;
+use std::ops::RangeInclusive;
+
+/// Largest field number protobuf allows (2^29 - 1)
+const MAX_FIELD_NUMBER: i32 = 536_870_911;
+
+/// Field numbers reserved for the protobuf implementation
+const IMPLEMENTATION_RESERVED: RangeInclusive<i32> = 19_000..=19_999;
+
+/// Keywords that start a top-level declaration, where error recovery resumes
+const TOP_LEVEL_KEYWORDS: [&str; 9] = ["syntax", "edition", "package", "import", "option", "message", "enum", "service", "extend"];
+
+/// The keyword that declares a field label
+fn label_keyword(label: &FieldLabel) -> &'static str {
+    match label {
+        FieldLabel::Optional => "optional",
+        FieldLabel::Required => "required",
+        FieldLabel::Repeated => "repeated",
+    }
+}
+
 /// Protobuf parser that converts .proto files to AST
-/// 
-/// This parser implements a line-by-line parsing approach for protobuf files,
-/// extracting messages, services, enums, and DMXP-specific options into a structured AST.
+///
+/// This parser tokenizes the protobuf source with [`Lexer`] and then walks the
+/// token stream with a recursive-descent parser, extracting messages, services,
+/// enums, and DMXP-specific options into a structured AST. Because it works on
+/// tokens, the layout of the file (line breaks, brace placement, several
+/// declarations on one line) never changes what gets parsed.
 #[derive(Debug)]
 pub struct ProtoParser {
     /// Raw content of the protobuf file
     pub content: String,
-    /// Current character position in the content (unused in line-based approach)
+    /// Tokens produced by the lexer (filled in by `parse`)
+    pub tokens: Vec<Token>,
+    /// Index of the current token in `tokens`
     pub position: usize,
-    /// Lines of the protobuf file for easier processing
-    pub lines: Vec<String>,
-    /// Current line being processed
+    /// Line of the current token (0 until parsing starts)
     pub current_line: usize,
+    /// Name of the file being parsed, recorded in the file and in every span
+    pub file_name: String,
+    /// Diagnostics collected by the parse in progress
+    pub diagnostics: Vec<Diagnostic>,
+    /// Whether problems with DMXP options are errors rather than warnings
+    pub strict_dmxp: bool,
 }
 
 impl ProtoParser {
     /// Create a new parser from file content
-    /// 
+    ///
     /// # Arguments
     /// * `content` - The raw protobuf file content as a string
-    /// 
+    ///
     /// # Returns
     /// A new ProtoParser instance ready to parse the content
     pub fn new(content: String) -> Self {
-        let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
         Self {
             content,
+            tokens: Vec::new(),
             position: 0,
-            lines,
             current_line: 0,
+            file_name: String::new(),
+            diagnostics: Vec::new(),
+            strict_dmxp: true,
         }
     }
 
+    /// Set the name of the file being parsed (e.g. "acme/events.proto")
+    ///
+    /// # Arguments
+    /// * `file_name` - Name recorded as `ProtoFile::name` and in every `Span`
+    ///
+    /// # Returns
+    /// The parser, for chaining after `new`
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = file_name.into();
+        self
+    }
+
+    /// Choose how problems with DMXP options are reported: an unknown
+    /// `dmxp_*` option, a value of the wrong type or a number out of range
+    ///
+    /// In strict mode, the default, they are errors. Otherwise they are
+    /// warnings and values that can still be read (e.g. `"5000"` for a
+    /// number) are applied, as older versions did without a word.
+    ///
+    /// # Arguments
+    /// * `strict` - Whether DMXP option problems are errors
+    ///
+    /// # Returns
+    /// The parser, for chaining after `new`
+    pub fn with_strict_dmxp(mut self, strict: bool) -> Self {
+        self.strict_dmxp = strict;
+        self
+    }
+
     /// Parse the protobuf content into an AST
-    /// 
+    ///
     /// This is the main entry point for parsing. It processes the entire protobuf file
     /// and builds a structured AST representation containing all messages, services,
     /// enums, and DMXP-specific options.
-    /// 
+    ///
     /// # Returns
     /// * `Result<ProtoFile>` - The parsed AST or an error if parsing fails
-    /// 
+    ///
     /// # Errors
-    /// Returns an error if the protobuf syntax is invalid or if parsing fails
+    /// Returns a [`ParseError`] holding every problem found (see `parse_partial`)
+    /// if the protobuf syntax is invalid or if parsing fails
     pub fn parse(&mut self) -> Result<ProtoFile> {
+        let (file, diagnostics) = self.parse_partial();
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(ParseError { diagnostics }.into());
+        }
+        Ok(file)
+    }
+
+    /// Parse the protobuf content, recovering from errors so a single pass
+    /// reports every problem
+    ///
+    /// After an error the parser skips ahead to the end of the statement (`;`),
+    /// the end of the enclosing block (`}`) or the next top-level keyword and
+    /// carries on, so declarations that parse are kept in the returned file.
+    /// Lexical errors (e.g. an unterminated string) still stop parsing.
+    ///
+    /// # Returns
+    /// * `(ProtoFile, Vec<Diagnostic>)` - The partial AST and all diagnostics, in source order
+    pub fn parse_partial(&mut self) -> (ProtoFile, Vec<Diagnostic>) {
+        self.diagnostics.clear();
+        self.position = 0;
         let mut builder = AstBuilder::new();
-        self.current_line = 0;
-        
-        while self.current_line < self.lines.len() {
-            let line = self.lines[self.current_line].trim();
-            
-            if line.is_empty() {
-                self.current_line += 1;
+        builder.current_file.name = self.file_name.clone();
+
+        match Lexer::new(&self.content).with_file_name(self.file_name.clone()).tokenize() {
+            Ok(tokens) => self.tokens = tokens,
+            Err(err) => {
+                self.tokens.clear();
+                self.record(err);
+                return (builder.build(), std::mem::take(&mut self.diagnostics));
+            }
+        }
+        self.current_line = self.peek().line;
+
+        while !self.at_end() {
+            if self.eat_symbol(';') {
                 continue;
             }
-            
-            if line.starts_with("syntax") {
-                self.parse_syntax(&mut builder)?;
-            } 
-            else if line.starts_with("package") {
-                self.parse_package(&mut builder)?;
-            } 
-            else if line.starts_with("message ") {
-                self.parse_message(&mut builder)?;
-            } 
-            else if line.starts_with("service ") {
-                self.parse_service(&mut builder)?;
-            } 
-            else if line.starts_with("enum ") {
-                self.parse_enum(&mut builder)?;
-            }
-            // Add other top-level declarations as needed
-            
-            self.current_line += 1;
-        }
-        
-        Ok(builder.build())
+
+            let start = self.position;
+            let result = match self.peek_ident().as_deref() {
+                Some("syntax") => self.parse_syntax(&mut builder),
+                Some("edition") => self.parse_edition(&mut builder),
+                Some("package") => self.parse_package(&mut builder),
+                Some("message") => self.parse_message(&mut builder),
+                Some("service") => self.parse_service(&mut builder),
+                Some("enum") => self.parse_enum(&mut builder),
+                Some("import") => self.parse_import(&mut builder),
+                Some("extend") => self.parse_extend(&mut builder),
+                Some("option") => self.parse_file_option(&mut builder),
+                _ => Err(self.unexpected("a top-level declaration")),
+            };
+            self.recover(result, start);
+        }
+
+        // Features are resolved once the whole file is known, since file
+        // options may follow the declarations they apply to
+        let mut file = builder.build();
+        resolver::resolve_local_types(&mut file);
+        self.diagnostics.extend(features::resolve_features(&mut file));
+        self.diagnostics.sort_by_key(|d| d.span.as_ref().map_or(usize::MAX, |span| span.offset));
+        (file, std::mem::take(&mut self.diagnostics))
     }
 
     /// Parse the syntax declaration (e.g., "syntax = \"proto3\";")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the syntax information to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_syntax(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        
-        if let Some(rest) = line.strip_prefix("syntax") {
-            let rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '=').trim_start();
-            let syntax = rest.trim_end_matches(';').trim().trim_matches('\"');
-            if !syntax.is_empty() {
-                builder.set_syntax(syntax.to_string());
-            }
+        self.expect_keyword("syntax")?;
+        self.expect_symbol('=')?;
+        let syntax = self.expect_string()?;
+        self.expect_symbol(';')?;
+
+        if !syntax.is_empty() {
+            builder.set_syntax(syntax);
+        }
+        Ok(())
+    }
+
+    /// Parse the edition declaration (e.g., "edition = \"2023\";"), which
+    /// takes the place of a syntax declaration
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the edition to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails or the edition is not supported
+    fn parse_edition(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        self.expect_keyword("edition")?;
+        self.expect_symbol('=')?;
+        let edition_index = self.position;
+        let edition = self.expect_string()?;
+        self.expect_symbol(';')?;
+
+        // Kept even when unsupported, so the file is still parsed as editions
+        builder.set_edition(edition.clone());
+        if FeatureSet::for_edition(&edition).is_none() {
+            let message = format!("Edition '{}' is not supported", edition);
+            let diagnostic = self.diagnostic_at(edition_index, ErrorCode::InvalidFeature, &message)
+                .with_note(format!("supported editions: {}", FeatureSet::SUPPORTED_EDITIONS.join(", ")));
+            return Err(ParseError::from(diagnostic).into());
         }
-        
         Ok(())
     }
 
     /// Parse the package declaration (e.g., "package com.example;")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the package information to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_package(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        if let Some(package) = line.strip_prefix("package").and_then(|s| s.strip_suffix(';')) {
-            builder.set_package(package.trim().to_string());
-        }
+        self.expect_keyword("package")?;
+        let package = self.parse_full_ident()?;
+        self.expect_symbol(';')?;
+
+        builder.set_package(package);
+        Ok(())
+    }
+
+    /// Parse import statements (e.g., "import public \"acme/common.proto\";")
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the import to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_import(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        self.expect_keyword("import")?;
+        let kind = if self.eat_keyword("public") {
+            ImportKind::Public
+        } else if self.eat_keyword("weak") {
+            ImportKind::Weak
+        } else {
+            ImportKind::Default
+        };
+        let path = self.expect_string()?;
+        self.expect_symbol(';')?;
+
+        builder.add_import(Import { path, kind });
         Ok(())
     }
 
     /// Parse message declarations (e.g., "message UserData { ... }")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the message to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_message(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        if let Some(name) = line.strip_prefix("message ").and_then(|s| s.split_whitespace().next()) {
-            let name = name.trim_end_matches('{');
-            builder.start_message(name.to_string());
-            
-            // Parse message body including fields and options
-            self.parse_message_body(builder)?;
-            builder.end_message();
-        }
-        Ok(())
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        self.expect_keyword("message")?;
+        let name = self.expect_ident()?;
+        comments.trailing = self.trailing_comment(self.position);
+        builder.start_message(name);
+        builder.set_message_comments(comments);
+        builder.set_message_span(span);
+
+        // Parse message body including fields and options; the message is
+        // kept even if its body has errors
+        let result = self.parse_message_body(builder);
+        builder.end_message();
+        result
     }
 
     /// Parse the body of a message declaration, including fields and options
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add message elements to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_message_body(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        // Skip the line with the message declaration
-        self.current_line += 1;
-        
-        while self.current_line < self.lines.len() {
-            let line = self.lines[self.current_line].trim();
-            
-            // Skip empty lines
-            if line.is_empty() {
-                self.current_line += 1;
-                continue;
-            }
-            
-            // Check for end of message
-            if line == "}" {
+        self.expect_symbol('{')?;
+
+        loop {
+            if self.eat_symbol('}') {
                 return Ok(());
             }
-            
+            if self.at_end() {
+                return Err(self.error(ErrorCode::UnexpectedToken, "Unexpected end of file while parsing message"));
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+
             // Parse different parts of the message
-            if line.starts_with("message ") {
-                self.parse_message(builder)?;
-            } 
-            else if line.starts_with("enum ") {
-                self.parse_enum(builder)?;
-            } 
-            else if line.starts_with("option ") {
-                self.parse_message_option(builder)?;
-            } 
-            else if is_field_line(line) {
-                self.parse_field(builder)?;
-            }
-            // Add support for oneof, extensions, etc. if needed
-            
-            self.current_line += 1;
-        }
-        
-        Err(anyhow::anyhow!("Unexpected end of file while parsing message"))
+            let start = self.position;
+            let result = match self.peek_ident().as_deref() {
+                Some("message") => self.parse_message(builder),
+                Some("enum") => self.parse_enum(builder),
+                Some("option") => self.parse_message_option(builder),
+                Some("oneof") => self.parse_oneof(builder),
+                Some("reserved") => self.parse_message_reserved(builder),
+                Some("extend") => self.parse_extend(builder),
+                Some("extensions") => self.parse_extensions(builder),
+                Some(_) => self.parse_field(builder),
+                None if self.is_symbol('.') => self.parse_field(builder),
+                None => Err(self.unexpected("a field or declaration")),
+            };
+            self.recover(result, start);
+        }
+    }
+
+    /// Parse file-level options (e.g., "option go_package = \"github.com/acme/events;events\";")
+    ///
+    /// Standard options are checked for the right value type and may only be
+    /// set once; all options are stored on the file.
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the option to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_file_option(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        let start = self.position;
+        let option = self.parse_option_statement()?;
+
+        let valid = match option.name.as_str() {
+            "java_package" | "java_outer_classname" | "go_package" | "objc_class_prefix"
+            | "csharp_namespace" | "swift_prefix" | "php_class_prefix" | "php_namespace"
+            | "php_metadata_namespace" | "ruby_package" => matches!(option.value, OptionValue::String(_)),
+            "java_multiple_files" | "java_generate_equals_and_hash" | "java_string_check_utf8"
+            | "cc_generic_services" | "java_generic_services" | "py_generic_services"
+            | "deprecated" | "cc_enable_arenas" => matches!(option.value, OptionValue::Boolean(_)),
+            "optimize_for" => matches!(
+                &option.value,
+                OptionValue::Identifier(mode) if matches!(mode.as_str(), "SPEED" | "CODE_SIZE" | "LITE_RUNTIME")
+            ),
+            _ => true,
+        };
+        if !valid {
+            let message = format!("Invalid value for file option '{}'{}", option.name, utf8_problem(&option.value));
+            return Err(self.error_at(start, ErrorCode::InvalidOption, &message));
+        }
+        if builder.current_file.find_option(&option.name).is_some() {
+            let message = format!("File option '{}' is set more than once", option.name);
+            return Err(self.error_at(start, ErrorCode::Duplicate, &message));
+        }
+
+        builder.add_option(option);
+        Ok(())
     }
 
     /// Parse message-level options, particularly DMXP channel options
-    /// 
+    ///
+    /// DMXP options are set one at a time (e.g. `option (dmxp_channel) = "events";`)
+    /// or all at once with `option (dmxp.channel) = { name: "events" buffer_size: 4096 };`.
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add options to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_message_option(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        
+        let start = self.position;
+        let (option, value_tokens) = self.parse_option_statement_with_value()?;
+
         // Check if this is a DMXP option
-        if line.contains("dmxp_") {
+        let settings = self.dmxp_settings(builder, "google.protobuf.MessageOptions", &option, start, value_tokens);
+        if !settings.is_empty() {
             // Get existing DMXP options or create new ones
             let mut dmxp_options = builder.get_dmxp_message_options()
                 .map(|opt| opt.clone())  // Clone the existing options if they exist
@@ -207,117 +391,819 @@ impl ProtoParser {
                     swap_enabled: None,
                     priority: None,
                 });
-            
-            // Handle each type of DMXP option
-            if line.contains("dmxp_channel") {
-                if let Some(channel_name) = extract_string_value(line, "dmxp_channel") {
-                    dmxp_options.channel = Some(channel_name);
-                }
+            for (key, value) in settings {
+                apply_dmxp_message_option(&mut dmxp_options, &key, value);
             }
-            else if line.contains("dmxp_persistent") {
-                if let Some(persistent) = extract_bool_value(line, "dmxp_persistent") {
-                    dmxp_options.persistent = Some(persistent);
-                }
+
+            // Set the updated options back
+            builder.set_dmxp_message_options(dmxp_options);
+        }
+        builder.add_message_option(option);
+        Ok(())
+    }
+
+    /// Bundled declaration of the DMXP option that `option_name` sets on
+    /// `extendee` (e.g. "google.protobuf.MessageOptions"), if it is one
+    ///
+    /// The option is looked up among the extensions declared in the file, then
+    /// among the bundled `dmxp/options.proto` declarations. It is a DMXP option
+    /// only if the declaring extension has the name and type of a bundled one.
+    /// On methods, `(dmxp_timeout_ms)` is still accepted for what is now
+    /// declared as `dmxp_method_timeout_ms`.
+    fn dmxp_option(&self, builder: &AstBuilder, extendee: &str, option_name: &str) -> Option<&'static Extension> {
+        let bundled = bundled::dmxp_options();
+        let legacy_name = match (extendee, option_name) {
+            ("google.protobuf.MethodOptions", "(dmxp_timeout_ms)" | "(dmxp.dmxp_timeout_ms)") => {
+                "(dmxp_method_timeout_ms)"
             }
-            else if line.contains("dmxp_buffer_size") {
-                if let Some(size) = extract_number_value::<u32>(line, "dmxp_buffer_size") {
-                    dmxp_options.buffer_size = Some(size);
-                }
+            _ => option_name,
+        };
+        let extension = builder.current_file
+            .find_option_extension(extendee, option_name)
+            .or_else(|| bundled.find_option_extension(extendee, legacy_name))?;
+        bundled
+            .find_option_extension(extendee, &extension.name)
+            .filter(|declared| declared.field_type == extension.field_type)
+    }
+
+    /// DMXP option fields set by `option`, checked against the bundled declarations
+    ///
+    /// A flat option (e.g. `option (dmxp_buffer_size) = 4096;`) sets one field.
+    /// An aggregate (e.g. `option (dmxp.channel) = { name: "events" };`) sets one
+    /// per entry, named after the matching flat option, and one per element of
+    /// a list (e.g. `channels: ["a", "b"]`). Unknown `dmxp_*` options and fields,
+    /// values of the wrong type and numbers out of range are reported (see
+    /// `with_strict_dmxp`).
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder, for the extensions declared in the file
+    /// * `extendee` - Options message being set (e.g. "google.protobuf.MessageOptions")
+    /// * `option` - The option just parsed
+    /// * `start` - Token index of the option, for error locations
+    /// * `value_tokens` - Indices of the first and last tokens of the option's value
+    ///
+    /// # Returns
+    /// The flat option names (e.g. "dmxp_buffer_size") and values set, in source order
+    fn dmxp_settings<'o>(
+        &mut self,
+        builder: &AstBuilder,
+        extendee: &str,
+        option: &'o ProtoOption,
+        start: usize,
+        value_tokens: RangeInclusive<usize>,
+    ) -> Vec<(String, &'o OptionValue)> {
+        let Some(declared) = self.dmxp_option(builder, extendee, &option.name) else {
+            if builder.current_file.find_option_extension(extendee, &option.name).is_none() {
+                self.check_unknown_dmxp_option(extendee, &option.name, start);
             }
-            else if line.contains("dmxp_wal_enabled") {
-                if let Some(enabled) = extract_bool_value(line, "dmxp_wal_enabled") {
-                    dmxp_options.wal_enabled = Some(enabled);
-                }
+            return Vec::new();
+        };
+        let FieldType::Message(config) = &declared.field_type else {
+            if let Some((code, problem)) = dmxp_value_problem(&declared.field_type, &option.value) {
+                let message = format!("Invalid value for option '{}': {}", option.name, problem);
+                let diagnostic = self.diagnostic_over(value_tokens, code, &message);
+                self.report_dmxp(diagnostic);
             }
-            else if line.contains("dmxp_swap_enabled") {
-                if let Some(enabled) = extract_bool_value(line, "dmxp_swap_enabled") {
-                    dmxp_options.swap_enabled = Some(enabled);
+            return vec![(declared.name.clone(), &option.value)];
+        };
+
+        let OptionValue::Aggregate(entries) = &option.value else {
+            let message = format!("Option '{}' must be set to a message literal", option.name);
+            let diagnostic = self.diagnostic_at(start, ErrorCode::InvalidOption, &message)
+                .with_help(format!("write e.g. 'option {} = {{ ... }};'", option.name));
+            self.report_dmxp(diagnostic);
+            return Vec::new();
+        };
+        let fields = bundled::dmxp_options()
+            .find_message(config)
+            .map(|message| message.fields.as_slice())
+            .unwrap_or_default();
+
+        let mut settings = Vec::new();
+        for entry in entries {
+            let Some(field) = fields.iter().find(|field| field.name == entry.name) else {
+                let message = format!("Unknown field '{}' in option '{}'", entry.name, option.name);
+                let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+                let mut diagnostic = Diagnostic::error(ErrorCode::InvalidOption, message)
+                    .with_span(entry.span.clone(), entry.name.len())
+                    .with_note(format!("dmxp.{} has fields: {}", config, names.join(", ")));
+                if let Some(suggestion) = closest_match(&entry.name, names.iter().copied()) {
+                    diagnostic = diagnostic.with_help(format!("did you mean '{}'?", suggestion));
                 }
-            }
-            else if line.contains("dmxp_priority") {
-                if let Some(priority) = extract_number_value::<u32>(line, "dmxp_priority") {
-                    dmxp_options.priority = Some(priority as u32);
+                self.report_dmxp(diagnostic);
+                continue;
+            };
+            let values = match &entry.value {
+                OptionValue::List(items) if matches!(field.label, FieldLabel::Repeated) => items.iter().collect(),
+                value => vec![value],
+            };
+            for value in values {
+                if let Some((code, problem)) = dmxp_value_problem(&field.field_type, value) {
+                    let message =
+                        format!("Invalid value for field '{}' of option '{}': {}", entry.name, option.name, problem);
+                    let diagnostic = Diagnostic::error(code, message).with_span(entry.span.clone(), entry.name.len());
+                    self.report_dmxp(diagnostic);
                 }
+                settings.push((dmxp_option_key(config, &field.name), value));
             }
-    
-            // Set the updated options back
-            builder.set_dmxp_message_options(dmxp_options);
         }
+        settings
+    }
+
+    /// Report `option_name` if it looks like a DMXP option (e.g. `(dmxp_bufer_size)`)
+    /// but `dmxp/options.proto` declares no such option for `extendee`
+    fn check_unknown_dmxp_option(&mut self, extendee: &str, option_name: &str, start: usize) {
+        let Some(name) = option_name.strip_prefix('(').and_then(|name| name.strip_suffix(')')) else {
+            return;
+        };
+        let name = name.trim_start_matches('.');
+        if !name.starts_with("dmxp_") && !name.starts_with("dmxp.") {
+            return;
+        }
+
+        let bundled = bundled::dmxp_options();
+        let package = if name.starts_with("dmxp.") { "dmxp." } else { "" };
+        let candidates: Vec<String> = bundled.extensions
+            .iter()
+            .filter(|extension| extension.extendee.trim_start_matches('.') == extendee)
+            .map(|extension| format!("{}{}", package, extension.name))
+            .collect();
+        let kind = extendee.trim_start_matches("google.protobuf.");
+        let message = format!("Unknown DMXP option '{}' in {}", option_name, kind);
+        let mut diagnostic = self.diagnostic_at(start + 1, ErrorCode::InvalidOption, &message);
+        if let Some(suggestion) = closest_match(name, candidates.iter().map(String::as_str)) {
+            diagnostic = diagnostic.with_help(format!("did you mean '({})'?", suggestion));
+        }
+        if let Some(other) = bundled.extensions.iter().find(|extension| extension.name == name) {
+            diagnostic = diagnostic.with_note(format!(
+                "'{}' is declared for {}",
+                option_name,
+                other.extendee.trim_start_matches('.').trim_start_matches("google.protobuf.")
+            ));
+        }
+        self.report_dmxp(diagnostic);
+    }
+
+    /// Record a problem with a DMXP option, as an error in strict mode and a warning otherwise
+    fn report_dmxp(&mut self, diagnostic: Diagnostic) {
+        let severity = if self.strict_dmxp { Severity::Error } else { Severity::Warning };
+        self.diagnostics.push(Diagnostic { severity, ..diagnostic });
+    }
+
+    /// Parse an extend block (e.g., "extend google.protobuf.MessageOptions { optional string topic = 50001; }")
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the extensions to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_extend(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        self.expect_keyword("extend")?;
+        let extendee = self.parse_type_name()?;
+        self.expect_symbol('{')?;
+
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(());
+            }
+            if self.at_end() {
+                return Err(self.error(ErrorCode::UnexpectedToken, "Unexpected end of file while parsing extend"));
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            let start = self.position;
+            let result = self.parse_extension(builder, &extendee);
+            self.recover(result, start);
+        }
+    }
+
+    /// Parse a single field of an extend block
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the extension to
+    /// * `extendee` - The message being extended
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_extension(&mut self, builder: &mut AstBuilder, extendee: &str) -> Result<()> {
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        let is_proto2 = builder.current_file.syntax == "proto2";
+        let is_editions = builder.current_file.edition.is_some();
+        let label_index = self.position;
+        let explicit_label = self.parse_field_label();
+        if matches!(explicit_label, Some(FieldLabel::Required)) {
+            return Err(self.error_at(label_index, ErrorCode::InvalidLabel, "Extensions cannot be required"));
+        }
+        if is_editions {
+            self.check_editions_label(explicit_label.as_ref(), label_index)?;
+        }
+        if self.is_group() {
+            return Err(self.error(ErrorCode::InvalidType, "Groups are not supported in extend blocks"));
+        }
+        if self.is_map_type() {
+            return Err(self.error(ErrorCode::InvalidType, "Extensions cannot be map fields"));
+        }
+        if is_proto2 && explicit_label.is_none() {
+            return Err(self.error(ErrorCode::InvalidLabel, "Extensions in proto2 must be 'optional' or 'repeated'"));
+        }
+
+        let type_name = self.parse_type_name()?;
+        let field_type = self.parse_field_type(&type_name);
+        let name = self.expect_ident()?;
+        self.expect_symbol('=')?;
+        let number_index = self.position;
+        let number = self.parse_field_number()?;
+        let options_start = self.position;
+        let (options, default_value) = self.parse_field_options()?;
+        self.expect_symbol(';')?;
+        comments.trailing = self.trailing_comment(self.position - 1);
+
+        if let Some(existing) = builder.current_file
+            .extensions
+            .iter()
+            .find(|ext| ext.extendee == extendee && ext.number == number)
+        {
+            return Err(self.error_at(
+                number_index,
+                ErrorCode::Duplicate,
+                &format!("Extension number {} on '{}' is already used by '{}'", number, extendee, existing.name),
+            ));
+        }
+
+        // Reuse the field checks for options and defaults
+        let field = Field {
+            name,
+            field_type,
+            resolved_type: None,
+            number,
+            label: explicit_label.unwrap_or(FieldLabel::Optional),
+            presence: FieldPresence::Explicit,
+            options,
+            features: None,
+            default_value,
+            comments,
+            span,
+        };
+        self.validate_field_options(&field, is_editions, options_start)?;
+        self.validate_default_value(&field, is_proto2 || is_editions, options_start)?;
+
+        log::debug!("Parsed extension {} = {} of {}", field.name, field.number, extendee);
+
+        builder.add_extension(Extension {
+            name: field.name,
+            extendee: extendee.to_string(),
+            field_type: field.field_type,
+            resolved_type: None,
+            number: field.number,
+            label: field.label,
+            scope: builder.current_scope(),
+            options: field.options,
+            comments: field.comments,
+            span: field.span,
+        });
         Ok(())
     }
-    
+
+
+    /// Parse oneof declarations (e.g., "oneof payload { string text = 1; bytes raw = 2; }")
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the oneof to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_oneof(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        let mut comments = self.leading_comments();
+        self.expect_keyword("oneof")?;
+        let name = self.expect_ident()?;
+        comments.trailing = self.trailing_comment(self.position);
+        self.expect_symbol('{')?;
+        builder.start_oneof(name, comments);
+
+        let result = loop {
+            if self.eat_symbol('}') {
+                break Ok(());
+            }
+            if self.at_end() {
+                break Err(self.error(ErrorCode::UnexpectedToken, "Unexpected end of file while parsing oneof"));
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+
+            let start = self.position;
+            let result = match self.peek_ident().as_deref() {
+                Some("option") => self.parse_option_statement().map(|option| builder.add_oneof_option(option)),
+                Some(label @ ("repeated" | "optional" | "required")) => {
+                    Err(self.error(ErrorCode::InvalidLabel, &format!("Fields in oneof cannot be '{}'", label)))
+                }
+                Some("map") if self.peek_ahead(1).kind == TokenKind::Symbol('<') => {
+                    Err(self.error(ErrorCode::InvalidType, "Map fields are not allowed in oneof"))
+                }
+                Some(_) => self.parse_field(builder),
+                None if self.is_symbol('.') => self.parse_field(builder),
+                None => Err(self.unexpected("a oneof field")),
+            };
+            self.recover(result, start);
+        };
+
+        builder.end_oneof();
+        result
+    }
 
     /// Parse message fields (e.g., "string user_id = 1;")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the field to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_field(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        let parts: Vec<&str> = line.split_whitespace().collect();
-    
-        if parts.len() < 3 {
-            return Ok(()); // not enough tokens to form a field
-        }
-    
-        // Handle "repeated" keyword properly
-        let (label, field_type_token, name_token, number_token) = if parts[0] == "repeated" {
-            if parts.len() < 5 {
-                return Err(anyhow::anyhow!("Malformed repeated field: {}", line));
-            }
-            // repeated <type> <name> = <num>;
-            (
-                FieldLabel::Repeated,
-                parts[1], // type
-                parts[2], // name
-                &parts[4], // "3;"
-            )
+        // [label] <type> <name> = <num> [<options>];
+        // map<<key>, <value>> <name> = <num> [<options>];
+        // [label] group <Name> = <num> [<options>] { ... }
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        let is_proto2 = builder.current_file.syntax == "proto2";
+        let is_editions = builder.current_file.edition.is_some();
+        let label_index = self.position;
+        let explicit_label = self.parse_field_label();
+        if is_editions {
+            self.check_editions_label(explicit_label.as_ref(), label_index)?;
+        } else if matches!(explicit_label, Some(FieldLabel::Required)) && !is_proto2 {
+            let diagnostic = self
+                .diagnostic_at(label_index, ErrorCode::InvalidLabel, "Required fields are not allowed in proto3")
+                .with_help("remove 'required'; proto3 fields are optional by default");
+            return Err(ParseError::from(diagnostic).into());
+        }
+        if self.is_group() {
+            return self.parse_group(builder, explicit_label, comments, span);
+        }
+
+        let presence = declared_presence(
+            &builder.current_file.syntax,
+            explicit_label.as_ref(),
+            builder.current_oneof.is_some(),
+        );
+        let mut label = explicit_label.clone().unwrap_or(FieldLabel::Optional);
+        let field_type = if self.is_map_type() {
+            if explicit_label.is_some() {
+                let message = format!("Map fields cannot be {}", label_keyword(&label));
+                return Err(self.error_at(label_index, ErrorCode::InvalidLabel, &message));
+            }
+            // Like protoc, map entries are modelled as a repeated field
+            label = FieldLabel::Repeated;
+            self.parse_map_type()?
         } else {
-            // <type> <name> = <num>;
-            if parts.len() < 4 {
-                return Err(anyhow::anyhow!("Malformed field: {}", line));
-            }
-            (
-                FieldLabel::Optional,
-                parts[0], // type
-                parts[1], // name
-                &parts[3], // "3;"
-            )
+            if is_proto2 && explicit_label.is_none() && builder.current_oneof.is_none() {
+                let message = "Fields in proto2 must be 'required', 'optional' or 'repeated'";
+                return Err(self.error(ErrorCode::InvalidLabel, message));
+            }
+            let type_name = self.parse_type_name()?;
+            self.parse_field_type(&type_name)
         };
-    
-        // Parse the field number
-        let number_str = number_token.trim_end_matches(';');
-        if number_str.is_empty() {
-            return Err(anyhow::anyhow!("Empty field number in line: {}", line));
-        }
-    
-        let number = number_str.parse::<i32>()
-            .map_err(|e| anyhow::anyhow!("Invalid field number '{}': {}", number_str, e))?;
-    
-        let field_type = self.parse_field_type(field_type_token);
-    
+        let name_index = self.position;
+        let name = self.expect_ident()?;
+        self.expect_symbol('=')?;
+        let number_index = self.position;
+        let number = self.parse_field_number()?;
+        self.check_field_duplicate(builder, &name, name_index, number, number_index)?;
+        self.check_field_reservation(builder, &name, name_index, number, number_index)?;
+        let options_start = self.position;
+        let (options, default_value) = self.parse_field_options()?;
+        self.expect_symbol(';')?;
+        comments.trailing = self.trailing_comment(self.position - 1);
+
         let field = Field {
-            name: name_token.to_string(),
+            name,
             field_type,
+            resolved_type: None,
             number,
             label,
-            options: Vec::new(),
+            presence,
+            options,
+            features: None,
+            default_value,
+            comments,
+            span,
+        };
+        self.validate_field_options(&field, is_editions, options_start)?;
+        self.validate_default_value(&field, is_proto2 || is_editions, options_start)?;
+
+        log::debug!("Parsed field {} = {}", field.name, field.number);
+
+        builder.add_field(field);
+        Ok(())
+    }
+
+    /// Consume a field label (`required`, `optional` or `repeated`), if any
+    ///
+    /// Labels are only keywords when a type and name follow, so a message
+    /// named e.g. `optional` still parses as a field type.
+    fn parse_field_label(&mut self) -> Option<FieldLabel> {
+        let label = match self.peek_ident().as_deref() {
+            Some("required") => FieldLabel::Required,
+            Some("optional") => FieldLabel::Optional,
+            Some("repeated") => FieldLabel::Repeated,
+            _ => return None,
+        };
+        let is_keyword = self.type_follows_keyword() && self.peek_ahead(2).kind != TokenKind::Symbol('=');
+        if !is_keyword {
+            return None;
+        }
+        self.advance();
+        Some(label)
+    }
+
+    /// Reject the `required` and `optional` labels, which editions replace
+    /// with `features.field_presence`
+    fn check_editions_label(&self, label: Option<&FieldLabel>, label_index: usize) -> Result<()> {
+        let (message, help) = match label {
+            Some(FieldLabel::Required) => (
+                "Required fields are not allowed in editions",
+                "use [features.field_presence = LEGACY_REQUIRED] instead",
+            ),
+            Some(FieldLabel::Optional) => (
+                "The 'optional' label is not allowed in editions",
+                "remove 'optional'; fields have explicit presence unless features.field_presence says otherwise",
+            ),
+            _ => return Ok(()),
+        };
+        let diagnostic = self.diagnostic_at(label_index, ErrorCode::InvalidLabel, message).with_help(help);
+        Err(ParseError::from(diagnostic).into())
+    }
+
+    /// Whether the current tokens start a group (e.g., "group Result = 1 { ... }")
+    fn is_group(&self) -> bool {
+        self.peek_ident().as_deref() == Some("group")
+            && matches!(self.peek_ahead(1).kind, TokenKind::Ident(_))
+            && self.peek_ahead(2).kind == TokenKind::Symbol('=')
+    }
+
+    /// Parse a proto2 group (e.g., "repeated group Result = 1 { required string url = 2; }")
+    ///
+    /// A group declares a nested message named after the group together with a
+    /// field of that type whose name is the lowercased group name.
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the nested message and field to
+    /// * `label` - The label written before `group`, if any
+    /// * `comments` - Comments attached to the group
+    /// * `span` - Where the group declaration starts
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_group(
+        &mut self,
+        builder: &mut AstBuilder,
+        label: Option<FieldLabel>,
+        mut comments: Comments,
+        span: Span,
+    ) -> Result<()> {
+        if builder.current_file.edition.is_some() {
+            let diagnostic = self
+                .diagnostic_at(self.position, ErrorCode::InvalidType, "Groups are not supported in editions")
+                .with_help("use a message field with [features.message_encoding = DELIMITED] instead");
+            return Err(ParseError::from(diagnostic).into());
+        }
+        if builder.current_file.syntax != "proto2" {
+            let diagnostic = self
+                .diagnostic_at(self.position, ErrorCode::InvalidType, "Groups are not supported in proto3")
+                .with_help("use a nested message instead");
+            return Err(ParseError::from(diagnostic).into());
+        }
+        if label.is_none() && builder.current_oneof.is_none() {
+            let message = "Groups in proto2 must be 'required', 'optional' or 'repeated'";
+            return Err(self.error(ErrorCode::InvalidLabel, message));
+        }
+        self.expect_keyword("group")?;
+
+        let name_index = self.position;
+        let type_name = self.expect_ident()?;
+        if !type_name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            let message = format!("Group name '{}' must start with a capital letter", type_name);
+            return Err(self.error_at(name_index, ErrorCode::InvalidType, &message));
+        }
+        let name = type_name.to_lowercase();
+        self.expect_symbol('=')?;
+        let number_index = self.position;
+        let number = self.parse_field_number()?;
+        self.check_field_duplicate(builder, &name, name_index, number, number_index)?;
+        self.check_field_reservation(builder, &name, name_index, number, number_index)?;
+        let options = self.parse_compact_options()?;
+        comments.trailing = self.trailing_comment(self.position);
+
+        // The body is parsed as a nested message; set aside any enclosing oneof
+        // so the group's own fields are not added to it
+        let oneof = builder.current_oneof.take();
+        builder.start_message(type_name.clone());
+        builder.set_message_comments(comments.clone());
+        builder.set_message_span(span.clone());
+        let result = self.parse_message_body(builder);
+        builder.end_message();
+        builder.current_oneof = oneof;
+        result?;
+
+        let presence = declared_presence(&builder.current_file.syntax, label.as_ref(), false);
+        let field = Field {
+            name,
+            field_type: FieldType::Message(type_name),
+            resolved_type: None,
+            number,
+            label: label.unwrap_or(FieldLabel::Optional),
+            presence,
+            options,
+            features: None,
             default_value: None,
+            comments,
+            span,
         };
-    
-        println!("DEBUG: Parsed field successfully → {:?} = {}", field.name, field.number);
-    
+        log::debug!("Parsed group {} = {}", field.name, field.number);
+
         builder.add_field(field);
         Ok(())
     }
-    
+
+    /// Check a field's name and number against the fields already declared in
+    /// the current message, including the members of its oneofs
+    fn check_field_duplicate(
+        &self,
+        builder: &AstBuilder,
+        name: &str,
+        name_index: usize,
+        number: i32,
+        number_index: usize,
+    ) -> Result<()> {
+        let Some(message) = builder.current_message.as_ref() else {
+            return Ok(());
+        };
+        let oneof_fields = builder.current_oneof.iter().flat_map(|o| o.fields.iter());
+        for field in message.all_fields().chain(oneof_fields) {
+            if field.name == name {
+                return Err(self.error_at(
+                    name_index,
+                    ErrorCode::Duplicate,
+                    &format!("Field '{}' is already defined in message '{}'", name, message.name),
+                ));
+            }
+            if field.number == number {
+                return Err(self.error_at(
+                    number_index,
+                    ErrorCode::Duplicate,
+                    &format!(
+                        "Field number {} of '{}' is already used by field '{}' in message '{}'",
+                        number, name, field.name, message.name
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a field's name and number against the reservations of the current message
+    fn check_field_reservation(
+        &self,
+        builder: &AstBuilder,
+        name: &str,
+        name_index: usize,
+        number: i32,
+        number_index: usize,
+    ) -> Result<()> {
+        let Some(message) = builder.current_message.as_ref() else {
+            return Ok(());
+        };
+        if message.is_reserved_name(name) {
+            return Err(self.error_at(
+                name_index,
+                ErrorCode::Reserved,
+                &format!("Field name '{}' is reserved in message '{}'", name, message.name),
+            ));
+        }
+        if message.is_reserved_number(number) {
+            return Err(self.error_at(
+                number_index,
+                ErrorCode::Reserved,
+                &format!("Field '{}' uses reserved field number {} in message '{}'", name, number, message.name),
+            ));
+        }
+        if message.is_extension_number(number) {
+            return Err(self.error_at(
+                number_index,
+                ErrorCode::InvalidNumber,
+                &format!("Field '{}' uses number {}, which is an extension number of message '{}'", name, number, message.name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parse a field's bracketed options, separating out `default`, which is
+    /// stored as the field's default value rather than as an option
+    fn parse_field_options(&mut self) -> Result<(Vec<ProtoOption>, Option<OptionValue>)> {
+        let options_start = self.position;
+        let (defaults, options): (Vec<ProtoOption>, Vec<ProtoOption>) = self
+            .parse_compact_options()?
+            .into_iter()
+            .partition(|option| option.name == "default");
+        if defaults.len() > 1 {
+            return Err(self.error_at(options_start, ErrorCode::Duplicate, "Option 'default' is set more than once"));
+        }
+        Ok((options, defaults.into_iter().next().map(|option| option.value)))
+    }
+
+    /// Check that a `[default = ...]` value is allowed on the field, matches its type
+    /// and, for integer fields, fits its range
+    ///
+    /// Enum defaults can only be checked once type references are resolved,
+    /// so fields of message or enum type just need an identifier here.
+    fn validate_default_value(&self, field: &Field, allows_defaults: bool, options_start: usize) -> Result<()> {
+        let Some(value) = &field.default_value else {
+            return Ok(());
+        };
+        if !allows_defaults {
+            let message = "Explicit default values are not allowed in proto3";
+            let diagnostic = self
+                .diagnostic_at(options_start, ErrorCode::InvalidDefault, message)
+                .with_note("proto3 fields default to the zero value of their type");
+            return Err(ParseError::from(diagnostic).into());
+        }
+        if matches!(field.label, FieldLabel::Repeated) {
+            return Err(self.error_at(
+                options_start,
+                ErrorCode::InvalidDefault,
+                &format!("Repeated field '{}' cannot have a default value", field.name),
+            ));
+        }
+
+        let number = match value {
+            OptionValue::Int(n) => Some(i128::from(*n)),
+            OptionValue::UInt(n) => Some(i128::from(*n)),
+            _ => None,
+        };
+        if let Some(number) = number
+            && let Some((type_name, min, max)) = integer_range(&field.field_type)
+            && !(min..=max).contains(&number)
+        {
+            return Err(self.error_at(
+                options_start,
+                ErrorCode::InvalidNumber,
+                &format!(
+                    "Invalid default value for field '{}': {} is out of range for {} ({} to {})",
+                    field.name, number, type_name, min, max
+                ),
+            ));
+        }
+
+        let valid = matches!(
+            (&field.field_type, value),
+            (FieldType::String | FieldType::Bytes, OptionValue::String(_))
+                | (FieldType::Bytes, OptionValue::Bytes(_))
+                | (FieldType::Bool, OptionValue::Boolean(_))
+                | (
+                    FieldType::Float | FieldType::Double,
+                    OptionValue::Number(_) | OptionValue::Int(_) | OptionValue::UInt(_)
+                )
+                | (
+                    FieldType::Int32 | FieldType::Int64 | FieldType::Sint32 | FieldType::Sint64
+                        | FieldType::Sfixed32 | FieldType::Sfixed64,
+                    OptionValue::Int(_) | OptionValue::UInt(_)
+                )
+                | (
+                    FieldType::Uint32 | FieldType::Uint64 | FieldType::Fixed32 | FieldType::Fixed64,
+                    OptionValue::UInt(_)
+                )
+                | (FieldType::Message(_) | FieldType::Enum(_), OptionValue::Identifier(_))
+        );
+        if !valid {
+            return Err(self.error_at(
+                options_start,
+                ErrorCode::InvalidDefault,
+                &format!("Invalid default value for field '{}'{}", field.name, utf8_problem(value)),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the standard field options (`deprecated`, `json_name`, `packed`)
+    /// for the right value type and a field they can apply to
+    ///
+    /// Editions replace `packed` with `features.repeated_field_encoding`.
+    fn validate_field_options(&self, field: &Field, is_editions: bool, options_start: usize) -> Result<()> {
+        if is_editions && field.find_option("packed").is_some() {
+            let diagnostic = self
+                .diagnostic_at(options_start, ErrorCode::InvalidOption, "Option 'packed' is not allowed in editions")
+                .with_help("use [features.repeated_field_encoding = EXPANDED] to turn off packed encoding");
+            return Err(ParseError::from(diagnostic).into());
+        }
+        for option in &field.options {
+            let valid = match option.name.as_str() {
+                "deprecated" | "packed" => matches!(option.value, OptionValue::Boolean(_)),
+                "json_name" => matches!(option.value, OptionValue::String(_)),
+                _ => true,
+            };
+            if !valid {
+                return Err(self.error_at(
+                    options_start,
+                    ErrorCode::InvalidOption,
+                    &format!(
+                        "Invalid value for option '{}' on field '{}'{}",
+                        option.name,
+                        field.name,
+                        utf8_problem(&option.value)
+                    ),
+                ));
+            }
+        }
+
+        // The JSON key is pasted into serde attributes and Go struct tags
+        if let Some(OptionValue::String(json_name)) = field.find_option("json_name")
+            && json_name.contains(['"', '\\', '`'])
+        {
+            return Err(self.error_at(
+                options_start,
+                ErrorCode::InvalidOption,
+                &format!("Option 'json_name' on field '{}' may not contain '\"', '\\' or '`'", field.name),
+            ));
+        }
+
+        // Message types may still turn out to be enums, which are packable
+        let packable = matches!(field.label, FieldLabel::Repeated)
+            && !matches!(field.field_type, FieldType::String | FieldType::Bytes | FieldType::Map(_, _));
+        if field.find_option("packed").is_some() && !packable {
+            return Err(self.error_at(
+                options_start,
+                ErrorCode::InvalidOption,
+                &format!("Option 'packed' is only allowed on repeated scalar numeric fields, not on '{}'", field.name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the current tokens start a `map<K, V>` type
+    fn is_map_type(&self) -> bool {
+        self.peek_ident().as_deref() == Some("map") && self.peek_ahead(1).kind == TokenKind::Symbol('<')
+    }
+
+    /// Parse a map type (e.g., "map<string, int64>")
+    ///
+    /// Only integral and string key types are allowed, as in the protobuf spec.
+    fn parse_map_type(&mut self) -> Result<FieldType> {
+        self.expect_keyword("map")?;
+        self.expect_symbol('<')?;
+
+        let key_name = self.parse_type_name()?;
+        let key_type = self.parse_field_type(&key_name);
+        match key_type {
+            FieldType::Int32 | FieldType::Int64 | FieldType::Uint32 | FieldType::Uint64
+            | FieldType::Sint32 | FieldType::Sint64 | FieldType::Fixed32 | FieldType::Fixed64
+            | FieldType::Sfixed32 | FieldType::Sfixed64 | FieldType::Bool | FieldType::String => {}
+            _ => {
+                let message = format!("Invalid map key type '{}'", key_name);
+                let diagnostic = self.diagnostic_at(self.position - 1, ErrorCode::InvalidType, &message)
+                    .with_help("map keys must be an integral or string type");
+                return Err(ParseError::from(diagnostic).into());
+            }
+        }
+
+        self.expect_symbol(',')?;
+        if self.is_map_type() {
+            return Err(self.error(ErrorCode::InvalidType, "Map values cannot themselves be maps"));
+        }
+        let value_name = self.parse_type_name()?;
+        let value_type = self.parse_field_type(&value_name);
+        self.expect_symbol('>')?;
+
+        Ok(FieldType::Map(Box::new(key_type), Box::new(value_type)))
+    }
+
+    /// Parse a field number, which must fit in a positive i32 and lie
+    /// outside the numbers reserved for the protobuf implementation
+    fn parse_field_number(&mut self) -> Result<i32> {
+        let number = self.parse_int()?;
+        let number = i32::try_from(number)
+            .ok()
+            .filter(|n| (1..=MAX_FIELD_NUMBER).contains(n))
+            .ok_or_else(|| self.error(ErrorCode::InvalidNumber, &format!("Invalid field number '{}'", number)))?;
+        if IMPLEMENTATION_RESERVED.contains(&number) {
+            return Err(self.error(
+                ErrorCode::InvalidNumber,
+                &format!(
+                    "Field number {} is reserved for the protobuf implementation ({} to {})",
+                    number,
+                    IMPLEMENTATION_RESERVED.start(),
+                    IMPLEMENTATION_RESERVED.end()
+                ),
+            ));
+        }
+        Ok(number)
+    }
+
     /// Parse field types from string representation to FieldType enum
-    /// 
+    ///
     /// # Arguments
     /// * `type_str` - The string representation of the field type
-    /// 
+    ///
     /// # Returns
     /// * `FieldType` - The parsed field type
     fn parse_field_type(&self, type_str: &str) -> FieldType {
@@ -342,277 +1228,1301 @@ impl ProtoParser {
     }
 
     /// Parse service declarations (e.g., "service UserService { ... }")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the service to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_service(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        if let Some(name) = line.strip_prefix("service ").and_then(|s| s.split_whitespace().next()) {
-            let name = name.trim_end_matches('{');
-            builder.start_service(name.to_string());
-            
-            // Parse service body including methods and options
-            self.parse_service_body(builder)?;
-            builder.end_service();
-        }
-        Ok(())
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        self.expect_keyword("service")?;
+        let name = self.expect_ident()?;
+        comments.trailing = self.trailing_comment(self.position);
+        builder.start_service(name);
+        builder.set_service_comments(comments);
+        builder.set_service_span(span);
+
+        // Parse service body including methods and options
+        let result = self.parse_service_body(builder);
+        builder.end_service();
+        result
     }
 
     /// Parse the body of a service declaration, including methods and options
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add service elements to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_service_body(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let mut brace_count = 1;
-        self.current_line += 1;
-        
-        while self.current_line < self.lines.len() && brace_count > 0 {
-            let line = self.lines[self.current_line].trim();
-            
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with("//") {
-                self.current_line += 1;
-                continue;
+        self.expect_symbol('{')?;
+
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(());
             }
-            
-            // Track brace nesting
-            if line.contains('{') {
-                brace_count += line.matches('{').count();
+            if self.at_end() {
+                return Err(self.error(ErrorCode::UnexpectedToken, "Unexpected end of file while parsing service"));
             }
-            if line.contains('}') {
-                brace_count -= line.matches('}').count();
-                if brace_count == 0 {
-                    break;
-                }
-            }
-            
-            // Parse service options (like DMXP channel options)
-            if line.starts_with("option") {
-                self.parse_service_option(builder)?;
-            }
-            // Parse RPC methods
-            else if line.starts_with("rpc") {
-                self.parse_method(builder)?;
+            if self.eat_symbol(';') {
+                continue;
             }
-            
-            self.current_line += 1;
+
+            let start = self.position;
+            let result = match self.peek_ident().as_deref() {
+                // Parse service options (like DMXP channel options)
+                Some("option") => self.parse_service_option(builder),
+                // Parse RPC methods
+                Some("rpc") => self.parse_method(builder),
+                _ => Err(self.unexpected("'option' or 'rpc'")),
+            };
+            self.recover(result, start);
         }
-        Ok(())
     }
 
     /// Parse service-level options, particularly DMXP channel options
-    /// 
+    ///
     /// This function is production-ready and handles multiple DMXP channel options
     /// by collecting them and setting them as a complete list on the service.
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add options to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_service_option(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        
-        // Parse DMXP channels option - handle multiple channel declarations
-        if line.contains("dmxp_channels") {
-            if let Some(channel_name) = extract_string_value(line, "dmxp_channels") {
-                // Get existing service options or create new ones
-                let mut existing_options = builder.current_service
-                    .as_ref()
-                    .and_then(|s| s.dmxp_options.clone())
-                    .unwrap_or_else(|| DmxpServiceOptions {
-                        channels: Vec::new(),
-                        timeout_ms: None,
-                        retry_count: None,
-                    });
-                
-                // Add the new channel to the existing list
-                existing_options.channels.push(channel_name);
-                
-                // Set the updated options back on the service
-                builder.set_dmxp_service_options(existing_options);
-            }
-        }
-        
-        // Parse other service options (timeout, retry count, etc.)
-        if line.contains("dmxp_timeout_ms") {
-            if let Some(timeout_str) = extract_string_value(line, "dmxp_timeout_ms") {
-                if let Ok(timeout_ms) = timeout_str.parse::<u32>() {
-                    let mut existing_options = builder.current_service
-                        .as_ref()
-                        .and_then(|s| s.dmxp_options.clone())
-                        .unwrap_or_else(|| DmxpServiceOptions {
-                            channels: Vec::new(),
-                            timeout_ms: None,
-                            retry_count: None,
-                        });
-                    
-                    existing_options.timeout_ms = Some(timeout_ms);
-                    builder.set_dmxp_service_options(existing_options);
-                }
-            }
-        }
-        
-        if line.contains("dmxp_retry_count") {
-            if let Some(retry_str) = extract_string_value(line, "dmxp_retry_count") {
-                if let Ok(retry_count) = retry_str.parse::<u32>() {
-                    let mut existing_options = builder.current_service
-                        .as_ref()
-                        .and_then(|s| s.dmxp_options.clone())
-                        .unwrap_or_else(|| DmxpServiceOptions {
-                            channels: Vec::new(),
-                            timeout_ms: None,
-                            retry_count: None,
-                        });
-                    
-                    existing_options.retry_count = Some(retry_count);
-                    builder.set_dmxp_service_options(existing_options);
-                }
+        let start = self.position;
+        let (option, value_tokens) = self.parse_option_statement_with_value()?;
+        let settings = self.dmxp_settings(builder, "google.protobuf.ServiceOptions", &option, start, value_tokens);
+
+        if !settings.is_empty() {
+            // Get existing service options or create new ones
+            let mut existing_options = builder.current_service
+                .as_ref()
+                .and_then(|s| s.dmxp_options.clone())
+                .unwrap_or_else(|| DmxpServiceOptions {
+                    channels: Vec::new(),
+                    timeout_ms: None,
+                    retry_count: None,
+                });
+            for (key, value) in settings {
+                apply_dmxp_service_option(&mut existing_options, &key, value);
             }
+
+            // Set the updated options back on the service
+            builder.set_dmxp_service_options(existing_options);
         }
-        
+        builder.add_service_option(option);
         Ok(())
     }
 
     /// Parse RPC method declarations (e.g., "rpc GetUser(GetUserRequest) returns (GetUserResponse);")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the method to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_method(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let mut line = self.lines[self.current_line].trim().to_string();
-    
-        // Strip inline comments
-        if let Some(idx) = line.find("//") {
-            line.truncate(idx);
-        }
-        line = line.trim_end_matches(';').trim().to_string();
-    
-        // Ensure starts with rpc
-        if !line.starts_with("rpc ") {
-            return Ok(());
+        // rpc <name> ( [stream] <input> ) returns ( [stream] <output> ) ( ";" | "{" ... "}" )
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        self.expect_keyword("rpc")?;
+        let name = self.expect_ident()?;
+
+        self.expect_symbol('(')?;
+        let client_streaming = self.eat_stream_keyword();
+        let input_type = self.parse_type_name()?;
+        self.expect_symbol(')')?;
+
+        self.expect_keyword("returns")?;
+
+        self.expect_symbol('(')?;
+        let server_streaming = self.eat_stream_keyword();
+        let output_type = self.parse_type_name()?;
+        self.expect_symbol(')')?;
+
+        comments.trailing = self.trailing_comment(self.position);
+        let mut method = Method {
+            name,
+            input_type,
+            output_type,
+            resolved_input_type: None,
+            resolved_output_type: None,
+            client_streaming,
+            server_streaming,
+            options: Vec::new(),
+            dmxp_options: None,
+            comments,
+            span,
+        };
+        if self.is_symbol('{') {
+            self.parse_method_body(builder, &mut method)?;
+        } else {
+            self.expect_symbol(';')?;
         }
-    
-        // Use regex to be fully spacing-tolerant
-        // Matches: rpc <name>(<input>)returns(<output>)
-        let re = Regex::new(
-            r"^rpc\s+([A-Za-z_]\w*)\s*\(\s*([A-Za-z_]\w*)\s*\)\s*returns\s*\(\s*([A-Za-z_]\w*)\s*\)"
-        )?;
-    
-        if let Some(caps) = re.captures(&line) {
-            let method = Method {
-                name: caps[1].to_string(),
-                input_type: caps[2].to_string(),
-                output_type: caps[3].to_string(),
-                options: Vec::new(),
-                dmxp_options: None,
+
+        log::debug!(
+            "Parsed RPC -> name: {}, input: {}{}, output: {}{}",
+            method.name,
+            if method.client_streaming { "stream " } else { "" },
+            method.input_type,
+            if method.server_streaming { "stream " } else { "" },
+            method.output_type
+        );
+        builder.add_method(method);
+
+        Ok(())
+    }
+
+    /// Parse the option block of an RPC (e.g., "{ option (dmxp_method_timeout_ms) = 50; }")
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder, used to look up declared options
+    /// * `method` - The method the options belong to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_method_body(&mut self, builder: &AstBuilder, method: &mut Method) -> Result<()> {
+        self.expect_symbol('{')?;
+
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(());
+            }
+            if self.at_end() {
+                return Err(self.error(ErrorCode::UnexpectedToken, "Unexpected end of file while parsing rpc"));
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+
+            let start = self.position;
+            let result = match self.peek_ident().as_deref() {
+                Some("option") => self.parse_method_option(builder, method),
+                _ => Err(self.unexpected("'option'")),
             };
-            println!(
-                "DEBUG: Parsed RPC -> name: {}, input: {}, output: {}",
-                method.name, method.input_type, method.output_type
-            );
-            builder.add_method(method);
-        } else {
-            return Err(anyhow::anyhow!("Invalid RPC syntax: {}", line));
+            self.recover(result, start);
         }
-    
+    }
+
+    /// Parse a single RPC option, filling in the method's DMXP options
+    ///
+    /// Recognized options are `dmxp_method_channel` (string),
+    /// `dmxp_method_timeout_ms` (number, also accepted as `dmxp_timeout_ms`)
+    /// and `dmxp_async` (bool); every option is also kept in `method.options`.
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder, used to look up declared options
+    /// * `method` - The method to add the option to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_method_option(&mut self, builder: &AstBuilder, method: &mut Method) -> Result<()> {
+        let start = self.position;
+        let (option, value_tokens) = self.parse_option_statement_with_value()?;
+        let settings = self.dmxp_settings(builder, "google.protobuf.MethodOptions", &option, start, value_tokens);
+
+        if !settings.is_empty() {
+            let dmxp_options = method.dmxp_options.get_or_insert(DmxpMethodOptions {
+                channel: None,
+                timeout_ms: None,
+                is_async: None,
+            });
+            for (key, value) in settings {
+                apply_dmxp_method_option(dmxp_options, &key, value);
+            }
+        }
+
+        method.options.push(option);
         Ok(())
-    }    
+    }
+
+    /// Consume a `stream` qualifier in an RPC signature
+    ///
+    /// `stream` is only a keyword when another type name follows it, so a
+    /// message that is itself called `stream` still parses as a type.
+    fn eat_stream_keyword(&mut self) -> bool {
+        let is_keyword = self.peek_ident().as_deref() == Some("stream") && self.type_follows_keyword();
+        if is_keyword {
+            self.advance();
+        }
+        is_keyword
+    }
 
     /// Parse enum declarations (e.g., "enum OrderStatus { ... }")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the enum to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_enum(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        if let Some(name) = line.strip_prefix("enum ").and_then(|s| s.split_whitespace().next()) {
-            let name = name.trim_end_matches('{');
-            builder.start_enum(name.to_string());
-            
-            // Parse enum body including values
-            self.parse_enum_body(builder)?;
-            builder.end_enum();
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        self.expect_keyword("enum")?;
+        let name = self.expect_ident()?;
+        comments.trailing = self.trailing_comment(self.position);
+        builder.start_enum(name);
+        builder.set_enum_comments(comments);
+        builder.set_enum_span(span);
+
+        // Parse enum body including values
+        let result = self.parse_enum_body(builder);
+        builder.end_enum();
+        result
+    }
+
+    /// Check an enum once its body is parsed: proto3 enums must start at zero,
+    /// and values may only share a number when `allow_alias` is set
+    ///
+    /// # Arguments
+    /// * `enum_def` - The enum to check
+    /// * `value_indices` - Token index of each value's name, for error locations
+    /// * `is_proto3` - Whether the file uses proto3 syntax
+    fn validate_enum(&self, enum_def: &Enum, value_indices: &[usize], is_proto3: bool) -> Result<()> {
+        if let (true, Some(first)) = (is_proto3, enum_def.values.first())
+            && first.number != 0
+        {
+            let message = format!("The first value of enum '{}' must be zero in proto3", enum_def.name);
+            let diagnostic = self.diagnostic_at(value_indices[0], ErrorCode::InvalidEnum, &message)
+                .with_note("the first value is the enum's default");
+            return Err(ParseError::from(diagnostic).into());
+        }
+
+        let mut has_alias = false;
+        for (value, index) in enum_def.values.iter().zip(value_indices) {
+            let canonical = enum_def.canonical_value(value);
+            if std::ptr::eq(canonical, value) {
+                continue;
+            }
+            if !enum_def.allows_alias() {
+                let message = format!(
+                    "Enum value '{}' reuses number {} of '{}' in enum '{}'",
+                    value.name, value.number, canonical.name, enum_def.name
+                );
+                let diagnostic = self.diagnostic_at(*index, ErrorCode::InvalidEnum, &message)
+                    .with_help("set 'option allow_alias = true;' to allow aliases");
+                return Err(ParseError::from(diagnostic).into());
+            }
+            has_alias = true;
+        }
+        if enum_def.allows_alias() && !has_alias {
+            return Err(self.error_at(
+                self.position - 1,
+                ErrorCode::InvalidEnum,
+                &format!("Enum '{}' sets allow_alias but has no aliases", enum_def.name),
+            ));
         }
         Ok(())
     }
 
+    /// Parse an enum option (e.g., "option allow_alias = true;")
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder to add the option to
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails
+    fn parse_enum_option(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        let start = self.position;
+        let option = self.parse_option_statement()?;
+        if matches!(option.name.as_str(), "allow_alias" | "deprecated")
+            && !matches!(option.value, OptionValue::Boolean(_))
+        {
+            let message = format!("Invalid value for enum option '{}'", option.name);
+            return Err(self.error_at(start, ErrorCode::InvalidOption, &message));
+        }
+        builder.add_enum_option(option);
+        Ok(())
+    }
+
     /// Parse the body of an enum declaration, including enum values
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add enum elements to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_enum_body(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let mut brace_count = 1;
-        self.current_line += 1;
-        
-        while self.current_line < self.lines.len() && brace_count > 0 {
-            let line = self.lines[self.current_line].trim();
-            
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with("//") {
-                self.current_line += 1;
-                continue;
+        self.expect_symbol('{')?;
+        let mut value_indices = Vec::new();
+
+        loop {
+            if self.eat_symbol('}') {
+                break;
             }
-            
-            // Track brace nesting
-            if line.contains('{') {
-                brace_count += line.matches('{').count();
+            if self.at_end() {
+                return Err(self.error(ErrorCode::UnexpectedToken, "Unexpected end of file while parsing enum"));
             }
-            if line.contains('}') {
-                brace_count -= line.matches('}').count();
-                if brace_count == 0 {
-                    break;
-                }
-            }
-            
-            // Parse enum values
-            if line.contains('=') && !line.starts_with("option") {
-                self.parse_enum_value(builder)?;
+            if self.eat_symbol(';') {
+                continue;
             }
-            
-            self.current_line += 1;
+
+            let start = self.position;
+            let result = match self.peek_ident().as_deref() {
+                Some("option") => self.parse_enum_option(builder),
+                Some("reserved") => self.parse_enum_reserved(builder),
+                // Parse enum values
+                Some(_) => self.parse_enum_value(builder).map(|()| value_indices.push(start)),
+                None => Err(self.unexpected("an enum value")),
+            };
+            self.recover(result, start);
+        }
+
+        if let Some(enum_def) = builder.current_enum.as_ref() {
+            self.validate_enum(enum_def, &value_indices, builder.current_file.syntax == "proto3")?;
         }
         Ok(())
     }
 
     /// Parse enum value declarations (e.g., "ORDER_STATUS_PENDING = 1;")
-    /// 
+    ///
     /// # Arguments
     /// * `builder` - The AST builder to add the enum value to
-    /// 
+    ///
     /// # Returns
     /// * `Result<()>` - Success or error if parsing fails
     fn parse_enum_value(&mut self, builder: &mut AstBuilder) -> Result<()> {
-        let line = self.lines[self.current_line].trim();
-        let parts: Vec<&str> = line.split('=').collect();
-        
-        if parts.len() == 2 {
-            let name = parts[0].trim();
-            let number = parts[1].trim_end_matches(';').trim().parse::<i32>()?;
-            
-            let enum_value = EnumValue {
-                name: name.to_string(),
-                number,
-                options: Vec::new(),
-            };
-            
-            builder.add_enum_value(enum_value);
+        let mut comments = self.leading_comments();
+        let span = self.span();
+        let name_index = self.position;
+        let name = self.expect_ident()?;
+        self.expect_symbol('=')?;
+        let number_index = self.position;
+        let value = self.parse_int()?;
+        let number = i32::try_from(value)
+            .map_err(|_| self.error(ErrorCode::InvalidNumber, &format!("Enum value '{}' is out of range", value)))?;
+        if let Some(enum_def) = builder.current_enum.as_ref() {
+            if enum_def.values.iter().any(|v| v.name == name) {
+                return Err(self.error_at(
+                    name_index,
+                    ErrorCode::Duplicate,
+                    &format!("Enum value '{}' is already defined in enum '{}'", name, enum_def.name),
+                ));
+            }
+            if enum_def.is_reserved_name(&name) {
+                return Err(self.error_at(
+                    name_index,
+                    ErrorCode::Reserved,
+                    &format!("Enum value name '{}' is reserved in enum '{}'", name, enum_def.name),
+                ));
+            }
+            if enum_def.is_reserved_number(number) {
+                return Err(self.error_at(
+                    number_index,
+                    ErrorCode::Reserved,
+                    &format!("Enum value '{}' uses reserved number {} in enum '{}'", name, number, enum_def.name),
+                ));
+            }
         }
+
+        let options_start = self.position;
+        let options = self.parse_compact_options()?;
+        self.expect_symbol(';')?;
+        comments.trailing = self.trailing_comment(self.position - 1);
+
+        if let Some(option) = options
+            .iter()
+            .find(|o| o.name == "deprecated" && !matches!(o.value, OptionValue::Boolean(_)))
+        {
+            return Err(self.error_at(
+                options_start,
+                ErrorCode::InvalidOption,
+                &format!("Invalid value for option '{}' on enum value '{}'", option.name, name),
+            ));
+        }
+
+        let enum_value = EnumValue {
+            name,
+            number,
+            options,
+            comments,
+            span,
+        };
+
+        builder.add_enum_value(enum_value);
+        Ok(())
+    }
+
+    /// Parse a reserved statement inside a message and check it against the
+    /// fields declared so far
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder holding the current message
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails or a field is already
+    ///   declared with a reserved number or name
+    fn parse_message_reserved(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        let start = self.position;
+        let is_editions = builder.current_file.edition.is_some();
+        let (ranges, names) = self.parse_reserved(1, MAX_FIELD_NUMBER, is_editions)?;
+
+        if let Some(message) = builder.current_message.as_ref()
+            && let Some(field) = message
+                .all_fields()
+                .find(|f| names.contains(&f.name) || ranges.iter().any(|r| r.contains(f.number)))
+        {
+            return Err(self.error_at(
+                start,
+                ErrorCode::Reserved,
+                &format!(
+                    "Reservation conflicts with field '{}' = {} in message '{}'",
+                    field.name, field.number, message.name
+                ),
+            ));
+        }
+
+        builder.add_message_reserved(ranges, names);
+        Ok(())
+    }
+
+    /// Parse a reserved statement inside an enum and check it against the
+    /// values declared so far
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder holding the current enum
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails or a value is already
+    ///   declared with a reserved number or name
+    fn parse_enum_reserved(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        let start = self.position;
+        let is_editions = builder.current_file.edition.is_some();
+        let (ranges, names) = self.parse_reserved(i32::MIN, i32::MAX, is_editions)?;
+
+        if let Some(enum_def) = builder.current_enum.as_ref()
+            && let Some(value) = enum_def
+                .values
+                .iter()
+                .find(|v| names.contains(&v.name) || ranges.iter().any(|r| r.contains(v.number)))
+        {
+            return Err(self.error_at(
+                start,
+                ErrorCode::Reserved,
+                &format!(
+                    "Reservation conflicts with value '{}' = {} in enum '{}'",
+                    value.name, value.number, enum_def.name
+                ),
+            ));
+        }
+
+        builder.add_enum_reserved(ranges, names);
         Ok(())
     }
 
+    /// Parse an extensions statement inside a message
+    /// (e.g., "extensions 100 to 199, 1000 to max;") and check it against the
+    /// fields declared so far
+    ///
+    /// Options on the statement (e.g. `[verification = UNVERIFIED]`) are
+    /// parsed but not kept.
+    ///
+    /// # Arguments
+    /// * `builder` - The AST builder holding the current message
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error if parsing fails, the file is proto3,
+    ///   or a field is already declared with a number in the ranges
+    fn parse_extensions(&mut self, builder: &mut AstBuilder) -> Result<()> {
+        let start = self.position;
+        if builder.current_file.syntax == "proto3" {
+            let diagnostic = self
+                .diagnostic_at(start, ErrorCode::InvalidType, "Extension ranges are not allowed in proto3")
+                .with_help("use a field of type google.protobuf.Any instead");
+            return Err(ParseError::from(diagnostic).into());
+        }
+        self.expect_keyword("extensions")?;
+        let ranges = self.parse_ranges(1, MAX_FIELD_NUMBER, "Extension")?;
+        self.parse_compact_options()?;
+        self.expect_symbol(';')?;
+
+        if let Some(message) = builder.current_message.as_ref()
+            && let Some(field) = message.all_fields().find(|f| ranges.iter().any(|r| r.contains(f.number)))
+        {
+            return Err(self.error_at(
+                start,
+                ErrorCode::InvalidNumber,
+                &format!(
+                    "Extension range includes field '{}' = {} in message '{}'",
+                    field.name, field.number, message.name
+                ),
+            ));
+        }
+
+        builder.add_message_extension_ranges(ranges);
+        Ok(())
+    }
+
+    /// Parse the ranges or names of a reserved statement
+    /// (e.g., "reserved 2, 15, 9 to 11, 40 to max;" or "reserved \"foo\", \"bar\";").
+    /// Editions files write reserved names as identifiers ("reserved foo, bar;")
+    ///
+    /// # Arguments
+    /// * `min` - Smallest number that may be reserved
+    /// * `max` - Largest number that may be reserved, also the value of `max`
+    /// * `is_editions` - Whether names are identifiers rather than string literals
+    ///
+    /// # Returns
+    /// * `Result<(Vec<ReservedRange>, Vec<String>)>` - The reserved ranges and names
+    fn parse_reserved(&mut self, min: i32, max: i32, is_editions: bool) -> Result<(Vec<ReservedRange>, Vec<String>)> {
+        self.expect_keyword("reserved")?;
+        let mut ranges = Vec::new();
+        let mut names = Vec::new();
+
+        // A statement reserves either numbers or names, never both
+        if is_editions && let TokenKind::Str(_) = self.peek().kind {
+            let diagnostic = self
+                .diagnostic_at(self.position, ErrorCode::UnexpectedToken, "Reserved names must be identifiers in editions")
+                .with_help("remove the quotes around the reserved names");
+            return Err(ParseError::from(diagnostic).into());
+        } else if is_editions && self.peek_ident().is_some() {
+            loop {
+                names.push(self.expect_ident()?);
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+        } else if let TokenKind::Str(_) = self.peek().kind {
+            loop {
+                names.push(self.expect_string()?);
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+        } else {
+            ranges = self.parse_ranges(min, max, "Reserved")?;
+        }
+
+        self.expect_symbol(';')?;
+        Ok((ranges, names))
+    }
+
+    /// Parse a comma-separated list of numbers and ranges
+    /// (e.g., "2, 9 to 11, 40 to max")
+    ///
+    /// # Arguments
+    /// * `min` - Smallest number allowed
+    /// * `max` - Largest number allowed, also the value of `max`
+    /// * `kind` - What the ranges are for in errors (e.g. "Reserved")
+    fn parse_ranges(&mut self, min: i32, max: i32, kind: &str) -> Result<Vec<ReservedRange>> {
+        let mut ranges = Vec::new();
+        loop {
+            let start_index = self.position;
+            let start = self.parse_range_number(min, max, kind)?;
+            let end = if self.eat_keyword("to") {
+                if self.eat_keyword("max") {
+                    max
+                } else {
+                    self.parse_range_number(min, max, kind)?
+                }
+            } else {
+                start
+            };
+            if end < start {
+                return Err(self.error_at(
+                    start_index,
+                    ErrorCode::InvalidNumber,
+                    &format!("{} range {} to {} ends before it starts", kind, start, end),
+                ));
+            }
+            ranges.push(ReservedRange { start, end });
+            if !self.eat_symbol(',') {
+                break;
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// Parse one number of a reserved or extension range, checking it lies in `min..=max`
+    fn parse_range_number(&mut self, min: i32, max: i32, kind: &str) -> Result<i32> {
+        let value = self.parse_int()?;
+        i32::try_from(value)
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| {
+                self.error(ErrorCode::InvalidNumber, &format!("{} number '{}' is out of range", kind, value))
+            })
+    }
+
+    /// Parse an option statement (e.g., "option (dmxp_channel) = \"user_updates\";")
+    fn parse_option_statement(&mut self) -> Result<ProtoOption> {
+        self.parse_option_statement_with_value().map(|(option, _)| option)
+    }
+
+    /// Parse an option statement, also returning the indices of the first and
+    /// last tokens of its value (e.g. `-` and `5` in "= -5;"), for error locations
+    fn parse_option_statement_with_value(&mut self) -> Result<(ProtoOption, RangeInclusive<usize>)> {
+        let span = self.span();
+        self.expect_keyword("option")?;
+        let name = self.parse_option_name()?;
+        self.expect_symbol('=')?;
+        let value_start = self.position;
+        let value = self.parse_constant()?;
+        let value_tokens = value_start..=self.position - 1;
+        self.expect_symbol(';')?;
+        Ok((ProtoOption { name, value, span }, value_tokens))
+    }
+
+    /// Parse a bracketed option list (e.g., "[deprecated = true, json_name = \"uid\"]"),
+    /// returning no options when there is no list
+    fn parse_compact_options(&mut self) -> Result<Vec<ProtoOption>> {
+        let mut options = Vec::new();
+        if !self.eat_symbol('[') {
+            return Ok(options);
+        }
+
+        loop {
+            let span = self.span();
+            let name = self.parse_option_name()?;
+            self.expect_symbol('=')?;
+            let value = self.parse_constant()?;
+            options.push(ProtoOption { name, value, span });
+
+            if self.eat_symbol(']') {
+                return Ok(options);
+            }
+            self.expect_symbol(',')?;
+        }
+    }
+
+    /// Parse an option name such as `deprecated`, `(dmxp_channel)` or `(foo.bar).baz`
+    fn parse_option_name(&mut self) -> Result<String> {
+        let mut name = String::new();
+        loop {
+            if self.eat_symbol('(') {
+                let extension = self.parse_full_ident()?;
+                self.expect_symbol(')')?;
+                name.push('(');
+                name.push_str(&extension);
+                name.push(')');
+            } else {
+                name.push_str(&self.expect_ident()?);
+            }
+
+            if !self.eat_symbol('.') {
+                return Ok(name);
+            }
+            name.push('.');
+        }
+    }
+
+    /// Parse a constant value used on the right-hand side of an option (e.g.,
+    /// `"a" "b"`, `-0x10`, `-inf`, `SPEED` or `{ name: "events" }`)
+    ///
+    /// Adjacent string literals are concatenated, integers are kept exact (as
+    /// `UInt`, or `Int` when negative), and `inf`/`nan` are read as numbers.
+    ///
+    /// # Returns
+    /// * `Result<OptionValue>` - The parsed constant
+    fn parse_constant(&mut self) -> Result<OptionValue> {
+        match self.peek().kind.clone() {
+            TokenKind::Str(_) => match String::from_utf8(self.expect_string_bytes()?) {
+                Ok(text) => Ok(OptionValue::String(text)),
+                Err(err) => Ok(OptionValue::Bytes(err.into_bytes())),
+            },
+            TokenKind::Symbol('{' | '<') => self.parse_aggregate(),
+            TokenKind::Int(_) | TokenKind::Float(_) | TokenKind::Symbol('-' | '+') => {
+                let negative = self.eat_symbol('-');
+                if !negative {
+                    self.eat_symbol('+');
+                }
+                let value = match &self.peek().kind {
+                    TokenKind::Int(value) if !negative => OptionValue::UInt(*value),
+                    TokenKind::Int(value) => match 0i64.checked_sub_unsigned(*value) {
+                        Some(value) => OptionValue::Int(value),
+                        None => {
+                            let message = format!("Integer -{} is out of range", value);
+                            return Err(self.error(ErrorCode::InvalidNumber, &message));
+                        }
+                    },
+                    TokenKind::Float(value) => OptionValue::Number(*value),
+                    TokenKind::Ident(ident) if ident == "inf" => OptionValue::Number(f64::INFINITY),
+                    TokenKind::Ident(ident) if ident == "nan" => OptionValue::Number(f64::NAN),
+                    _ => return Err(self.unexpected("a number")),
+                };
+                self.advance();
+                Ok(match value {
+                    OptionValue::Number(value) if negative => OptionValue::Number(-value),
+                    value => value,
+                })
+            }
+            TokenKind::Ident(_) => {
+                let ident = self.parse_full_ident()?;
+                Ok(match ident.as_str() {
+                    "true" => OptionValue::Boolean(true),
+                    "false" => OptionValue::Boolean(false),
+                    "inf" => OptionValue::Number(f64::INFINITY),
+                    "nan" => OptionValue::Number(f64::NAN),
+                    _ => OptionValue::Identifier(ident),
+                })
+            }
+            _ => Err(self.unexpected("a constant value")),
+        }
+    }
+
+    /// Parse an aggregate message literal in text format (e.g.,
+    /// `{ name: "events" retry { count: 3 } tags: ["a", "b"] }`)
+    ///
+    /// Fields may be separated by `,` or `;`, the `:` may be left out before a
+    /// nested message, and `<...>` may be used instead of `{...}`.
+    ///
+    /// # Returns
+    /// * `Result<OptionValue>` - An `OptionValue::Aggregate` with the fields in source order
+    fn parse_aggregate(&mut self) -> Result<OptionValue> {
+        let close = if self.eat_symbol('<') {
+            '>'
+        } else {
+            self.expect_symbol('{')?;
+            '}'
+        };
+
+        let mut fields = Vec::new();
+        while !self.eat_symbol(close) {
+            let span = self.span();
+            let name = if self.eat_symbol('[') {
+                let extension = self.parse_full_ident()?;
+                self.expect_symbol(']')?;
+                format!("[{}]", extension)
+            } else {
+                self.expect_ident()?
+            };
+
+            let value = if self.eat_symbol(':') {
+                self.parse_aggregate_value()?
+            } else if matches!(self.peek().kind, TokenKind::Symbol('{' | '<')) {
+                self.parse_aggregate()?
+            } else {
+                return Err(self.unexpected("':'"));
+            };
+            fields.push(ProtoOption { name, value, span });
+
+            if !self.eat_symbol(',') {
+                self.eat_symbol(';');
+            }
+        }
+        Ok(OptionValue::Aggregate(fields))
+    }
+
+    /// Parse the value of an aggregate field: a constant, a nested message or
+    /// a `[...]` list of either
+    fn parse_aggregate_value(&mut self) -> Result<OptionValue> {
+        if !self.eat_symbol('[') {
+            return self.parse_constant();
+        }
+
+        let mut values = Vec::new();
+        if self.eat_symbol(']') {
+            return Ok(OptionValue::List(values));
+        }
+        loop {
+            values.push(self.parse_constant()?);
+            if self.eat_symbol(']') {
+                return Ok(OptionValue::List(values));
+            }
+            self.expect_symbol(',')?;
+        }
+    }
+
+    /// Parse an optionally negative integer literal
+    fn parse_int(&mut self) -> Result<i64> {
+        let negative = self.eat_symbol('-');
+        let TokenKind::Int(value) = self.peek().kind else {
+            return Err(self.unexpected("an integer"));
+        };
+        let value = i64::try_from(value)
+            .map_err(|_| self.error(ErrorCode::InvalidNumber, &format!("Integer '{}' is out of range", value)))?;
+        self.advance();
+        Ok(if negative { -value } else { value })
+    }
+
+    /// Parse a dotted identifier (e.g. "com.example.foo")
+    fn parse_full_ident(&mut self) -> Result<String> {
+        let mut ident = self.expect_ident()?;
+        while self.eat_symbol('.') {
+            ident.push('.');
+            ident.push_str(&self.expect_ident()?);
+        }
+        Ok(ident)
+    }
+
+    /// Parse a type reference used by fields, RPC signatures and extend
+    /// blocks: a dotted name, fully qualified when it starts with a dot (e.g.
+    /// `Outer.Inner` or `.acme.v1.GetRequest`)
+    fn parse_type_name(&mut self) -> Result<String> {
+        if self.eat_symbol('.') {
+            return Ok(format!(".{}", self.parse_full_ident()?));
+        }
+        self.parse_full_ident()
+    }
+
+    /// Whether a type name follows the keyword at the current token (e.g.
+    /// `repeated Foo` or `stream .acme.Req`)
+    ///
+    /// A dot directly after the keyword continues it instead, so `stream.Req`
+    /// is the type `Req` in package `stream`.
+    fn type_follows_keyword(&self) -> bool {
+        let keyword = self.peek();
+        let next = self.peek_ahead(1);
+        match next.kind {
+            TokenKind::Ident(_) => true,
+            TokenKind::Symbol('.') => next.offset > keyword.offset + keyword.length,
+            _ => false,
+        }
+    }
+
+    /// Collect the leading and detached comments of the declaration starting
+    /// at the current token
+    ///
+    /// A comment that starts on the same line as the previous token is that
+    /// token's trailing comment and is left out. Consecutive `//` lines form a
+    /// single block; the block directly above the declaration (no blank line in
+    /// between) is the leading comment, earlier blocks are detached.
+    fn leading_comments(&self) -> Comments {
+        let token = self.peek();
+        let mut comments: &[Comment] = &token.comments;
+
+        if let Some(previous) = self.position.checked_sub(1).map(|i| &self.tokens[i])
+            && comments.first().is_some_and(|c| c.start_line == previous.line)
+        {
+            comments = &comments[1..];
+        }
+
+        let mut blocks: Vec<(String, usize)> = Vec::new();
+        let mut previous: Option<&Comment> = None;
+        for comment in comments {
+            let continues_block = previous.is_some_and(|p| {
+                !p.is_block && !comment.is_block && comment.start_line == p.end_line + 1
+            });
+            match blocks.last_mut() {
+                Some((text, end_line)) if continues_block => {
+                    text.push('\n');
+                    text.push_str(&comment.text);
+                    *end_line = comment.end_line;
+                }
+                _ => blocks.push((comment.text.clone(), comment.end_line)),
+            }
+            previous = Some(comment);
+        }
+
+        let leading = match blocks.last() {
+            Some((_, end_line)) if end_line + 1 >= token.line => blocks.pop().map(|(text, _)| text),
+            _ => None,
+        };
+
+        Comments {
+            leading,
+            trailing: None,
+            detached: blocks.into_iter().map(|(text, _)| text).collect(),
+        }
+    }
+
+    /// Comment written on the same line right after the token at `index`
+    /// (a field's `;` or a message's opening `{`)
+    fn trailing_comment(&self, index: usize) -> Option<String> {
+        let token = self.tokens.get(index)?;
+        let next = self.tokens.get(index + 1)?;
+        next.comments
+            .first()
+            .filter(|c| c.start_line == token.line)
+            .map(|c| c.text.clone())
+    }
+
+    /// Record the error of a failed statement that started at token `start`
+    /// and skip ahead to where parsing can resume
+    fn recover(&mut self, result: Result<()>, start: usize) {
+        if let Err(err) = result {
+            self.record(err);
+            self.synchronize(start);
+        }
+    }
+
+    /// Add the diagnostics of `err` to the parse in progress
+    ///
+    /// Every enclosing block reports its own "unexpected end of file", so only
+    /// the first diagnostic at the end of the file is kept.
+    fn record(&mut self, err: Error) {
+        let diagnostics = match err.downcast::<ParseError>() {
+            Ok(parse_error) => parse_error.diagnostics,
+            Err(err) => vec![Diagnostic::error(ErrorCode::UnexpectedToken, err.to_string())],
+        };
+        let eof = self.tokens.last().map(|token| token.offset);
+        let at_eof = |diagnostic: &Diagnostic| diagnostic.span.as_ref().map(|span| span.offset) == eof;
+
+        for diagnostic in diagnostics {
+            if at_eof(&diagnostic) && self.diagnostics.iter().any(at_eof) {
+                continue;
+            }
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Skip the rest of a failed statement that started at token `start`
+    ///
+    /// Stops after the statement's `;` or its balanced `{ ... }` block, before
+    /// the `}` that closes the enclosing block, or before the next top-level
+    /// keyword. A statement that failed only after its closing `;` or `}` is
+    /// already complete and nothing is skipped.
+    fn synchronize(&mut self, start: usize) {
+        if self.position > start && matches!(self.tokens[self.position - 1].kind, TokenKind::Symbol(';' | '}')) {
+            return;
+        }
+        // Always make progress, so a token that cannot start anything is not retried
+        if self.position == start && !self.at_end() {
+            self.advance();
+        }
+
+        let mut depth = 0usize;
+        while !self.at_end() {
+            match &self.peek().kind {
+                TokenKind::Symbol('{') => depth += 1,
+                TokenKind::Symbol('}') if depth == 0 => return,
+                TokenKind::Symbol('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                TokenKind::Symbol(';') if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenKind::Ident(keyword) if depth == 0 && TOP_LEVEL_KEYWORDS.contains(&keyword.as_str()) => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// Current token (the lexer always terminates the stream with `Eof`)
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position.min(self.tokens.len() - 1)]
+    }
+
+    /// Token `offset` positions after the current one
+    fn peek_ahead(&self, offset: usize) -> &Token {
+        &self.tokens[(self.position + offset).min(self.tokens.len() - 1)]
+    }
+
+    /// Current token if it is an identifier
+    fn peek_ident(&self) -> Option<String> {
+        match &self.peek().kind {
+            TokenKind::Ident(ident) => Some(ident.clone()),
+            _ => None,
+        }
+    }
+
+    /// Move to the next token, returning the one just consumed
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.position += 1;
+        }
+        self.current_line = self.peek().line;
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek().kind == TokenKind::Eof
+    }
+
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek().kind == TokenKind::Symbol(symbol)
+    }
+
+    fn eat_symbol(&mut self, symbol: char) -> bool {
+        if self.is_symbol(symbol) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", symbol)))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_ident().as_deref() == Some(keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", keyword)))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String> {
+        match self.peek_ident() {
+            Some(ident) => {
+                self.advance();
+                Ok(ident)
+            }
+            None => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    /// Expect a string literal, concatenating adjacent literals (e.g. `"a" 'b'`),
+    /// which must be valid UTF-8
+    fn expect_string(&mut self) -> Result<String> {
+        let start = self.position;
+        let bytes = self.expect_string_bytes()?;
+        String::from_utf8(bytes)
+            .map_err(|_| self.error_at(start, ErrorCode::InvalidToken, "String literal is not valid UTF-8"))
+    }
+
+    /// Expect a string literal, concatenating adjacent literals, as raw bytes
+    fn expect_string_bytes(&mut self) -> Result<Vec<u8>> {
+        let TokenKind::Str(first) = &self.peek().kind else {
+            return Err(self.unexpected("a string literal"));
+        };
+        let mut value = first.clone();
+        self.advance();
+        while let TokenKind::Str(next) = &self.peek().kind {
+            value.extend_from_slice(next);
+            self.advance();
+        }
+        Ok(value)
+    }
+
+    /// Span of the current token
+    fn span(&self) -> Span {
+        self.span_at(self.position)
+    }
+
+    /// Span of the token with the given index
+    fn span_at(&self, index: usize) -> Span {
+        let token = &self.tokens[index.min(self.tokens.len() - 1)];
+        Span {
+            file: self.file_name.clone(),
+            offset: token.offset,
+            line: token.line,
+            column: token.column,
+        }
+    }
+
+    /// Build an error located at the current token
+    fn error(&self, code: ErrorCode, message: &str) -> Error {
+        self.error_at(self.position, code, message)
+    }
+
+    /// Build an error located at the token with the given index
+    fn error_at(&self, index: usize, code: ErrorCode, message: &str) -> Error {
+        ParseError::from(self.diagnostic_at(index, code, message)).into()
+    }
+
+    /// Build an error diagnostic that underlines the token with the given index
+    fn diagnostic_at(&self, index: usize, code: ErrorCode, message: &str) -> Diagnostic {
+        let index = index.min(self.tokens.len() - 1);
+        Diagnostic::error(code, message).with_span(self.span_at(index), self.tokens[index].length)
+    }
+
+    /// Build an error diagnostic that underlines the tokens from the first to
+    /// the last index of `tokens`
+    fn diagnostic_over(&self, tokens: RangeInclusive<usize>, code: ErrorCode, message: &str) -> Diagnostic {
+        let last = (*tokens.end()).min(self.tokens.len() - 1);
+        let first = (*tokens.start()).min(last);
+        let length = self.tokens[last].offset + self.tokens[last].length - self.tokens[first].offset;
+        Diagnostic::error(code, message).with_span(self.span_at(first), length)
+    }
+
+    /// Build an "expected X, found Y" error located at the current token
+    fn unexpected(&self, expected: &str) -> Error {
+        let found = match &self.peek().kind {
+            TokenKind::Ident(ident) => format!("'{}'", ident),
+            TokenKind::Int(value) => format!("'{}'", value),
+            TokenKind::Float(value) => format!("'{}'", value),
+            TokenKind::Str(value) => format!("\"{}\"", String::from_utf8_lossy(value)),
+            TokenKind::Symbol(symbol) => format!("'{}'", symbol),
+            TokenKind::Eof => "end of file".to_string(),
+        };
+        self.error(ErrorCode::UnexpectedToken, &format!("Expected {}, found {}", expected, found))
+    }
+}
+
+/// Presence of a field as declared, before editions features are resolved:
+/// proto3 fields have implicit presence unless written `optional` or declared
+/// in a oneof, and `required` fields must be set
+fn declared_presence(syntax: &str, label: Option<&FieldLabel>, in_oneof: bool) -> FieldPresence {
+    match label {
+        Some(FieldLabel::Required) => FieldPresence::LegacyRequired,
+        None if syntax == "proto3" && !in_oneof => FieldPresence::Implicit,
+        _ => FieldPresence::Explicit,
+    }
+}
+
+/// Why `value` is not a string where one is expected, if it is a string
+/// literal that is not valid UTF-8 (e.g. ": string literal is not valid UTF-8")
+fn utf8_problem(value: &OptionValue) -> &'static str {
+    match value {
+        OptionValue::Bytes(_) => ": string literal is not valid UTF-8",
+        _ => "",
+    }
+}
+
+/// Flat option name matching `field` of the bundled aggregate `config`
+/// message (e.g. "dmxp_channel" for `name` in "ChannelConfig")
+fn dmxp_option_key(config: &str, field: &str) -> String {
+    match (config, field) {
+        ("ChannelConfig", "name") => "dmxp_channel".to_string(),
+        ("MethodConfig", "channel") => "dmxp_method_channel".to_string(),
+        ("MethodConfig", "timeout_ms") => "dmxp_method_timeout_ms".to_string(),
+        _ => format!("dmxp_{}", field),
+    }
 }
 
+/// Why `value` cannot be assigned to a DMXP option of type `field_type`, if it cannot
+fn dmxp_value_problem(field_type: &FieldType, value: &OptionValue) -> Option<(ErrorCode, String)> {
+    let problem = match (field_type, value) {
+        (FieldType::String, OptionValue::String(_)) | (FieldType::Bool, OptionValue::Boolean(_)) => return None,
+        (FieldType::Uint32, OptionValue::Number(n)) => format!("{:?} is not an integer", n),
+        (FieldType::Uint32, OptionValue::UInt(n)) if *n <= u64::from(u32::MAX) => return None,
+        (FieldType::Uint32, OptionValue::UInt(n)) => return Some(uint32_out_of_range(n)),
+        (FieldType::Uint32, OptionValue::Int(n)) => return Some(uint32_out_of_range(n)),
+        _ => {
+            let expected = match field_type {
+                FieldType::String => "a string",
+                FieldType::Bool => "true or false",
+                FieldType::Uint32 => "an unsigned integer",
+                _ => "a message literal",
+            };
+            let found = match value {
+                OptionValue::String(s) => format!("string \"{}\"", s),
+                OptionValue::Bytes(_) => "a string that is not valid UTF-8".to_string(),
+                OptionValue::Number(n) => format!("number {}", n),
+                OptionValue::UInt(n) => format!("number {}", n),
+                OptionValue::Int(n) => format!("number {}", n),
+                OptionValue::Boolean(b) => format!("'{}'", b),
+                OptionValue::Identifier(ident) => format!("identifier '{}'", ident),
+                OptionValue::Aggregate(_) => "a message literal".to_string(),
+                OptionValue::List(_) => "a list".to_string(),
+            };
+            format!("expected {}, found {}", expected, found)
+        }
+    };
+    Some((ErrorCode::InvalidOption, problem))
+}
+
+/// Problem reported for an integer option value that does not fit a uint32
+fn uint32_out_of_range(n: &dyn std::fmt::Display) -> (ErrorCode, String) {
+    (ErrorCode::InvalidNumber, format!("{} is out of range for uint32 (0 to {})", n, u32::MAX))
+}
+
+/// Proto name and value range of an integer field type, `None` for other types
+fn integer_range(field_type: &FieldType) -> Option<(&'static str, i128, i128)> {
+    let range = match field_type {
+        FieldType::Int32 => ("int32", i32::MIN.into(), i32::MAX.into()),
+        FieldType::Sint32 => ("sint32", i32::MIN.into(), i32::MAX.into()),
+        FieldType::Sfixed32 => ("sfixed32", i32::MIN.into(), i32::MAX.into()),
+        FieldType::Uint32 => ("uint32", 0, u32::MAX.into()),
+        FieldType::Fixed32 => ("fixed32", 0, u32::MAX.into()),
+        FieldType::Int64 => ("int64", i64::MIN.into(), i64::MAX.into()),
+        FieldType::Sint64 => ("sint64", i64::MIN.into(), i64::MAX.into()),
+        FieldType::Sfixed64 => ("sfixed64", i64::MIN.into(), i64::MAX.into()),
+        FieldType::Uint64 => ("uint64", 0, u64::MAX.into()),
+        FieldType::Fixed64 => ("fixed64", 0, u64::MAX.into()),
+        _ => return None,
+    };
+    Some(range)
+}
+
+/// Apply a flat DMXP message option (e.g. "dmxp_buffer_size"), ignoring
+/// values of the wrong type
+fn apply_dmxp_message_option(options: &mut DmxpMessageOptions, key: &str, value: &OptionValue) {
+    match key {
+        "dmxp_channel" => {
+            if let Some(channel_name) = extract_string_value(value) {
+                options.channel = Some(channel_name);
+            }
+        }
+        "dmxp_persistent" => {
+            if let Some(persistent) = extract_bool_value(value) {
+                options.persistent = Some(persistent);
+            }
+        }
+        "dmxp_buffer_size" => {
+            if let Some(size) = extract_number_value::<u32>(value) {
+                options.buffer_size = Some(size);
+            }
+        }
+        "dmxp_wal_enabled" => {
+            if let Some(enabled) = extract_bool_value(value) {
+                options.wal_enabled = Some(enabled);
+            }
+        }
+        "dmxp_swap_enabled" => {
+            if let Some(enabled) = extract_bool_value(value) {
+                options.swap_enabled = Some(enabled);
+            }
+        }
+        "dmxp_priority" => {
+            if let Some(priority) = extract_number_value::<u32>(value) {
+                options.priority = Some(priority);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Apply a flat DMXP service option (e.g. "dmxp_channels"), ignoring values
+/// of the wrong type; each channel is added to the list
+fn apply_dmxp_service_option(options: &mut DmxpServiceOptions, key: &str, value: &OptionValue) {
+    match key {
+        "dmxp_channels" => {
+            if let Some(channel_name) = extract_string_value(value) {
+                options.channels.push(channel_name);
+            }
+        }
+        "dmxp_timeout_ms" => {
+            if let Some(timeout_ms) = extract_number_value::<u32>(value) {
+                options.timeout_ms = Some(timeout_ms);
+            }
+        }
+        "dmxp_retry_count" => {
+            if let Some(retry_count) = extract_number_value::<u32>(value) {
+                options.retry_count = Some(retry_count);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Apply a flat DMXP method option (e.g. "dmxp_method_channel"), ignoring
+/// values of the wrong type
+fn apply_dmxp_method_option(options: &mut DmxpMethodOptions, key: &str, value: &OptionValue) {
+    match key {
+        "dmxp_method_channel" => {
+            if let Some(channel) = extract_string_value(value) {
+                options.channel = Some(channel);
+            }
+        }
+        "dmxp_method_timeout_ms" => {
+            if let Some(timeout_ms) = extract_number_value::<u32>(value) {
+                options.timeout_ms = Some(timeout_ms);
+            }
+        }
+        "dmxp_async" => {
+            if let Some(is_async) = extract_bool_value(value) {
+                options.is_async = Some(is_async);
+            }
+        }
+        _ => {}
+    }
+}
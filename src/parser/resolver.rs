@@ -0,0 +1,338 @@
+use crate::ast::*;
+use crate::parser::diagnostic::{Diagnostic, ErrorCode, ParseError};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+/// Kind of a named type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Message,
+    Enum,
+}
+
+/// A message or enum declared somewhere in a set of files
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    /// Name of the file that declares the type
+    pub file: String,
+    /// Value names, for enums
+    pub values: Vec<String>,
+}
+
+/// All types declared in a set of files, keyed by fully qualified name
+/// (without the leading dot, e.g. "acme.v1.User.Address")
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    /// Collect every message and enum declared in `files`
+    pub fn from_files(files: &[ProtoFile]) -> Self {
+        let mut table = Self::default();
+        for file in files {
+            for message in &file.messages {
+                table.add_message(&file.package, message, &file.name);
+            }
+            for enum_def in &file.enums {
+                table.add_enum(&file.package, enum_def, &file.name);
+            }
+        }
+        table
+    }
+
+    fn add_message(&mut self, scope: &str, message: &Message, file: &str) {
+        let full_name = qualify(scope, &message.name);
+        for nested in &message.nested_messages {
+            self.add_message(&full_name, nested, file);
+        }
+        for enum_def in &message.nested_enums {
+            self.add_enum(&full_name, enum_def, file);
+        }
+        self.add(full_name, SymbolKind::Message, file, Vec::new());
+    }
+
+    fn add_enum(&mut self, scope: &str, enum_def: &Enum, file: &str) {
+        let values = enum_def.values.iter().map(|v| v.name.clone()).collect();
+        self.add(qualify(scope, &enum_def.name), SymbolKind::Enum, file, values);
+    }
+
+    fn add(&mut self, full_name: String, kind: SymbolKind, file: &str, values: Vec<String>) {
+        self.symbols.insert(full_name, Symbol { kind, file: file.to_string(), values });
+    }
+
+    /// Look up a symbol by its fully qualified name
+    pub fn get(&self, full_name: &str) -> Option<&Symbol> {
+        self.symbols.get(full_name)
+    }
+
+    /// Resolve `name` as written inside `scope` (a package or message name),
+    /// following protobuf scoping: the innermost enclosing scope wins, and a
+    /// name with a leading dot (e.g. ".acme.v1.User") is already fully qualified
+    ///
+    /// Only symbols declared in one of `visible_files` are considered.
+    pub fn resolve(&self, name: &str, scope: &str, visible_files: &HashSet<String>) -> Option<(String, &Symbol)> {
+        if let Some(full_name) = name.strip_prefix('.') {
+            return self.symbols
+                .get(full_name)
+                .filter(|symbol| visible_files.contains(&symbol.file))
+                .map(|symbol| (full_name.to_string(), symbol));
+        }
+        let parts: Vec<&str> = if scope.is_empty() { Vec::new() } else { scope.split('.').collect() };
+        (0..=parts.len()).rev().find_map(|depth| {
+            let candidate = qualify(&parts[..depth].join("."), name);
+            self.symbols
+                .get(&candidate)
+                .filter(|symbol| visible_files.contains(&symbol.file))
+                .map(|symbol| (candidate, symbol))
+        })
+    }
+}
+
+/// Resolve every type reference in `files`, which must be in dependency order
+///
+/// References to enums are turned into `FieldType::Enum`, and the fully
+/// qualified name of every referenced type is recorded next to the name as
+/// written (e.g. `Field::resolved_type`); references that do not resolve, or
+/// that point into a file that is not imported, are reported together as one
+/// [`ParseError`] with a diagnostic per reference.
+pub fn resolve_references(files: &mut [ProtoFile]) -> Result<()> {
+    let table = SymbolTable::from_files(files);
+    let mut errors = Vec::new();
+
+    for index in 0..files.len() {
+        let visible = visible_files(files, &files[index].name);
+        let file = &mut files[index];
+        let package = file.package.clone();
+        let file_name = file.name.clone();
+        let mut context = ResolveContext {
+            table: &table,
+            visible: &visible,
+            file: &file_name,
+            errors: &mut errors,
+        };
+
+        for message in &mut file.messages {
+            context.resolve_message(&package, message);
+        }
+        for extension in &mut file.extensions {
+            let scope = qualify(&package, &extension.scope);
+            let location = format!("extension '{}'", qualify(&scope, &extension.name));
+            extension.resolved_type = context
+                .resolve_field_type(&scope, &mut extension.field_type, &location, &extension.span)
+                .map(|(full_name, _)| full_name);
+        }
+        for service in &mut file.services {
+            for method in &mut service.methods {
+                method.resolved_input_type =
+                    context.resolve_method_type(&package, &method.input_type, &service.name, method);
+                method.resolved_output_type =
+                    context.resolve_method_type(&package, &method.output_type, &service.name, method);
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseError { diagnostics: errors }.into())
+    }
+}
+
+/// Resolve references to types declared in `file` itself
+///
+/// The parser runs this so that a single file, parsed without its imports,
+/// already tells enum fields from message fields (e.g. for field presence)
+/// and knows the fully qualified name of its own nested types; references
+/// into other files are left to [`resolve_references`].
+pub fn resolve_local_types(file: &mut ProtoFile) {
+    let table = SymbolTable::from_files(std::slice::from_ref(file));
+    let visible = HashSet::from([file.name.clone()]);
+    let resolve = |scope: &str, field_type: &mut FieldType| -> Option<String> {
+        let field_type = match field_type {
+            FieldType::Map(_, value) => value.as_mut(),
+            other => other,
+        };
+        let (FieldType::Message(name) | FieldType::Enum(name)) = field_type else {
+            return None;
+        };
+        let (full_name, symbol) = table.resolve(name, scope, &visible)?;
+        if symbol.kind == SymbolKind::Enum {
+            *field_type = FieldType::Enum(name.clone());
+        }
+        Some(format!(".{}", full_name))
+    };
+
+    fn visit(scope: &str, message: &mut Message, resolve: &dyn Fn(&str, &mut FieldType) -> Option<String>) {
+        let full_name = qualify(scope, &message.name);
+        let fields = message.fields.iter_mut().chain(message.oneofs.iter_mut().flat_map(|o| o.fields.iter_mut()));
+        for field in fields {
+            field.resolved_type = resolve(&full_name, &mut field.field_type);
+        }
+        for nested in &mut message.nested_messages {
+            visit(&full_name, nested, resolve);
+        }
+    }
+
+    for message in &mut file.messages {
+        visit(&file.package, message, &resolve);
+    }
+    for extension in &mut file.extensions {
+        extension.resolved_type = resolve(&qualify(&file.package, &extension.scope), &mut extension.field_type);
+    }
+    let resolve_message = |name: &str| {
+        table
+            .resolve(name, &file.package, &visible)
+            .filter(|(_, symbol)| symbol.kind == SymbolKind::Message)
+            .map(|(full_name, _)| format!(".{}", full_name))
+    };
+    for method in file.services.iter_mut().flat_map(|s| s.methods.iter_mut()) {
+        method.resolved_input_type = resolve_message(&method.input_type);
+        method.resolved_output_type = resolve_message(&method.output_type);
+    }
+}
+
+/// Files whose declarations `file_name` may reference: the file itself, its
+/// direct imports, and anything those re-export through `import public`
+fn visible_files(files: &[ProtoFile], file_name: &str) -> HashSet<String> {
+    let find = |name: &str| files.iter().find(|f| f.name == name);
+    let mut visible = HashSet::from([file_name.to_string()]);
+
+    let mut pending: Vec<&str> = find(file_name)
+        .map(|f| f.imports.iter().map(|i| i.path.as_str()).collect())
+        .unwrap_or_default();
+    while let Some(name) = pending.pop() {
+        if !visible.insert(name.to_string()) {
+            continue;
+        }
+        if let Some(imported) = find(name) {
+            pending.extend(
+                imported.imports
+                    .iter()
+                    .filter(|i| i.kind == ImportKind::Public)
+                    .map(|i| i.path.as_str()),
+            );
+        }
+    }
+    visible
+}
+
+struct ResolveContext<'a> {
+    table: &'a SymbolTable,
+    visible: &'a HashSet<String>,
+    file: &'a str,
+    errors: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> ResolveContext<'a> {
+    fn resolve_message(&mut self, scope: &str, message: &mut Message) {
+        let full_name = qualify(scope, &message.name);
+        let fields = message.fields.iter_mut().chain(message.oneofs.iter_mut().flat_map(|o| o.fields.iter_mut()));
+        for field in fields {
+            let location = format!("field '{}.{}'", full_name, field.name);
+            let resolved = self.resolve_field_type(&full_name, &mut field.field_type, &location, &field.span);
+            if let Some(default) = &field.default_value {
+                self.check_default_value(resolved.as_ref().map(|(_, symbol)| *symbol), default, &location, &field.span);
+            }
+            field.resolved_type = resolved.map(|(full_name, _)| full_name);
+        }
+        for nested in &mut message.nested_messages {
+            self.resolve_message(&full_name, nested);
+        }
+    }
+
+    /// Resolve a field's type in place, returning the fully qualified name
+    /// (with a leading dot) and symbol of the type it refers to
+    fn resolve_field_type(
+        &mut self,
+        scope: &str,
+        field_type: &mut FieldType,
+        location: &str,
+        span: &Span,
+    ) -> Option<(String, &'a Symbol)> {
+        match field_type {
+            FieldType::Message(name) => {
+                let (full_name, symbol) = self.lookup(name, scope, location, span)?;
+                if symbol.kind == SymbolKind::Enum {
+                    *field_type = FieldType::Enum(name.clone());
+                }
+                Some((full_name, symbol))
+            }
+            // Already marked by the parser (see `resolve_local_types`)
+            FieldType::Enum(name) => self.lookup(name, scope, location, span),
+            FieldType::Map(_, value) => self.resolve_field_type(scope, value, location, span),
+            _ => None,
+        }
+    }
+
+    /// Check a `[default = ...]` on a field of message or enum type: messages
+    /// cannot have defaults, and enum defaults must name one of the enum's values
+    fn check_default_value(&mut self, symbol: Option<&Symbol>, default: &OptionValue, location: &str, span: &Span) {
+        let Some(symbol) = symbol else {
+            return;
+        };
+        match (symbol.kind, default) {
+            (SymbolKind::Message, _) => self.report(
+                ErrorCode::InvalidDefault,
+                format!("{} has a message type and cannot have a default value", location),
+                span,
+            ),
+            (SymbolKind::Enum, OptionValue::Identifier(value)) if !symbol.values.contains(value) => self.report(
+                ErrorCode::InvalidDefault,
+                format!("default value '{}' of {} is not a value of the enum", value, location),
+                span,
+            ),
+            _ => {}
+        }
+    }
+
+    /// Resolve a request or response type, returning its fully qualified name
+    /// (with a leading dot)
+    fn resolve_method_type(&mut self, scope: &str, type_name: &str, service: &str, method: &Method) -> Option<String> {
+        let location = format!("rpc '{}.{}'", service, method.name);
+        let (full_name, symbol) = self.lookup(type_name, scope, &location, &method.span)?;
+        if symbol.kind == SymbolKind::Enum {
+            self.report(
+                ErrorCode::InvalidType,
+                format!("'{}' in {} is an enum, but RPC types must be messages", type_name, location),
+                &method.span,
+            );
+        }
+        Some(full_name)
+    }
+
+    /// Look up a type reference, returning its fully qualified name (with a
+    /// leading dot) and symbol, or reporting why it does not resolve
+    fn lookup(&mut self, name: &str, scope: &str, location: &str, span: &Span) -> Option<(String, &'a Symbol)> {
+        if let Some((full_name, symbol)) = self.table.resolve(name, scope, self.visible) {
+            return Some((format!(".{}", full_name), symbol));
+        }
+
+        let everything: HashSet<String> = self.table.symbols.values().map(|s| s.file.clone()).collect();
+        let message = match self.table.resolve(name, scope, &everything) {
+            Some((full_name, symbol)) => format!(
+                "'{}' in {} resolves to '{}' in '{}', which is not imported",
+                name, location, full_name, symbol.file
+            ),
+            None => format!("unknown type '{}' in {}", name, location),
+        };
+        self.report(ErrorCode::UnresolvedType, message, span);
+        None
+    }
+
+    /// Record an error at `span`, which is always attributed to the file being resolved
+    fn report(&mut self, code: ErrorCode, message: String, span: &Span) {
+        let span = Span { file: self.file.to_string(), ..span.clone() };
+        self.errors.push(Diagnostic::error(code, message).with_span(span, 1));
+    }
+}
+
+/// Join a scope and a name with a dot, omitting the dot for an empty scope
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
@@ -0,0 +1,98 @@
+use crate::utils::LoadFile;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where `ProtoLoader` reads proto files from, by import name (e.g.
+/// "acme/common.proto")
+///
+/// Implement this to load from a virtual file tree, a database or a network
+/// service; [`FileSystemProvider`] and [`InMemoryProvider`] cover the common
+/// cases.
+pub trait SourceProvider: fmt::Debug + Send + Sync {
+    /// Read the file imported as `name`
+    ///
+    /// # Returns
+    /// * `Result<Option<String>>` - The file content, `None` if the provider
+    ///   has no such file, or an error if it exists but cannot be read
+    fn read(&self, name: &str) -> Result<Option<String>>;
+
+    /// Where files are looked up, for "not found" errors (e.g. "include paths: 'proto'")
+    fn describe(&self) -> String;
+}
+
+/// Reads files from `-I`-style include paths, searched in order
+#[derive(Debug, Clone, Default)]
+pub struct FileSystemProvider {
+    include_paths: Vec<PathBuf>,
+}
+
+impl FileSystemProvider {
+    /// Create a provider searching `include_paths` in order
+    pub fn new(include_paths: Vec<PathBuf>) -> Self {
+        Self { include_paths }
+    }
+}
+
+impl SourceProvider for FileSystemProvider {
+    fn read(&self, name: &str) -> Result<Option<String>> {
+        let Some(path) = self.include_paths
+            .iter()
+            .map(|include| include.join(name))
+            .find(|candidate| candidate.is_file())
+        else {
+            return Ok(None);
+        };
+        LoadFile::LoadFile(&path.to_string_lossy()).map(Some)
+    }
+
+    fn describe(&self) -> String {
+        let paths: Vec<String> = self.include_paths.iter().map(|p| format!("'{}'", p.display())).collect();
+        format!("include paths: {}", paths.join(", "))
+    }
+}
+
+/// Serves files from memory, for callers that receive proto text rather than
+/// reading it from disk
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryProvider {
+    files: HashMap<String, String>,
+}
+
+impl InMemoryProvider {
+    /// Create a provider with no files
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file under its import name, replacing any file with that name
+    ///
+    /// # Arguments
+    /// * `name` - Import name of the file (e.g. "acme/common.proto")
+    /// * `content` - The file's proto source
+    ///
+    /// # Returns
+    /// The provider, for chaining after `new`
+    pub fn with_file(mut self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        self.add_file(name, content);
+        self
+    }
+
+    /// Add a file under its import name, replacing any file with that name
+    pub fn add_file(&mut self, name: impl Into<String>, content: impl Into<String>) {
+        self.files.insert(name.into(), content.into());
+    }
+}
+
+impl SourceProvider for InMemoryProvider {
+    fn read(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.files.get(name).cloned())
+    }
+
+    fn describe(&self) -> String {
+        let mut names: Vec<&str> = self.files.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        format!("in-memory files: {}", names.iter().map(|n| format!("'{}'", n)).collect::<Vec<_>>().join(", "))
+    }
+}
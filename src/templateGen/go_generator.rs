@@ -1,5 +1,5 @@
 use crate::ast::*;
-use crate::templateGen::template_generator::{CodeGenerator, GeneratorOptions, helpers};
+use crate::templateGen::template_generator::{CodeGenerator, GeneratorOptions, Language, helpers};
 use anyhow::Result;
 
 /// Go code generator
@@ -15,6 +15,8 @@ impl GoGenerator {
 
 impl CodeGenerator for GoGenerator {
     fn generate(&self, proto_file: &ProtoFile) -> Result<String> {
+        // Nested types are generated at the top level under qualified names
+        let proto_file = &helpers::hoist_nested_types(proto_file);
         let mut code = String::new();
         
         // Add header comment
@@ -24,17 +26,32 @@ impl CodeGenerator for GoGenerator {
         ));
         code.push_str("// DO NOT EDIT - This file is auto-generated\n\n");
         
-        // Add package declaration
-        let package_name = self.options.package_override
-            .as_ref()
-            .unwrap_or(&proto_file.package)
-            .replace("-", "_");
-        code.push_str(&format!("package {}\n\n", package_name));
+        // Add package declaration; an explicit override wins over go_package,
+        // which wins over the proto package
+        let go_package = proto_file.go_package();
+        let package_name = match (&self.options.package_override, &go_package) {
+            (Some(package_override), _) => package_override.replace("-", "_"),
+            (None, Some(go_package)) => go_package.name.clone(),
+            (None, None) => proto_file.package.replace(['-', '.'], "_"),
+        };
+        match go_package.filter(|p| !p.import_path.is_empty()) {
+            Some(go_package) => code.push_str(&format!(
+                "package {} // import \"{}\"\n\n",
+                package_name, go_package.import_path
+            )),
+            None => code.push_str(&format!("package {}\n\n", package_name)),
+        }
         
         // Add imports
         code.push_str("import (\n");
         code.push_str("\t\"context\"\n");
         code.push_str("\t\"encoding/json\"\n");
+        if self.uses_math(proto_file) {
+            code.push_str("\t\"math\"\n");
+        }
+        if self.uses_timeouts(proto_file) || self.uses_time_types(proto_file) {
+            code.push_str("\t\"time\"\n");
+        }
         if self.options.include_dmxp {
             code.push_str("\t\"github.com/dmxp/dmxp-go\"\n");
         }
@@ -43,19 +60,25 @@ impl CodeGenerator for GoGenerator {
         // Generate enums first
         for enum_def in &proto_file.enums {
             code.push_str(&self.generate_enum(enum_def)?);
-            code.push_str("\n");
+            code.push('\n');
         }
         
         // Generate messages
         for message in &proto_file.messages {
-            code.push_str(&self.generate_message(message)?);
-            code.push_str("\n");
+            code.push_str(&self.generate_message(message, proto_file)?);
+            code.push('\n');
         }
         
         // Generate services
         for service in &proto_file.services {
             code.push_str(&self.generate_service(service)?);
-            code.push_str("\n");
+            code.push('\n');
+        }
+        
+        // Generate the pumps shared by streaming methods
+        if proto_file.services.iter().any(|s| s.methods.iter().any(|m| m.is_streaming())) {
+            code.push_str(&self.generate_stream_helpers());
+            code.push('\n');
         }
         
         // Generate DMXP channels
@@ -72,10 +95,21 @@ impl GoGenerator {
     fn generate_enum(&self, enum_def: &Enum) -> Result<String> {
         let mut code = String::new();
         
+        code.push_str(&helpers::generate_doc_comment(&enum_def.comments, "", &Language::Go));
         code.push_str(&format!("type {} int32\n\n", enum_def.name));
         code.push_str("const (\n");
         
+        // Aliases are ordinary constants; only the first value with a number
+        // appears in the switches below, since Go rejects duplicate cases
         for value in &enum_def.values {
+            let doc = helpers::generate_doc_comment(&value.comments, "\t", &Language::Go);
+            code.push_str(&doc);
+            if value.is_deprecated() {
+                if !doc.is_empty() {
+                    code.push_str("\t//\n");
+                }
+                code.push_str("\t// Deprecated: Marked as deprecated in the proto file.\n");
+            }
             code.push_str(&format!(
                 "\t{}_{} {} = {}\n",
                 enum_def.name.to_uppercase(),
@@ -96,7 +130,7 @@ func (e {}) String() string {{
             enum_def.name
         ));
         
-        for value in &enum_def.values {
+        for value in enum_def.canonical_values() {
             code.push_str(&format!(
                 "\tcase {}_{}:\n\t\treturn \"{}\"\n",
                 enum_def.name.to_uppercase(),
@@ -118,7 +152,7 @@ func {}FromNumber(num int32) ({} {}) {{
             enum_def.name, enum_def.name, enum_def.name
         ));
         
-        for value in &enum_def.values {
+        for value in enum_def.canonical_values() {
             code.push_str(&format!(
                 "\tcase {}:\n\t\treturn {}_{}\n",
                 value.number,
@@ -127,7 +161,12 @@ func {}FromNumber(num int32) ({} {}) {{
             ));
         }
         
-        code.push_str("\tdefault:\n\t\treturn 0\n");
+        // Open enums keep numbers they do not declare
+        if enum_def.generates_open_enum() {
+            code.push_str(&format!("\tdefault:\n\t\treturn {}(num)\n", enum_def.name));
+        } else {
+            code.push_str("\tdefault:\n\t\treturn 0\n");
+        }
         code.push_str("\t}\n");
         code.push_str("}\n");
         
@@ -135,23 +174,35 @@ func {}FromNumber(num int32) ({} {}) {{
     }
 
     /// Generate Go message struct
-    fn generate_message(&self, message: &Message) -> Result<String> {
+    fn generate_message(&self, message: &Message, proto_file: &ProtoFile) -> Result<String> {
         let mut code = String::new();
         
         // Generate the main struct
+        code.push_str(&helpers::generate_doc_comment(&message.comments, "", &Language::Go));
         code.push_str(&format!("type {} struct {{\n", message.name));
         
         for field in &message.fields {
             let field_type = self.convert_field_type(field);
             let field_name = helpers::convert_field_name(&field.name, &crate::templateGen::template_generator::Language::Go);
-            let json_tag = format!("json:\"{}\"", field.name);
+            let json_tag = format!("json:\"{}\"", field.json_name());
             
+            code.push_str(&self.generate_field_doc(field, field.is_packed(&proto_file.syntax), "\t"));
             code.push_str(&format!(
                 "\t{} {} `{}`\n",
                 field_name, field_type, json_tag
             ));
         }
         
+        // Oneof members are (un)marshalled by the generated MarshalJSON/UnmarshalJSON
+        for oneof in &message.oneofs {
+            code.push_str(&helpers::generate_doc_comment(&oneof.comments, "\t", &Language::Go));
+            code.push_str(&format!(
+                "\t{} {} `json:\"-\"`\n",
+                helpers::to_pascal_case(&oneof.name),
+                self.oneof_interface_name(message, oneof)
+            ));
+        }
+        
         code.push_str("}\n");
         
         // Generate constructor
@@ -165,13 +216,22 @@ func New{}() *{} {{
         
         for field in &message.fields {
             let field_name = helpers::convert_field_name(&field.name, &crate::templateGen::template_generator::Language::Go);
-            let default_value = self.get_default_value(field);
+            let default_value = self.get_default_value(message, field);
             code.push_str(&format!("\t\t{}: {},\n", field_name, default_value));
         }
         
         code.push_str("\t}\n");
         code.push_str("}\n");
         
+        code.push_str(&self.generate_declared_defaults(message));
+        
+        for oneof in &message.oneofs {
+            code.push_str(&self.generate_oneof(message, oneof, proto_file)?);
+        }
+        if !message.oneofs.is_empty() {
+            code.push_str(&self.generate_oneof_json(message, proto_file)?);
+        }
+        
         // Generate DMXP methods if applicable
         if self.options.include_dmxp {
             code.push_str(&helpers::generate_dmxp_channel_code(message, &crate::templateGen::template_generator::Language::Go));
@@ -180,6 +240,139 @@ func New{}() *{} {{
         Ok(code)
     }
 
+    /// Generate the interface and wrapper structs backing a oneof
+    fn generate_oneof(&self, message: &Message, oneof: &Oneof, proto_file: &ProtoFile) -> Result<String> {
+        let mut code = String::new();
+        let interface_name = self.oneof_interface_name(message, oneof);
+        
+        code.push_str(&format!(
+            "\ntype {} interface {{\n\t{}()\n}}\n",
+            interface_name, interface_name
+        ));
+        
+        for field in &oneof.fields {
+            let wrapper_name = self.oneof_wrapper_name(message, field, proto_file);
+            let field_type = helpers::convert_field_type(&field.field_type, &Language::Go);
+            
+            code.push('\n');
+            // Oneof members cannot be repeated, so they are never packed
+            code.push_str(&self.generate_field_doc(field, false, ""));
+            code.push_str(&format!(
+                "type {} struct {{\n\t{} {} `json:\"{}\"`\n}}\n",
+                wrapper_name,
+                helpers::to_pascal_case(&field.name),
+                field_type,
+                field.json_name()
+            ));
+            code.push_str(&format!("\nfunc (*{}) {}() {{}}\n", wrapper_name, interface_name));
+        }
+        
+        Ok(code)
+    }
+
+    /// Generate MarshalJSON/UnmarshalJSON so oneof members appear as ordinary
+    /// fields of the JSON object, as in proto3 JSON
+    fn generate_oneof_json(&self, message: &Message, proto_file: &ProtoFile) -> Result<String> {
+        let mut code = String::new();
+        
+        code.push_str(&format!(
+            r#"
+func (m *{}) MarshalJSON() ([]byte, error) {{
+    type plain {}
+    raw, err := json.Marshal((*plain)(m))
+    if err != nil {{
+        return nil, err
+    }}
+    out := map[string]json.RawMessage{{}}
+    if err := json.Unmarshal(raw, &out); err != nil {{
+        return nil, err
+    }}
+"#,
+            message.name, message.name
+        ));
+        
+        for oneof in &message.oneofs {
+            code.push_str(&format!("\tswitch v := m.{}.(type) {{\n", helpers::to_pascal_case(&oneof.name)));
+            for field in &oneof.fields {
+                code.push_str(&format!(
+                    "\tcase *{}:\n\t\tvalue, err := json.Marshal(v.{})\n\t\tif err != nil {{\n\t\t\treturn nil, err\n\t\t}}\n\t\tout[\"{}\"] = value\n",
+                    self.oneof_wrapper_name(message, field, proto_file),
+                    helpers::to_pascal_case(&field.name),
+                    field.json_name()
+                ));
+            }
+            code.push_str("\t}\n");
+        }
+        
+        code.push_str("\treturn json.Marshal(out)\n}\n");
+        
+        code.push_str(&format!(
+            r#"
+func (m *{}) UnmarshalJSON(data []byte) error {{
+    type plain {}
+    if err := json.Unmarshal(data, (*plain)(m)); err != nil {{
+        return err
+    }}
+    raw := map[string]json.RawMessage{{}}
+    if err := json.Unmarshal(data, &raw); err != nil {{
+        return err
+    }}
+"#,
+            message.name, message.name
+        ));
+        
+        for oneof in &message.oneofs {
+            for field in &oneof.fields {
+                let field_type = helpers::convert_field_type(&field.field_type, &Language::Go);
+                code.push_str(&format!(
+                    "\tif value, ok := raw[\"{}\"]; ok {{\n\t\tvar member {}\n\t\tif err := json.Unmarshal(value, &member); err != nil {{\n\t\t\treturn err\n\t\t}}\n\t\tm.{} = &{}{{{}: member}}\n\t}}\n",
+                    field.json_name(),
+                    field_type,
+                    helpers::to_pascal_case(&oneof.name),
+                    self.oneof_wrapper_name(message, field, proto_file),
+                    helpers::to_pascal_case(&field.name)
+                ));
+            }
+        }
+        
+        code.push_str("\treturn nil\n}\n");
+        
+        Ok(code)
+    }
+
+    /// Doc comment for a struct field, including a note when it is encoded
+    /// packed and the standard deprecation notice
+    fn generate_field_doc(&self, field: &Field, packed: bool, indent: &str) -> String {
+        let mut code = helpers::generate_doc_comment(&field.comments, indent, &Language::Go);
+        if packed {
+            if !code.is_empty() {
+                code.push_str(&format!("{}//\n", indent));
+            }
+            code.push_str(&format!("{}// Encoded as a packed repeated field.\n", indent));
+        }
+        if field.is_deprecated() {
+            if !code.is_empty() {
+                code.push_str(&format!("{}//\n", indent));
+            }
+            code.push_str(&format!("{}// Deprecated: Marked as deprecated in the proto file.\n", indent));
+        }
+        code
+    }
+
+    /// Name of the interface generated for a oneof (e.g. `isUserEvent_Payload`)
+    fn oneof_interface_name(&self, message: &Message, oneof: &Oneof) -> String {
+        format!("is{}_{}", message.name, helpers::to_pascal_case(&oneof.name))
+    }
+
+    /// Name of the wrapper struct generated for a oneof member (e.g. `UserEvent_Created`),
+    /// with a trailing `_` when a hoisted nested type already has that name, as
+    /// protoc-gen-go does
+    fn oneof_wrapper_name(&self, message: &Message, field: &Field, proto_file: &ProtoFile) -> String {
+        let name = format!("{}_{}", message.name, helpers::to_pascal_case(&field.name));
+        let taken = proto_file.messages.iter().any(|m| m.name == name) || proto_file.enums.iter().any(|e| e.name == name);
+        if taken { format!("{}_", name) } else { name }
+    }
+
     /// Generate Go service
     fn generate_service(&self, service: &Service) -> Result<String> {
         let mut code = String::new();
@@ -188,6 +381,7 @@ func New{}() *{} {{
         let service_struct_name = format!("{}Service", service_name);
         
         // Generate service struct
+        code.push_str(&helpers::generate_doc_comment(&service.comments, "", &Language::Go));
         code.push_str(&format!("type {} struct {{\n", service_struct_name));
         if self.options.include_dmxp {
             code.push_str("\tcontext *dmxp.Context\n");
@@ -208,32 +402,206 @@ func New{}Service(context *dmxp.Context) *{} {{
         
         // Generate methods
         for method in &service.methods {
-            if self.options.use_async {
-                code.push_str(&format!(
-                    r#"
-func (s *{}) {}(ctx context.Context, request *{}) (*{}, error) {{
-    // TODO: Implement {} method
-    return nil, fmt.Errorf("method {} not implemented")
-}}
-"#,
-                    service_struct_name, method.name, method.input_type, method.output_type, method.name, method.name
-                ));
+            let doc = helpers::generate_doc_comment(&method.comments, "", &Language::Go);
+            if method.is_streaming() {
+                code.push_str(&self.generate_streaming_method(service, method, &doc));
+                continue;
+            }
+            
+            let input_type = helpers::message_type(&method.input_type, &Language::Go);
+            let output_type = helpers::message_type(&method.output_type, &Language::Go);
+            let (ctx_param, ctx_setup) = if method.is_async(self.options.use_async) {
+                ("ctx context.Context, ", "")
             } else {
-                code.push_str(&format!(
-                    r#"
-func (s *{}) {}(request *{}) (*{}, error) {{
-    // TODO: Implement {} method
-    return nil, fmt.Errorf("method {} not implemented")
+                ("", "    ctx := context.Background()\n")
+            };
+            code.push_str(&format!(
+                "\n{}func (s *{}) {}({}request *{}) (*{}, error) {{\n",
+                doc, service_struct_name, method.name, ctx_param, input_type, output_type
+            ));
+            code.push_str(ctx_setup);
+            if let Some(timeout_ms) = service.method_timeout_ms(method) {
+                code.push_str(&format!("    ctx, cancel := context.WithTimeout(ctx, {}*time.Millisecond)\n", timeout_ms));
+                code.push_str("    defer cancel()\n");
+            }
+            code.push_str(&format!(
+                r#"    response := new({})
+    if err := s.context.Request(ctx, {}, request, response); err != nil {{
+        return nil, err
+    }}
+    return response, nil
 }}
 "#,
-                    service_struct_name, method.name, method.input_type, method.output_type, method.name, method.name
-                ));
-            }
+                output_type, helpers::string_literal(&service.method_channel(method), &Language::Go)
+            ));
         }
         
         Ok(code)
     }
 
+    /// Check if any method in the file has a timeout, which needs the `time` package
+    fn uses_timeouts(&self, proto_file: &ProtoFile) -> bool {
+        proto_file.services
+            .iter()
+            .any(|s| s.methods.iter().any(|m| s.method_timeout_ms(m).is_some()))
+    }
+
+    /// Check if any field (nested messages included) or RPC has a
+    /// `google.protobuf.Timestamp` or `Duration` type, which map to `time` types
+    fn uses_time_types(&self, proto_file: &ProtoFile) -> bool {
+        fn is_time(name: &str) -> bool {
+            matches!(helpers::well_known_type(name), Some("Timestamp" | "Duration"))
+        }
+        fn has_time(field_type: &FieldType) -> bool {
+            match field_type {
+                FieldType::Message(name) => is_time(name),
+                FieldType::Map(_, value) => has_time(value),
+                _ => false,
+            }
+        }
+        fn message_uses_time(message: &Message) -> bool {
+            message.all_fields().any(|f| has_time(&f.field_type))
+                || message.nested_messages.iter().any(message_uses_time)
+        }
+
+        proto_file.messages.iter().any(message_uses_time)
+            || proto_file.services
+                .iter()
+                .flat_map(|s| s.methods.iter())
+                .any(|m| is_time(&m.input_type) || is_time(&m.output_type))
+    }
+
+    /// Generate a streaming method and its channel-based stream handle
+    ///
+    /// Responses are delivered on `Recv`; for client and bidirectional streaming,
+    /// requests written to `Send` are forwarded until it is closed.
+    fn generate_streaming_method(&self, service: &Service, method: &Method, doc: &str) -> String {
+        let channel = service.method_channel(method);
+        let service_struct_name = format!("{}Service", service.name);
+        let handle_name = format!("{}{}Stream", service.name, method.name);
+        let input_type = helpers::message_type(&method.input_type, &Language::Go);
+        let output_type = helpers::message_type(&method.output_type, &Language::Go);
+        let mut code = String::new();
+        
+        // Stream handle
+        code.push_str(&format!(
+            "\n// {} is the stream handle for {}.{}, backed by the {} DMXP channel.\n",
+            handle_name, service.name, method.name, helpers::string_literal(&channel, &Language::Go)
+        ));
+        code.push_str(&format!("type {} struct {{\n", handle_name));
+        code.push_str(&format!("\t// Recv delivers responses and is closed when the stream ends\n\tRecv <-chan *{}\n", output_type));
+        if method.client_streaming {
+            code.push_str(&format!("\t// Send accepts requests; close it to finish sending\n\tSend chan<- *{}\n", input_type));
+        }
+        code.push_str("\t// Err reports the error that ended the stream, if any\n\tErr <-chan error\n");
+        code.push_str("}\n");
+        
+        // Method
+        let request_param = if method.client_streaming {
+            String::new()
+        } else {
+            format!(", request *{}", input_type)
+        };
+        code.push_str(&format!(
+            "\n{}func (s *{}) {}(ctx context.Context{}) (*{}, error) {{\n",
+            doc, service_struct_name, method.name, request_param, handle_name
+        ));
+        // The stream owns the context; the receive loop cancels it once the stream ends
+        match service.method_timeout_ms(method) {
+            Some(timeout_ms) => code.push_str(&format!(
+                "    ctx, cancel := context.WithTimeout(ctx, {}*time.Millisecond)\n",
+                timeout_ms
+            )),
+            None => code.push_str("    ctx, cancel := context.WithCancel(ctx)\n"),
+        }
+        code.push_str(&format!(
+            r#"    stream, err := s.context.OpenStream(ctx, {})
+    if err != nil {{
+        cancel()
+        return nil, err
+    }}
+"#,
+            helpers::string_literal(&channel, &Language::Go)
+        ));
+        
+        if !method.client_streaming {
+            code.push_str("    if err := stream.Send(request); err != nil {\n        cancel()\n        return nil, err\n    }\n");
+            code.push_str("    if err := stream.CloseSend(); err != nil {\n        cancel()\n        return nil, err\n    }\n");
+        }
+        code.push_str("    errs := make(chan error, 2)\n");
+        code.push_str("    ended := make(chan struct{})\n");
+        code.push_str(&format!("    recv := make(chan *{})\n", output_type));
+        code.push_str("    go dmxpRecvLoop(ctx, cancel, stream, recv, ended, errs)\n");
+        if method.client_streaming {
+            code.push_str(&format!("    send := make(chan *{})\n", input_type));
+            code.push_str("    go dmxpSendLoop(ctx, stream, send, ended, errs)\n");
+            code.push_str(&format!("    return &{}{{Recv: recv, Send: send, Err: errs}}, nil\n", handle_name));
+        } else {
+            code.push_str(&format!("    return &{}{{Recv: recv, Err: errs}}, nil\n", handle_name));
+        }
+        code.push_str("}\n");
+        
+        code
+    }
+
+    /// Generate the generic loops that move messages between DMXP streams and Go channels
+    fn generate_stream_helpers(&self) -> String {
+        r#"// dmxpRecvLoop delivers messages read from a DMXP stream to out until the
+// stream ends, then closes out and ended and cancels the stream's context.
+// A failure is reported on errs.
+func dmxpRecvLoop[T any](ctx context.Context, cancel context.CancelFunc, stream *dmxp.Stream, out chan<- *T, ended chan<- struct{}, errs chan<- error) {
+    defer cancel()
+    defer close(ended)
+    defer close(out)
+    for {
+        msg := new(T)
+        if err := stream.Recv(msg); err != nil {
+            if err != dmxp.EndOfStream {
+                errs <- err
+            }
+            return
+        }
+        select {
+        case out <- msg:
+        case <-ctx.Done():
+            errs <- ctx.Err()
+            return
+        }
+    }
+}
+
+// dmxpSendLoop writes messages from in to a DMXP stream until in is closed,
+// then closes the sending side of the stream. A failure is reported on errs;
+// the context being cancelled because the stream ended is not one.
+func dmxpSendLoop[T any](ctx context.Context, stream *dmxp.Stream, in <-chan *T, ended <-chan struct{}, errs chan<- error) {
+    for {
+        select {
+        case msg, ok := <-in:
+            if !ok {
+                if err := stream.CloseSend(); err != nil {
+                    errs <- err
+                }
+                return
+            }
+            if err := stream.Send(msg); err != nil {
+                errs <- err
+                return
+            }
+        case <-ctx.Done():
+            select {
+            case <-ended:
+                // The stream ended and cancelled ctx itself
+            default:
+                errs <- ctx.Err()
+            }
+            return
+        }
+    }
+}
+"#
+        .to_string()
+    }
+
     /// Generate DMXP channels
     fn generate_dmxp_channels(&self, proto_file: &ProtoFile) -> Result<String> {
         let mut code = String::new();
@@ -241,28 +609,31 @@ func (s *{}) {}(request *{}) (*{}, error) {{
         code.push_str("// DMXP Channel implementations\n");
         
         for message in &proto_file.messages {
-            if let Some(dmxp_opts) = &message.dmxp_options {
-                if let Some(channel) = &dmxp_opts.channel {
-                    code.push_str(&format!(
-                        r#"
+            if let Some(channel) = message.get_dmxp_channel() {
+                let channel_suffix = self.to_pascal_case(&helpers::channel_ident(channel));
+                let literal = helpers::string_literal(channel, &Language::Go);
+                code.push_str(&format!(
+                    r#"
 // Channel: {}
-func (m *{}) PublishTo{}(publisher *dmxp.Publisher) error {{
-    return publisher.Publish("{}", m)
+
+{}func (m *{}) PublishTo{}(publisher *dmxp.Publisher) error {{
+    return publisher.Publish({}, m)
 }}
 
-func SubscribeTo{}(callback func(*{}) error) error {{
-    return dmxp.Subscribe("{}", callback)
+{}func SubscribeTo{}(callback func(*{}) error) error {{
+    return dmxp.Subscribe({}, callback)
 }}
 "#,
-                        channel,
-                        message.name,
-                        self.to_pascal_case(&channel.replace("-", "_")),
-                        channel,
-                        self.to_pascal_case(&channel.replace("-", "_")),
-                        message.name,
-                        channel
-                    ));
-                }
+                    channel.escape_debug(),
+                    helpers::generate_channel_doc_comment(message, channel, &format!("PublishTo{} publishes", channel_suffix), "", &Language::Go),
+                    message.name,
+                    channel_suffix,
+                    literal,
+                    helpers::generate_channel_doc_comment(message, channel, &format!("SubscribeTo{} subscribes to", channel_suffix), "", &Language::Go),
+                    channel_suffix,
+                    message.name,
+                    literal
+                ));
             }
         }
         
@@ -271,31 +642,173 @@ func SubscribeTo{}(callback func(*{}) error) error {{
 
     /// Convert field type to Go type
     fn convert_field_type(&self, field: &Field) -> String {
-        let base_type = helpers::convert_field_type(&field.field_type, &crate::templateGen::template_generator::Language::Go);
+        let base_type = self.value_type(field);
+        
+        // Maps are already a reference type; the label does not wrap them
+        if let FieldType::Map(_, _) = field.field_type {
+            return base_type;
+        }
         
+        // Message fields are already pointers and byte slices are nil when
+        // unset, so neither needs another pointer to track presence
+        let is_nilable = match &field.field_type {
+            FieldType::Bytes => true,
+            FieldType::Message(_) => self.enum_name(field).is_none(),
+            // NullValue is an enum, but an interface in Go
+            FieldType::Enum(name) => helpers::well_known_type(name).is_some(),
+            _ => false,
+        };
         match field.label {
-            FieldLabel::Optional => format!("*{}", base_type),
+            FieldLabel::Optional if !field.has_implicit_presence() && !is_nilable => format!("*{}", base_type),
             FieldLabel::Repeated => format!("[]{}", base_type),
-            FieldLabel::Required => base_type,
+            FieldLabel::Optional | FieldLabel::Required => base_type,
+        }
+    }
+
+    /// Go type of a single value of the field, before the label wraps it
+    fn value_type(&self, field: &Field) -> String {
+        match self.enum_name(field) {
+            Some(name) => helpers::type_ident(name).to_string(),
+            None => helpers::convert_field_type(&field.field_type, &Language::Go),
+        }
+    }
+
+    /// Name of the field's enum type, if it has one that is generated as an
+    /// integer (so not the well-known `NullValue`)
+    ///
+    /// Outside the loader, enums declared in other files are still
+    /// `FieldType::Message`; a field with a default is an enum all the same,
+    /// since message fields cannot have one.
+    fn enum_name<'f>(&self, field: &'f Field) -> Option<&'f str> {
+        match (&field.field_type, &field.default_value) {
+            (FieldType::Enum(name), _) | (FieldType::Message(name), Some(OptionValue::Identifier(_))) => {
+                Some(name.as_str()).filter(|name| helpers::well_known_type(name).is_none())
+            }
+            _ => None,
         }
     }
 
     /// Get default value for a field
-    fn get_default_value(&self, field: &Field) -> String {
+    fn get_default_value(&self, message: &Message, field: &Field) -> String {
+        if let FieldType::Map(_, _) = field.field_type {
+            return format!("make({})", self.convert_field_type(field));
+        }
+        
+        // Well-known types mapped to Go value types are never nil
+        let value_zero = match &field.field_type {
+            FieldType::Message(name) => match helpers::well_known_type(name) {
+                Some("Timestamp") => Some("time.Time{}"),
+                Some("Duration") => Some("0"),
+                Some("Empty") => Some("struct{}{}"),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let (Some(zero), false) = (value_zero, matches!(field.label, FieldLabel::Repeated)) {
+            return zero.to_string();
+        }
+
+        // Fields with implicit presence hold their zero value like required ones
         match field.label {
-            FieldLabel::Optional => "nil".to_string(),
+            FieldLabel::Optional if !field.has_implicit_presence() => "nil".to_string(),
             FieldLabel::Repeated => "nil".to_string(),
-            FieldLabel::Required => match field.field_type {
+            _ if self.declared_default(field).is_some() => self.default_constant_name(message, field),
+            _ if self.enum_name(field).is_some() => "0".to_string(),
+            _ => match field.field_type {
                 FieldType::String => "\"\"".to_string(),
-                FieldType::Bytes => "nil".to_string(),
-                FieldType::Int32 | FieldType::Int64 | FieldType::Uint32 | FieldType::Uint64 => "0".to_string(),
+                // The only enum left here is NullValue, an interface
+                FieldType::Bytes | FieldType::Message(_) | FieldType::Enum(_) => "nil".to_string(),
                 FieldType::Float | FieldType::Double => "0.0".to_string(),
                 FieldType::Bool => "false".to_string(),
-                _ => "{}".to_string(),
+                _ => "0".to_string(),
             },
         }
     }
 
+    /// Generate `Default_<Message>_<Field>` for every field with a declared
+    /// default, plus a `Get<Field>()` getter that falls back to it for
+    /// optional fields
+    fn generate_declared_defaults(&self, message: &Message) -> String {
+        let mut code = String::new();
+        
+        for field in &message.fields {
+            let Some(default) = self.declared_default(field) else {
+                continue;
+            };
+            let constant = self.default_constant_name(message, field);
+            let field_type = self.value_type(field);
+            // Byte slices and the results of math.Inf/math.NaN cannot be Go constants
+            let is_constant = !matches!(field.field_type, FieldType::Bytes) && !default.contains("math.");
+            code.push_str(&format!(
+                "\n{} {} {} = {}\n",
+                if is_constant { "const" } else { "var" }, constant, field_type, default
+            ));
+            
+            if let FieldLabel::Optional = field.label {
+                let field_name = helpers::convert_field_name(&field.name, &Language::Go);
+                // Byte slices are stored as is rather than behind a pointer
+                let deref = if matches!(field.field_type, FieldType::Bytes) { "" } else { "*" };
+                code.push_str(&format!(
+                    r#"
+// Get{} returns {}, or its declared default when it is not set.
+func (m *{}) Get{}() {} {{
+    if m != nil && m.{} != nil {{
+        return {}m.{}
+    }}
+    return {}
+}}
+"#,
+                    field_name, field_name, message.name, field_name, field_type, field_name, deref, field_name, constant
+                ));
+            }
+        }
+        
+        code
+    }
+
+    /// Name of the constant holding a field's declared default
+    fn default_constant_name(&self, message: &Message, field: &Field) -> String {
+        format!("Default_{}_{}", message.name, helpers::convert_field_name(&field.name, &Language::Go))
+    }
+
+    /// Go expression for a field's `[default = ...]` value, if it declares one
+    fn declared_default(&self, field: &Field) -> Option<String> {
+        let value = field.default_value.as_ref()?;
+        let literal = match (&field.field_type, value) {
+            (FieldType::String, OptionValue::String(text)) => helpers::string_literal(text, &Language::Go),
+            (FieldType::Bytes, OptionValue::String(text)) => format!("[]byte({})", helpers::string_literal(text, &Language::Go)),
+            (FieldType::Bytes, OptionValue::Bytes(bytes)) => format!("[]byte({})", go_bytes_literal(bytes)),
+            (_, OptionValue::Boolean(flag)) => flag.to_string(),
+            (FieldType::Float | FieldType::Double, OptionValue::Number(number)) if !number.is_finite() => {
+                let go_type = helpers::convert_field_type(&field.field_type, &Language::Go);
+                if number.is_nan() {
+                    format!("{}(math.NaN())", go_type)
+                } else {
+                    format!("{}(math.Inf({}))", go_type, number.signum())
+                }
+            }
+            (FieldType::Float | FieldType::Double, OptionValue::Number(number)) => format!("{:?}", number),
+            (FieldType::Float | FieldType::Double, OptionValue::UInt(number)) => format!("{}.0", number),
+            (FieldType::Float | FieldType::Double, OptionValue::Int(number)) => format!("{}.0", number),
+            (_, OptionValue::UInt(number)) => number.to_string(),
+            (_, OptionValue::Int(number)) => number.to_string(),
+            // Message-typed defaults are rejected, so this is an enum value
+            (FieldType::Message(name) | FieldType::Enum(name), OptionValue::Identifier(ident)) => {
+                format!("{}_{}", helpers::type_ident(name).to_uppercase(), ident)
+            }
+            _ => return None,
+        };
+        Some(literal)
+    }
+
+    /// Check if any declared default needs the `math` package (`inf` and `nan`)
+    fn uses_math(&self, proto_file: &ProtoFile) -> bool {
+        proto_file.messages.iter().flat_map(|m| m.fields.iter()).any(|f| {
+            matches!(f.field_type, FieldType::Float | FieldType::Double)
+                && matches!(f.default_value, Some(OptionValue::Number(n)) if !n.is_finite())
+        })
+    }
+
     /// Convert to PascalCase
     fn to_pascal_case(&self, s: &str) -> String {
         s.split('_')
@@ -309,3 +822,18 @@ func SubscribeTo{}(callback func(*{}) error) error {{
             .collect()
     }
 }
+
+/// Quote `bytes`, which need not be UTF-8, as a Go interpreted string literal
+fn go_bytes_literal(bytes: &[u8]) -> String {
+    let mut literal = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b' '..=b'~' => literal.push(char::from(byte)),
+            _ => literal.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -1,5 +1,5 @@
 use crate::ast::*;
-use crate::templateGen::template_generator::{CodeGenerator, GeneratorOptions, helpers};
+use crate::templateGen::template_generator::{CodeGenerator, GeneratorOptions, Language, helpers};
 use anyhow::Result;
 
 /// Rust code generator
@@ -15,6 +15,8 @@ impl RustGenerator {
 
 impl CodeGenerator for RustGenerator {
     fn generate(&self, proto_file: &ProtoFile) -> Result<String> {
+        // Nested types are generated at the top level under qualified names
+        let proto_file = &helpers::hoist_nested_types(proto_file);
         let mut code = String::new();
         
         // Add header comment
@@ -33,24 +35,24 @@ impl CodeGenerator for RustGenerator {
         if self.options.use_async {
             code.push_str("use tokio::sync::RwLock;\n");
         }
-        code.push_str("\n");
+        code.push('\n');
         
         // Generate enums first
         for enum_def in &proto_file.enums {
             code.push_str(&self.generate_enum(enum_def)?);
-            code.push_str("\n");
+            code.push('\n');
         }
         
         // Generate messages
         for message in &proto_file.messages {
-            code.push_str(&self.generate_message(message)?);
-            code.push_str("\n");
+            code.push_str(&self.generate_message(message, &proto_file.syntax)?);
+            code.push('\n');
         }
         
         // Generate services
         for service in &proto_file.services {
             code.push_str(&self.generate_service(service)?);
-            code.push_str("\n");
+            code.push('\n');
         }
         
         // Generate DMXP channels
@@ -67,43 +69,83 @@ impl RustGenerator {
     fn generate_enum(&self, enum_def: &Enum) -> Result<String> {
         let mut code = String::new();
         
-        code.push_str(&format!("#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]\n"));
+        code.push_str(&helpers::generate_doc_comment(&enum_def.comments, "", &Language::Rust));
+        code.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]\n");
+        code.push_str(self.allow_nested_name(&enum_def.name));
         code.push_str(&format!("pub enum {} {{\n", enum_def.name));
         
-        for value in &enum_def.values {
+        // Like protobuf, the first value is the default. Aliases share their
+        // number with an earlier value, so they become constants rather than
+        // variants
+        for (index, value) in enum_def.canonical_values().enumerate() {
+            code.push_str(&helpers::generate_doc_comment(&value.comments, "    ", &Language::Rust));
+            if index == 0 {
+                code.push_str("    #[default]\n");
+            }
+            if value.is_deprecated() {
+                code.push_str("    #[deprecated]\n");
+            }
             code.push_str(&format!("    {},\n", value.name));
         }
+        if enum_def.generates_open_enum() {
+            code.push_str("    /// A number the proto file does not declare, kept because the enum is open\n");
+            code.push_str("    Unrecognized(i32),\n");
+        }
         
         code.push_str("}\n");
         
         // Generate implementation methods
-        code.push_str(&format!(
-            r#"
-impl {} {{
-    pub fn from_number(num: i32) -> Option<Self> {{
-        match num {{
+        let allow_deprecated = if enum_def.values.iter().any(|v| v.is_deprecated()) {
+            "#[allow(deprecated)]\n"
+        } else {
+            ""
+        };
+        code.push_str(&format!("\n{}impl {} {{\n", allow_deprecated, enum_def.name));
+        
+        for (alias, canonical) in enum_def.aliases() {
+            let doc = helpers::generate_doc_comment(&alias.comments, "    ", &Language::Rust);
+            if !doc.is_empty() {
+                code.push_str(&doc);
+                code.push_str("    ///\n");
+            }
+            code.push_str(&format!("    /// Alias of [`{}::{}`].\n", enum_def.name, canonical.name));
+            if alias.is_deprecated() {
+                code.push_str("    #[deprecated]\n");
+            }
+            code.push_str(&format!("    pub const {}: Self = Self::{};\n\n", alias.name, canonical.name));
+        }
+        
+        code.push_str(
+            r#"    pub fn from_number(num: i32) -> Option<Self> {
+        match num {
 "#,
-            enum_def.name
-        ));
+        );
         
-        for value in &enum_def.values {
+        for value in enum_def.canonical_values() {
             code.push_str(&format!("            {} => Some({}::{}),\n", value.number, enum_def.name, value.name));
         }
         
-        code.push_str("            _ => None,\n");
+        if enum_def.generates_open_enum() {
+            code.push_str(&format!("            _ => Some({}::Unrecognized(num)),\n", enum_def.name));
+        } else {
+            code.push_str("            _ => None,\n");
+        }
         code.push_str("        }\n");
         code.push_str("    }\n");
         
-        code.push_str(&format!(
+        code.push_str(
             r#"
-    pub fn to_number(&self) -> i32 {{
-        match self {{
+    pub fn to_number(&self) -> i32 {
+        match self {
 "#,
-        ));
+        );
         
-        for value in &enum_def.values {
+        for value in enum_def.canonical_values() {
             code.push_str(&format!("            {}::{} => {},\n", enum_def.name, value.name, value.number));
         }
+        if enum_def.generates_open_enum() {
+            code.push_str(&format!("            {}::Unrecognized(num) => *num,\n", enum_def.name));
+        }
         
         code.push_str("        }\n");
         code.push_str("    }\n");
@@ -113,30 +155,48 @@ impl {} {{
     }
 
     /// Generate Rust message struct
-    fn generate_message(&self, message: &Message) -> Result<String> {
+    fn generate_message(&self, message: &Message, syntax: &str) -> Result<String> {
         let mut code = String::new();
         
         // Generate the main struct
-        code.push_str(&format!("#[derive(Debug, Clone, Serialize, Deserialize)]\n"));
+        code.push_str(&helpers::generate_doc_comment(&message.comments, "", &Language::Rust));
+        code.push_str("#[derive(Debug, Clone, Serialize, Deserialize)]\n");
+        code.push_str(self.allow_nested_name(&message.name));
         code.push_str(&format!("pub struct {} {{\n", message.name));
         
         for field in &message.fields {
             let field_type = self.convert_field_type(field);
             let field_name = helpers::convert_field_name(&field.name, &crate::templateGen::template_generator::Language::Rust);
             
+            code.push_str(&self.generate_field_doc(field, syntax));
+            code.push_str(&self.generate_field_attributes(field));
             code.push_str(&format!("    pub {}: {},\n", field_name, field_type));
         }
         
+        // Each oneof is a single optional enum, flattened so its members
+        // appear as ordinary fields in the JSON object like in proto3 JSON
+        for oneof in &message.oneofs {
+            code.push_str(&helpers::generate_doc_comment(&oneof.comments, "    ", &Language::Rust));
+            code.push_str("    #[serde(flatten)]\n");
+            let oneof_name = helpers::convert_field_name(&oneof.name, &Language::Rust);
+            code.push_str(&format!("    pub {}: Option<{}>,\n", oneof_name, self.oneof_type_name(message, oneof)));
+        }
+        
         code.push_str("}\n");
         
         // Generate implementation
+        let allow_deprecated = if message.fields.iter().any(|f| f.is_deprecated()) {
+            "#[allow(deprecated)]\n"
+        } else {
+            ""
+        };
         code.push_str(&format!(
             r#"
-impl {} {{
+{}impl {} {{
     pub fn new() -> Self {{
         Self {{
 "#,
-            message.name
+            allow_deprecated, message.name
         ));
         
         for field in &message.fields {
@@ -144,10 +204,18 @@ impl {} {{
             let default_value = self.get_default_value(field);
             code.push_str(&format!("            {}: {},\n", field_name, default_value));
         }
+        for oneof in &message.oneofs {
+            code.push_str(&format!("            {}: None,\n", helpers::convert_field_name(&oneof.name, &Language::Rust)));
+        }
         
         code.push_str("        }\n");
         code.push_str("    }\n");
         
+        // Unset optional fields read as their declared default
+        for field in &message.fields {
+            code.push_str(&self.generate_default_accessor(field));
+        }
+        
         // Generate DMXP methods if applicable
         if self.options.include_dmxp {
             code.push_str(&helpers::generate_dmxp_channel_code(message, &crate::templateGen::template_generator::Language::Rust));
@@ -166,9 +234,134 @@ impl Default for {} {{
             message.name
         ));
         
+        for oneof in &message.oneofs {
+            code.push('\n');
+            code.push_str(&self.generate_oneof(message, oneof)?);
+        }
+        
         Ok(code)
     }
 
+    /// Generate the Rust enum backing a oneof, with one variant per member
+    fn generate_oneof(&self, message: &Message, oneof: &Oneof) -> Result<String> {
+        let mut code = String::new();
+        
+        code.push_str(&helpers::generate_doc_comment(&oneof.comments, "", &Language::Rust));
+        code.push_str("#[derive(Debug, Clone, Serialize, Deserialize)]\n");
+        code.push_str(self.allow_nested_name(&message.name));
+        code.push_str(&format!("pub enum {} {{\n", self.oneof_type_name(message, oneof)));
+        
+        for field in &oneof.fields {
+            let field_type = helpers::convert_field_type(&field.field_type, &Language::Rust);
+            code.push_str(&helpers::generate_doc_comment(&field.comments, "    ", &Language::Rust));
+            if field.is_deprecated() {
+                code.push_str("    #[deprecated]\n");
+            }
+            code.push_str(&format!("    #[serde(rename = \"{}\")]\n", field.json_name()));
+            code.push_str(&format!("    {}({}),\n", self.oneof_variant_name(field), field_type));
+        }
+        
+        code.push_str("}\n");
+        
+        Ok(code)
+    }
+
+    /// Generate `<field>_or_default()` for an optional field with a declared default
+    fn generate_default_accessor(&self, field: &Field) -> String {
+        let (FieldLabel::Optional, Some(default)) = (&field.label, self.declared_default(field)) else {
+            return String::new();
+        };
+        let field_name = helpers::convert_field_name(&field.name, &Language::Rust);
+        let field_type = helpers::convert_field_type(&field.field_type, &Language::Rust);
+        let body = match field.field_type {
+            FieldType::String | FieldType::Bytes => format!("self.{}.clone().unwrap_or_else(|| {})", field_name, default),
+            _ => format!("self.{}.unwrap_or({})", field_name, default),
+        };
+        format!(
+            r#"
+    /// Returns `{}`, or its declared default when it is not set
+    pub fn {}_or_default(&self) -> {} {{
+        {}
+    }}
+"#,
+            field.name, field.name, field_type, body
+        )
+    }
+
+    /// Rust expression for a field's `[default = ...]` value, if it declares one
+    fn declared_default(&self, field: &Field) -> Option<String> {
+        let value = field.default_value.as_ref()?;
+        let base_type = helpers::convert_field_type(&field.field_type, &Language::Rust);
+        let literal = match (&field.field_type, value) {
+            (FieldType::String, OptionValue::String(text)) => format!("{:?}.to_string()", text),
+            (FieldType::Bytes, OptionValue::String(text)) => rust_bytes_literal(text.as_bytes()),
+            (FieldType::Bytes, OptionValue::Bytes(bytes)) => rust_bytes_literal(bytes),
+            (_, OptionValue::Boolean(flag)) => flag.to_string(),
+            (FieldType::Float | FieldType::Double, OptionValue::Number(number)) if !number.is_finite() => {
+                match (number.is_nan(), number.is_sign_negative()) {
+                    (true, _) => format!("{}::NAN", base_type),
+                    (false, true) => format!("{}::NEG_INFINITY", base_type),
+                    (false, false) => format!("{}::INFINITY", base_type),
+                }
+            }
+            (FieldType::Float | FieldType::Double, OptionValue::Number(number)) => format!("{:?}", number),
+            (FieldType::Float | FieldType::Double, OptionValue::UInt(number)) => format!("{}.0", number),
+            (FieldType::Float | FieldType::Double, OptionValue::Int(number)) => format!("{}.0", number),
+            (_, OptionValue::UInt(number)) => number.to_string(),
+            (_, OptionValue::Int(number)) => number.to_string(),
+            // Message-typed defaults are rejected, so this is an enum value
+            (_, OptionValue::Identifier(ident)) => format!("{}::{}", base_type, ident),
+            _ => return None,
+        };
+        Some(literal)
+    }
+
+    /// Doc comment for a struct field, noting when a repeated field is encoded packed
+    fn generate_field_doc(&self, field: &Field, syntax: &str) -> String {
+        let mut code = helpers::generate_doc_comment(&field.comments, "    ", &Language::Rust);
+        if field.is_packed(syntax) {
+            if !code.is_empty() {
+                code.push_str("    ///\n");
+            }
+            code.push_str("    /// Encoded as a packed repeated field.\n");
+        }
+        code
+    }
+
+    /// Attributes for a struct field derived from its standard options
+    fn generate_field_attributes(&self, field: &Field) -> String {
+        let mut code = String::new();
+        if field.is_deprecated() {
+            code.push_str("    #[deprecated]\n");
+        }
+        // Serde drops the `r#` of a raw identifier but keeps any other escaping
+        let field_name = helpers::convert_field_name(&field.name, &Language::Rust);
+        if field.json_name() != field_name.trim_start_matches("r#") {
+            code.push_str(&format!("    #[serde(rename = \"{}\")]\n", field.json_name()));
+        }
+        code
+    }
+
+    /// Lint allowance for a hoisted nested type, whose name (e.g. `Outer_Inner`)
+    /// is not camel case
+    fn allow_nested_name(&self, name: &str) -> &'static str {
+        if name.contains('_') { "#[allow(non_camel_case_types)]\n" } else { "" }
+    }
+
+    /// Name of the enum generated for a oneof (e.g. `UserEventPayload`)
+    fn oneof_type_name(&self, message: &Message, oneof: &Oneof) -> String {
+        format!("{}{}", message.name, helpers::to_pascal_case(&oneof.name))
+    }
+
+    /// Name of the enum variant for a oneof member (e.g. `DeletedId`); `self`
+    /// would become the keyword `Self`, so it gets a trailing underscore
+    fn oneof_variant_name(&self, field: &Field) -> String {
+        match helpers::to_pascal_case(&field.name) {
+            name if name == "Self" => format!("{}_", name),
+            name => name,
+        }
+    }
+
     /// Generate Rust service
     fn generate_service(&self, service: &Service) -> Result<String> {
         let mut code = String::new();
@@ -177,7 +370,8 @@ impl Default for {} {{
         let service_struct_name = format!("{}Service", service_name);
         
         // Generate service struct
-        code.push_str(&format!("#[derive(Debug, Clone)]\n"));
+        code.push_str(&helpers::generate_doc_comment(&service.comments, "", &Language::Rust));
+        code.push_str("#[derive(Debug, Clone)]\n");
         code.push_str(&format!("pub struct {} {{\n", service_struct_name));
         if self.options.include_dmxp {
             code.push_str("    context: Context,\n");
@@ -199,25 +393,26 @@ impl {} {{
         
         // Generate methods
         for method in &service.methods {
-            if self.options.use_async {
-                code.push_str(&format!(
-                    r#"
-    pub async fn {}(&self, request: {}) -> Result<{}, DmxpError> {{
-        // TODO: Implement {} method
-        todo!("Implement {} method")
-    }}
-"#,
-                    method.name, method.input_type, method.output_type, method.name, method.name
-                ));
+            let doc = helpers::generate_doc_comment(&method.comments, "    ", &Language::Rust);
+            if method.is_streaming() {
+                code.push_str(&self.generate_streaming_method(service, method, &doc));
             } else {
+                let (asyncness, await_suffix) = if method.is_async(self.options.use_async) {
+                    ("async ", ".await")
+                } else {
+                    ("", "")
+                };
+                let input_type = helpers::message_type(&method.input_type, &Language::Rust);
+                let output_type = helpers::message_type(&method.output_type, &Language::Rust);
                 code.push_str(&format!(
                     r#"
-    pub fn {}(&self, request: {}) -> Result<{}, DmxpError> {{
-        // TODO: Implement {} method
-        todo!("Implement {} method")
+{}    pub {}fn {}(&self, request: {}) -> Result<{}, DmxpError> {{
+        self.context.request::<{}, {}>({:?}, request, {}){}
     }}
 "#,
-                    method.name, method.input_type, method.output_type, method.name, method.name
+                    doc, asyncness, method.name, input_type, output_type,
+                    input_type, output_type, service.method_channel(method), self.timeout_expr(service, method),
+                    await_suffix
                 ));
             }
         }
@@ -227,6 +422,67 @@ impl {} {{
         Ok(code)
     }
 
+    /// Generate a streaming method backed by the method's DMXP channel
+    ///
+    /// Server streaming returns a `dmxp::Stream` of responses; client and
+    /// bidirectional streaming return a `dmxp::Sink` for requests together with
+    /// the response stream.
+    fn generate_streaming_method(&self, service: &Service, method: &Method, doc: &str) -> String {
+        let channel = service.method_channel(method);
+        let timeout = self.timeout_expr(service, method);
+        let (asyncness, await_suffix) = if method.is_async(self.options.use_async) {
+            ("async ", ".await")
+        } else {
+            ("", "")
+        };
+        let separator = if doc.is_empty() { "" } else { "    ///\n" };
+        let input_type = helpers::message_type(&method.input_type, &Language::Rust);
+        let output_type = helpers::message_type(&method.output_type, &Language::Rust);
+        
+        if !method.client_streaming {
+            return format!(
+                r#"
+{}{}    /// Responses arrive on the returned stream over the `{}` DMXP channel.
+    pub {}fn {}(&self, request: {}) -> Result<dmxp::Stream<{}>, DmxpError> {{
+        let (mut sink, stream) = self.context.open_stream::<{}, {}>({:?}, {}){}?;
+        sink.send(request)?;
+        sink.close()?;
+        Ok(stream)
+    }}
+"#,
+                doc, separator, channel.escape_debug(),
+                asyncness, method.name, input_type, output_type,
+                input_type, output_type, channel, timeout, await_suffix
+            );
+        }
+        
+        let response_note = if method.server_streaming {
+            ""
+        } else {
+            "\n    /// The stream yields a single response once the sink is closed."
+        };
+        format!(
+            r#"
+{}{}    /// Requests are sent through the returned sink and responses arrive on the
+    /// returned stream, over the `{}` DMXP channel.{}
+    pub {}fn {}(&self) -> Result<(dmxp::Sink<{}>, dmxp::Stream<{}>), DmxpError> {{
+        self.context.open_stream::<{}, {}>({:?}, {}){}
+    }}
+"#,
+            doc, separator, channel.escape_debug(), response_note,
+            asyncness, method.name, input_type, output_type,
+            input_type, output_type, channel, timeout, await_suffix
+        )
+    }
+
+    /// Rust expression for the optional timeout passed to the DMXP context
+    fn timeout_expr(&self, service: &Service, method: &Method) -> String {
+        match service.method_timeout_ms(method) {
+            Some(ms) => format!("Some(std::time::Duration::from_millis({}))", ms),
+            None => "None".to_string(),
+        }
+    }
+
     /// Generate DMXP channels
     fn generate_dmxp_channels(&self, proto_file: &ProtoFile) -> Result<String> {
         let mut code = String::new();
@@ -234,30 +490,30 @@ impl {} {{
         code.push_str("// DMXP Channel implementations\n");
         
         for message in &proto_file.messages {
-            if let Some(dmxp_opts) = &message.dmxp_options {
-                if let Some(channel) = &dmxp_opts.channel {
-                    code.push_str(&format!(
-                        r#"
+            if let Some(channel) = message.get_dmxp_channel() {
+                code.push_str(&format!(
+                    r#"
 // Channel: {}
 impl {} {{
-    pub fn publish_to_{}(&self, publisher: &Publisher) -> Result<(), DmxpError> {{
-        publisher.publish("{}", self)
+{}    pub fn publish_to_{}(&self, publisher: &Publisher) -> Result<(), DmxpError> {{
+        publisher.publish({:?}, self)
     }}
     
-    pub fn subscribe_to_{}(callback: impl Fn({}) -> Result<(), DmxpError> + Send + Sync + 'static) -> Result<(), DmxpError> {{
-        dmxp::subscribe("{}", callback)
+{}    pub fn subscribe_to_{}(callback: impl Fn({}) -> Result<(), DmxpError> + Send + Sync + 'static) -> Result<(), DmxpError> {{
+        dmxp::subscribe({:?}, callback)
     }}
 }}
 "#,
-                        channel,
-                        message.name,
-                        channel.replace("-", "_"),
-                        channel,
-                        channel.replace("-", "_"),
-                        message.name,
-                        channel
-                    ));
-                }
+                    channel.escape_debug(),
+                    message.name,
+                    helpers::generate_channel_doc_comment(message, channel, "Publishes", "    ", &Language::Rust),
+                    helpers::channel_ident(channel),
+                    channel,
+                    helpers::generate_channel_doc_comment(message, channel, "Subscribes to", "    ", &Language::Rust),
+                    helpers::channel_ident(channel),
+                    message.name,
+                    channel
+                ));
             }
         }
         
@@ -268,26 +524,60 @@ impl {} {{
     fn convert_field_type(&self, field: &Field) -> String {
         let base_type = helpers::convert_field_type(&field.field_type, &crate::templateGen::template_generator::Language::Rust);
         
+        // Maps are already a collection; the label does not wrap them
+        if let FieldType::Map(_, _) = field.field_type {
+            return base_type;
+        }
+        
         match field.label {
-            FieldLabel::Optional => format!("Option<{}>", base_type),
+            FieldLabel::Optional if self.tracks_presence(field) => format!("Option<{}>", base_type),
             FieldLabel::Repeated => format!("Vec<{}>", base_type),
-            FieldLabel::Required => base_type,
+            FieldLabel::Optional | FieldLabel::Required => base_type,
         }
     }
 
+    /// Whether an optional field is wrapped in `Option`: fields with implicit
+    /// presence are not, and neither is `google.protobuf.NullValue`, whose
+    /// only value is `()`
+    fn tracks_presence(&self, field: &Field) -> bool {
+        let null_value = match &field.field_type {
+            FieldType::Message(name) | FieldType::Enum(name) => helpers::well_known_type(name) == Some("NullValue"),
+            _ => false,
+        };
+        !field.has_implicit_presence() && !null_value
+    }
+
     /// Get default value for a field
     fn get_default_value(&self, field: &Field) -> String {
+        if let FieldType::Map(_, _) = field.field_type {
+            return "HashMap::new()".to_string();
+        }
+        
+        // Fields with implicit presence hold their zero value like required ones
         match field.label {
-            FieldLabel::Optional => "None".to_string(),
+            FieldLabel::Optional if self.tracks_presence(field) => "None".to_string(),
             FieldLabel::Repeated => "Vec::new()".to_string(),
-            FieldLabel::Required => match field.field_type {
+            _ if field.default_value.is_some() => {
+                self.declared_default(field).unwrap_or_else(|| "Default::default()".to_string())
+            }
+            _ => match field.field_type {
                 FieldType::String => "String::new()".to_string(),
                 FieldType::Bytes => "Vec::new()".to_string(),
                 FieldType::Int32 | FieldType::Int64 | FieldType::Uint32 | FieldType::Uint64 => "0".to_string(),
                 FieldType::Float | FieldType::Double => "0.0".to_string(),
                 FieldType::Bool => "false".to_string(),
+                // SystemTime has no Default
+                FieldType::Message(ref name) if helpers::well_known_type(name) == Some("Timestamp") => {
+                    "std::time::UNIX_EPOCH".to_string()
+                }
                 _ => "Default::default()".to_string(),
             },
         }
     }
 }
+
+/// `bytes` as a Rust `Vec<u8>` expression (e.g. `b"\xff\x00".to_vec()`)
+fn rust_bytes_literal(bytes: &[u8]) -> String {
+    let escaped: String = bytes.iter().copied().flat_map(std::ascii::escape_default).map(char::from).collect();
+    format!("b\"{}\".to_vec()", escaped)
+}
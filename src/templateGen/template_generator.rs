@@ -1,6 +1,5 @@
 use crate::ast::*;
 use anyhow::Result;
-use std::collections::HashMap;
 
 /// Supported target languages for code generation
 #[derive(Debug, Clone, PartialEq)]
@@ -106,6 +105,154 @@ pub mod helpers {
         }
     }
 
+    /// Identifier generated for a message or enum referenced as `name` (e.g.
+    /// `User` or `.acme.v1.User`): its last component
+    ///
+    /// References to nested types are renamed by [`hoist_nested_types`]
+    /// first, so only types of other packages reach this under a longer name.
+    pub fn type_ident(name: &str) -> &str {
+        name.rsplit('.').next().unwrap_or(name)
+    }
+
+    /// Copy of `file` with nested messages and enums moved to the top level
+    /// under their path joined by underscores (e.g. `Outer.Inner` becomes
+    /// `Outer_Inner`), so that nested types sharing a simple name stay apart
+    ///
+    /// Every reference resolved to a type of the file's package is renamed
+    /// to match, which is how the generators tell `Outer.Inner` from
+    /// `Other.Inner`.
+    pub fn hoist_nested_types(file: &ProtoFile) -> ProtoFile {
+        let mut hoisted = file.clone();
+        hoisted.messages.clear();
+        for message in &file.messages {
+            hoist_message(message, "", &mut hoisted);
+        }
+        let package = file.package.as_str();
+        for message in &mut hoisted.messages {
+            let fields = message.fields.iter_mut().chain(message.oneofs.iter_mut().flat_map(|o| o.fields.iter_mut()));
+            for field in fields {
+                let field_type = match &mut field.field_type {
+                    FieldType::Map(_, value) => value.as_mut(),
+                    other => other,
+                };
+                if let FieldType::Message(name) | FieldType::Enum(name) = field_type {
+                    rename_reference(name, field.resolved_type.as_deref(), package);
+                }
+            }
+        }
+        for method in hoisted.services.iter_mut().flat_map(|s| s.methods.iter_mut()) {
+            rename_reference(&mut method.input_type, method.resolved_input_type.as_deref(), package);
+            rename_reference(&mut method.output_type, method.resolved_output_type.as_deref(), package);
+        }
+        hoisted
+    }
+
+    /// Add `message` and everything nested in it to `file`, with `prefix`
+    /// (e.g. "Outer_") in front of their names
+    fn hoist_message(message: &Message, prefix: &str, file: &mut ProtoFile) {
+        let mut message = message.clone();
+        message.name = format!("{}{}", prefix, message.name);
+        let prefix = format!("{}_", message.name);
+        for mut enum_def in std::mem::take(&mut message.nested_enums) {
+            enum_def.name = format!("{}{}", prefix, enum_def.name);
+            file.enums.push(enum_def);
+        }
+        let nested = std::mem::take(&mut message.nested_messages);
+        file.messages.push(message);
+        for nested in &nested {
+            hoist_message(nested, &prefix, file);
+        }
+    }
+
+    /// Rename a reference resolved to `resolved` (e.g. ".acme.v1.Outer.Inner")
+    /// to its hoisted name (e.g. `Outer_Inner`) if the type is in `package`
+    fn rename_reference(name: &mut String, resolved: Option<&str>, package: &str) {
+        let Some(full_name) = resolved.map(|r| r.trim_start_matches('.')) else {
+            return;
+        };
+        let path = match package {
+            "" => Some(full_name),
+            _ => full_name.strip_prefix(package).and_then(|rest| rest.strip_prefix('.')),
+        };
+        if let Some(path) = path
+            && well_known_type(name).is_none()
+        {
+            *name = path.replace('.', "_");
+        }
+    }
+
+    /// Well-known messages (and the `NullValue` enum) bundled in
+    /// `proto/google/protobuf`, which map to language types instead of generated ones
+    const WELL_KNOWN_TYPES: [&str; 17] = [
+        "Any", "Duration", "Empty", "Struct", "Value", "ListValue", "NullValue", "Timestamp", "DoubleValue",
+        "FloatValue", "Int64Value", "UInt64Value", "Int32Value", "UInt32Value", "BoolValue", "StringValue",
+        "BytesValue",
+    ];
+
+    /// Well-known type (e.g. "Timestamp") that a message or enum referenced as `name`
+    /// (e.g. `google.protobuf.Timestamp`) is, if it is one
+    pub fn well_known_type(name: &str) -> Option<&str> {
+        let name = name.trim_start_matches('.').strip_prefix("google.protobuf.")?;
+        WELL_KNOWN_TYPES.contains(&name).then_some(name)
+    }
+
+    /// Type of an RPC request or response referenced as `name`, which the
+    /// Go generator passes by pointer
+    pub fn message_type(name: &str, language: &Language) -> String {
+        match (well_known_type(name), language) {
+            (Some(well_known), Language::Rust) => well_known_rust_type(well_known).to_string(),
+            (Some(well_known), Language::Go) => well_known_go_type(well_known).trim_start_matches('*').to_string(),
+            (None, _) => type_ident(name).to_string(),
+        }
+    }
+
+    /// Rust type of a well-known message; wrappers are their scalar, which
+    /// fields wrap in `Option` like any message
+    fn well_known_rust_type(name: &str) -> &'static str {
+        match name {
+            "Timestamp" => "std::time::SystemTime",
+            "Duration" => "std::time::Duration",
+            "Empty" | "NullValue" => "()",
+            "Struct" => "HashMap<String, serde_json::Value>",
+            "ListValue" => "Vec<serde_json::Value>",
+            "DoubleValue" => "f64",
+            "FloatValue" => "f32",
+            "Int64Value" => "i64",
+            "UInt64Value" => "u64",
+            "Int32Value" => "i32",
+            "UInt32Value" => "u32",
+            "BoolValue" => "bool",
+            "StringValue" => "String",
+            "BytesValue" => "Vec<u8>",
+            // Any keeps its JSON form, an object with an "@type" member
+            _ => "serde_json::Value",
+        }
+    }
+
+    /// Go type of a well-known message; wrappers are pointers, so that an
+    /// unset value is nil
+    fn well_known_go_type(name: &str) -> &'static str {
+        match name {
+            "Timestamp" => "time.Time",
+            "Duration" => "time.Duration",
+            "Empty" => "struct{}",
+            "Any" => "json.RawMessage",
+            "Struct" => "map[string]interface{}",
+            "ListValue" => "[]interface{}",
+            "DoubleValue" => "*float64",
+            "FloatValue" => "*float32",
+            "Int64Value" => "*int64",
+            "UInt64Value" => "*uint64",
+            "Int32Value" => "*int32",
+            "UInt32Value" => "*uint32",
+            "BoolValue" => "*bool",
+            "StringValue" => "*string",
+            "BytesValue" => "[]byte",
+            // NullValue, like a JSON null in a Value, is a nil interface
+            _ => "interface{}",
+        }
+    }
+
     /// Convert to Rust type
     fn convert_to_rust_type(field_type: &FieldType) -> String {
         match field_type {
@@ -124,8 +271,14 @@ pub mod helpers {
             FieldType::Bool => "bool".to_string(),
             FieldType::String => "String".to_string(),
             FieldType::Bytes => "Vec<u8>".to_string(),
-            FieldType::Message(name) => name.clone(),
-            FieldType::Enum(name) => name.clone(),
+            FieldType::Message(name) => match well_known_type(name) {
+                Some(well_known) => well_known_rust_type(well_known).to_string(),
+                None => type_ident(name).to_string(),
+            },
+            FieldType::Enum(name) => match well_known_type(name) {
+                Some(well_known) => well_known_rust_type(well_known).to_string(),
+                None => type_ident(name).to_string(),
+            },
             FieldType::Map(key_type, value_type) => {
                 format!("HashMap<{}, {}>", 
                     convert_to_rust_type(key_type), 
@@ -152,8 +305,14 @@ pub mod helpers {
             FieldType::Bool => "bool".to_string(),
             FieldType::String => "string".to_string(),
             FieldType::Bytes => "[]byte".to_string(),
-            FieldType::Message(name) => format!("*{}", name),
-            FieldType::Enum(name) => name.clone(),
+            FieldType::Message(name) => match well_known_type(name) {
+                Some(well_known) => well_known_go_type(well_known).to_string(),
+                None => format!("*{}", type_ident(name)),
+            },
+            FieldType::Enum(name) => match well_known_type(name) {
+                Some(well_known) => well_known_go_type(well_known).to_string(),
+                None => type_ident(name).to_string(),
+            },
             FieldType::Map(key_type, value_type) => {
                 format!("map[{}]{}", 
                     convert_to_go_type(key_type), 
@@ -181,17 +340,33 @@ pub mod helpers {
         }
     }
 
+    /// Rust keywords, which a field name must not be written as directly
+    const RUST_KEYWORDS: [&str; 52] = [
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false",
+        "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+        "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+        "abstract", "become", "box", "do", "final", "gen", "macro", "override", "priv", "try", "typeof",
+        "unsized", "virtual", "yield",
+    ];
+
     /// Generate field name in language-specific style
+    ///
+    /// Rust keywords become raw identifiers (`type` -> `r#type`), except
+    /// those that cannot be raw, which get a trailing underscore (`self_`)
     pub fn convert_field_name(name: &str, language: &Language) -> String {
         match language {
-            Language::Rust => name.to_string(), // snake_case
+            Language::Rust => match name {
+                "self" | "Self" | "super" | "crate" => format!("{}_", name),
+                _ if RUST_KEYWORDS.contains(&name) => format!("r#{}", name),
+                _ => name.to_string(), // snake_case
+            },
             Language::Go => to_pascal_case(name), // PascalCase
 
         }
     }
 
     /// Convert string to PascalCase
-    fn to_pascal_case(s: &str) -> String {
+    pub fn to_pascal_case(s: &str) -> String {
         s.split('_')
             .map(|word| {
                 let mut chars = word.chars();
@@ -203,6 +378,95 @@ pub mod helpers {
             .collect()
     }
 
+    /// Quote `text` as a string literal in the target language
+    ///
+    /// # Arguments
+    /// * `text` - The string to quote
+    /// * `language` - The target language
+    ///
+    /// # Returns
+    /// A Rust or Go (interpreted) string literal with quotes, backslashes and
+    /// control characters escaped
+    pub fn string_literal(text: &str, language: &Language) -> String {
+        match language {
+            Language::Rust => format!("{:?}", text),
+            Language::Go => {
+                let mut literal = String::from("\"");
+                for c in text.chars() {
+                    match c {
+                        '"' => literal.push_str("\\\""),
+                        '\\' => literal.push_str("\\\\"),
+                        '\n' => literal.push_str("\\n"),
+                        '\r' => literal.push_str("\\r"),
+                        '\t' => literal.push_str("\\t"),
+                        c if c.is_control() => literal.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => literal.push(c),
+                    }
+                }
+                literal.push('"');
+                literal
+            }
+        }
+    }
+
+    /// Turn a DMXP channel name into an identifier fragment
+    ///
+    /// Every character that may not appear in an identifier becomes `_`, so
+    /// `user-updates` and `feed.lookup` yield `user_updates` and `feed_lookup`.
+    pub fn channel_ident(channel: &str) -> String {
+        channel
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+
+    /// Render proto comments as documentation comments for the target language
+    ///
+    /// Leading and trailing comments are emitted (`///` for Rust, `//` for Go);
+    /// detached comments are dropped, as protoc's own generators do.
+    pub fn generate_doc_comment(comments: &Comments, indent: &str, language: &Language) -> String {
+        let texts: Vec<&String> = comments.leading.iter().chain(comments.trailing.iter()).collect();
+        let lines: Vec<&str> = texts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, text)| {
+                // Keep a blank doc line between the leading and trailing comment
+                let separator = if i > 0 { vec![""] } else { Vec::new() };
+                separator.into_iter().chain(text.lines())
+            })
+            .collect();
+        render_doc_lines(&lines, indent, language)
+    }
+
+    /// Doc comment for a generated publish/subscribe API, describing the channel
+    /// and what the message carries
+    pub fn generate_channel_doc_comment(message: &Message, channel: &str, action: &str, indent: &str, language: &Language) -> String {
+        let summary = format!("{} `{}` on the `{}` DMXP channel.", action, message.name, channel.escape_debug());
+        let mut lines = vec![summary.as_str()];
+        if let Some(leading) = &message.comments.leading {
+            lines.push("");
+            lines.extend(leading.lines());
+        }
+        render_doc_lines(&lines, indent, language)
+    }
+
+    fn render_doc_lines(lines: &[&str], indent: &str, language: &Language) -> String {
+        let marker = match language {
+            Language::Rust => "///",
+            Language::Go => "//",
+        };
+        lines
+            .iter()
+            .map(|line| {
+                if line.is_empty() {
+                    format!("{}{}\n", indent, marker)
+                } else {
+                    format!("{}{} {}\n", indent, marker, line)
+                }
+            })
+            .collect()
+    }
+
     /// Generate DMXP channel code for a message
     pub fn generate_dmxp_channel_code(message: &Message, language: &Language) -> String {
         if let Some(dmxp_opts) = &message.dmxp_options {
@@ -224,29 +488,40 @@ pub mod helpers {
         format!(
             r#"
 impl {} {{
-    pub fn publish(&self, publisher: &dmxp::Publisher) -> Result<(), dmxp::Error> {{
-        publisher.publish("{}", self)
+{}    pub fn publish(&self, publisher: &dmxp::Publisher) -> Result<(), dmxp::Error> {{
+        publisher.publish({:?}, self)
     }}
     
-    pub fn subscribe(callback: impl Fn({}) -> Result<(), dmxp::Error> + Send + Sync + 'static) -> Result<(), dmxp::Error> {{
-        dmxp::subscribe("{}", callback)
+{}    pub fn subscribe(callback: impl Fn({}) -> Result<(), dmxp::Error> + Send + Sync + 'static) -> Result<(), dmxp::Error> {{
+        dmxp::subscribe({:?}, callback)
     }}
 }}"#,
-            message.name, channel, message.name, channel
+            message.name,
+            generate_channel_doc_comment(message, channel, "Publishes", "    ", &Language::Rust),
+            channel,
+            generate_channel_doc_comment(message, channel, "Subscribes to", "    ", &Language::Rust),
+            message.name,
+            channel
         )
     }
 
     fn generate_go_dmxp_code(message: &Message, channel: &str) -> String {
         format!(
             r#"
-func (m *{}) Publish(publisher *dmxp.Publisher) error {{
-    return publisher.Publish("{}", m)
+{}func (m *{}) Publish(publisher *dmxp.Publisher) error {{
+    return publisher.Publish({}, m)
 }}
 
-func Subscribe{}(callback func(*{}) error) error {{
-    return dmxp.Subscribe("{}", callback)
+{}func Subscribe{}(callback func(*{}) error) error {{
+    return dmxp.Subscribe({}, callback)
 }}"#,
-            message.name, channel, message.name, message.name, channel
+            generate_channel_doc_comment(message, channel, "Publish publishes", "", &Language::Go),
+            message.name,
+            string_literal(channel, &Language::Go),
+            generate_channel_doc_comment(message, channel, &format!("Subscribe{} subscribes to", message.name), "", &Language::Go),
+            message.name,
+            message.name,
+            string_literal(channel, &Language::Go)
         )
     }
 
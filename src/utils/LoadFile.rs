@@ -20,7 +20,7 @@ mod tests {
     #[test]
     fn test_load_file() {
         println!("Loading file: test.proto");
-        let result = LoadFile("/Users/neeraj/CodeSection/DMXP-Protobuf-Plugin/test.proto");
+        let result = LoadFile("test.proto");
         println!("Result: {:?}", result);
         assert!(result.is_ok());
     }
@@ -0,0 +1,691 @@
+use DMXP_Protobuf_Plugin::ast::ProtoFile;
+use DMXP_Protobuf_Plugin::parser::parser::ProtoParser;
+use DMXP_Protobuf_Plugin::templateGen::{Language, TemplateGenerator};
+
+fn parse_source(content: &str) -> ProtoFile {
+    let mut parser = ProtoParser::new(content.to_string());
+    parser.parse().expect("Parsing failed")
+}
+
+fn generate(content: &str, language: Language) -> String {
+    TemplateGenerator::new(language)
+        .generate(&parse_source(content))
+        .expect("Generation failed")
+}
+
+const DOCUMENTED_PROTO: &str = r#"syntax = "proto3";
+package demo;
+
+// User data message that will be published to a channel
+message UserData {
+  option (dmxp_channel) = "user_updates";
+
+  // Unique user id
+  string user_id = 1;
+}
+
+// Users over DMXP
+service UserService {
+  // Look up a user
+  rpc GetUser(UserData) returns (UserData);
+}
+"#;
+
+#[test]
+fn test_rust_doc_comments() {
+    let code = generate(DOCUMENTED_PROTO, Language::Rust);
+
+    assert!(code.contains("/// User data message that will be published to a channel\n#[derive(Debug, Clone, Serialize, Deserialize)]\npub struct UserData {"));
+    assert!(code.contains("    /// Unique user id\n    pub user_id:"));
+    assert!(code.contains("/// Users over DMXP\n#[derive(Debug, Clone)]\npub struct UserServiceService {"));
+    assert!(code.contains("    /// Look up a user\n    pub async fn GetUser("));
+    assert!(code.contains(
+        "    /// Publishes `UserData` on the `user_updates` DMXP channel.\n    ///\n    /// User data message that will be published to a channel\n    pub fn publish_to_user_updates("
+    ));
+}
+
+#[test]
+fn test_go_doc_comments() {
+    let code = generate(DOCUMENTED_PROTO, Language::Go);
+
+    assert!(code.contains("// User data message that will be published to a channel\ntype UserData struct {"));
+    assert!(code.contains("\t// Unique user id\n\tUserId "));
+    assert!(code.contains("// Users over DMXP\ntype UserServiceService struct {"));
+    assert!(code.contains("// Look up a user\nfunc (s *UserServiceService) GetUser("));
+    assert!(code.contains(
+        "// SubscribeToUserUpdates subscribes to `UserData` on the `user_updates` DMXP channel.\n//\n// User data message that will be published to a channel\nfunc SubscribeToUserUpdates("
+    ));
+}
+
+const ONEOF_PROTO: &str = r#"syntax = "proto3";
+package demo;
+
+message UserCreated { string user_id = 1; }
+
+message UserEvent {
+  string event_id = 1;
+  // What happened to the user
+  oneof payload {
+    UserCreated created = 2;
+    // Id of the removed user
+    string deleted_id = 3;
+  }
+}
+"#;
+
+#[test]
+fn test_rust_oneof_enum() {
+    let code = generate(ONEOF_PROTO, Language::Rust);
+
+    assert!(code.contains("    /// What happened to the user\n    #[serde(flatten)]\n    pub payload: Option<UserEventPayload>,\n"));
+    assert!(code.contains("            payload: None,\n"));
+    assert!(code.contains("pub enum UserEventPayload {\n    #[serde(rename = \"created\")]\n    Created(UserCreated),\n"));
+    assert!(code.contains("    /// Id of the removed user\n    #[serde(rename = \"deleted_id\")]\n    DeletedId(String),\n"));
+    assert!(!code.contains("pub created:"), "oneof members must not become struct fields");
+}
+
+#[test]
+fn test_rust_keyword_names_are_escaped() {
+    let source = r#"syntax = "proto2";
+message Event {
+  optional string match = 1 [default = "all"];
+  optional string super = 2;
+  oneof type {
+    string name = 3;
+    int32 self = 4;
+  }
+}"#;
+    let code = generate(source, Language::Rust);
+
+    assert!(code.contains("    #[serde(flatten)]\n    pub r#type: Option<EventType>,\n"));
+    assert!(code.contains("            r#type: None,\n"));
+    assert!(code.contains("    pub r#match: Option<String>,\n"));
+    assert!(code.contains("    pub fn match_or_default(&self) -> String {\n        self.r#match.clone()"));
+    assert!(code.contains("    #[serde(rename = \"super\")]\n    pub super_: Option<String>,\n"));
+    assert!(code.contains("    #[serde(rename = \"self\")]\n    Self_(i32),\n"));
+    assert!(!code.contains("rename = \"match\""));
+}
+
+#[test]
+fn test_go_oneof_wrappers() {
+    let code = generate(ONEOF_PROTO, Language::Go);
+
+    assert!(code.contains("\tPayload isUserEvent_Payload `json:\"-\"`\n"));
+    assert!(code.contains("type isUserEvent_Payload interface {\n\tisUserEvent_Payload()\n}\n"));
+    assert!(code.contains("type UserEvent_Created struct {\n\tCreated *UserCreated `json:\"created\"`\n}\n"));
+    assert!(code.contains("func (*UserEvent_DeletedId) isUserEvent_Payload() {}\n"));
+    assert!(code.contains("func (m *UserEvent) MarshalJSON() ([]byte, error) {"));
+    assert!(code.contains("\tcase *UserEvent_DeletedId:\n"));
+    assert!(code.contains("\t\tm.Payload = &UserEvent_Created{Created: member}\n"));
+}
+
+#[test]
+fn test_go_oneof_wrapper_avoids_nested_type_names() {
+    let source = r#"syntax = "proto3";
+message Outer {
+  message Text { string s = 1; }
+  oneof payload {
+    Text text = 1;
+    string note = 2;
+  }
+}"#;
+    let code = generate(source, Language::Go);
+
+    assert!(code.contains("type Outer_Text struct {\n\tS string `json:\"s\"`\n}\n"));
+    assert!(code.contains("type Outer_Text_ struct {\n\tText *Outer_Text `json:\"text\"`\n}\n"));
+    assert!(code.contains("func (*Outer_Text_) isOuter_Payload() {}\n"));
+    assert!(code.contains("\tcase *Outer_Text_:\n"));
+    assert!(code.contains("\t\tm.Payload = &Outer_Text_{Text: member}\n"));
+    assert!(code.contains("type Outer_Note struct {\n"));
+    assert_eq!(code.matches("type Outer_Text struct").count(), 1);
+}
+
+#[test]
+fn test_map_fields_generation() {
+    let source = "syntax = \"proto3\"; message Stats { map<string, int64> counters = 1; map<int32, Stats> children = 2; }";
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("    pub counters: HashMap<String, i64>,\n"));
+    assert!(rust.contains("    pub children: HashMap<i32, Stats>,\n"));
+    assert!(rust.contains("            counters: HashMap::new(),\n"));
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("\tCounters map[string]int64 `json:\"counters\"`\n"));
+    assert!(go.contains("\tChildren map[int32]*Stats `json:\"children\"`\n"));
+    assert!(go.contains("\t\tCounters: make(map[string]int64),\n"));
+}
+
+#[test]
+fn test_standard_field_options_generation() {
+    let source = r#"syntax = "proto3";
+message User {
+  string user_id = 1 [json_name = "uid"];
+  // Old name
+  string legacy = 2 [deprecated = true];
+}"#;
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("    #[serde(rename = \"uid\")]\n    pub user_id: String,\n"));
+    assert!(rust.contains("    /// Old name\n    #[deprecated]\n    pub legacy: String,\n"));
+    assert!(rust.contains("#[allow(deprecated)]\nimpl User {"));
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("\tUserId string `json:\"uid\"`\n"));
+    assert!(go.contains("\t// Old name\n\t//\n\t// Deprecated: Marked as deprecated in the proto file.\n\tLegacy string `json:\"legacy\"`\n"));
+}
+
+#[test]
+fn test_packed_fields_generation() {
+    let source = r#"syntax = "proto3";
+message Scores {
+  // Latest scores
+  repeated int32 values = 1;
+  repeated int32 unpacked = 2 [packed = false];
+  repeated string names = 3;
+}"#;
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("    /// Latest scores\n    ///\n    /// Encoded as a packed repeated field.\n    pub values: Vec<i32>,\n"));
+    assert!(rust.contains("    pub values: Vec<i32>,\n    pub unpacked: Vec<i32>,\n"));
+    assert!(rust.contains("\n    pub names: Vec<String>,\n"));
+    assert_eq!(rust.matches("packed repeated field").count(), 1);
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("\t// Latest scores\n\t//\n\t// Encoded as a packed repeated field.\n\tValues []int32 `json:\"values\"`\n"));
+    assert_eq!(go.matches("packed repeated field").count(), 1);
+
+    // proto2 only packs fields that ask for it
+    let proto2 = r#"syntax = "proto2";
+message Scores {
+  repeated int32 values = 1 [packed = true];
+  repeated int32 plain = 2;
+}"#;
+    let rust = generate(proto2, Language::Rust);
+    assert!(rust.contains("    /// Encoded as a packed repeated field.\n    pub values: Vec<i32>,\n"));
+    assert_eq!(rust.matches("packed repeated field").count(), 1);
+    let go = generate(proto2, Language::Go);
+    assert!(go.contains("\t// Encoded as a packed repeated field.\n\tValues []int32 `json:\"values\"`\n"));
+    assert_eq!(go.matches("packed repeated field").count(), 1);
+}
+
+const STREAMING_PROTO: &str = r#"syntax = "proto3";
+message Req { string id = 1; }
+message Resp { string id = 1; }
+service Feed {
+  option (dmxp_channels) = "feed";
+  rpc Watch(Req) returns (stream Resp);
+  rpc Chat(stream Req) returns (stream Resp);
+}
+"#;
+
+#[test]
+fn test_rust_streaming_methods() {
+    use DMXP_Protobuf_Plugin::templateGen::template_generator::GeneratorOptions;
+
+    let code = generate(STREAMING_PROTO, Language::Rust);
+
+    assert!(code.contains("pub async fn Watch(&self, request: Req) -> Result<dmxp::Stream<Resp>, DmxpError> {"));
+    assert!(code.contains("self.context.open_stream::<Req, Resp>(\"feed.Watch\", None).await?;"));
+    assert!(code.contains("pub async fn Chat(&self) -> Result<(dmxp::Sink<Req>, dmxp::Stream<Resp>), DmxpError> {"));
+    assert!(code.contains("self.context.open_stream::<Req, Resp>(\"feed.Chat\", None).await\n"));
+
+    let options = GeneratorOptions { use_async: false, ..GeneratorOptions::default() };
+    let code = TemplateGenerator::new_with_options(Language::Rust, options)
+        .generate(&parse_source(STREAMING_PROTO))
+        .expect("Generation failed");
+    assert!(code.contains("pub fn Watch(&self, request: Req) -> Result<dmxp::Stream<Resp>, DmxpError> {"));
+    assert!(code.contains("self.context.open_stream::<Req, Resp>(\"feed.Watch\", None)?;"));
+    assert!(code.contains("self.context.open_stream::<Req, Resp>(\"feed.Chat\", None)\n"));
+}
+
+#[test]
+fn test_go_streaming_methods() {
+    let code = generate(STREAMING_PROTO, Language::Go);
+
+    assert!(code.contains("type FeedWatchStream struct {"));
+    assert!(code.contains("func (s *FeedService) Watch(ctx context.Context, request *Req) (*FeedWatchStream, error) {"));
+    assert!(code.contains("s.context.OpenStream(ctx, \"feed.Watch\")"));
+    assert!(code.contains("func (s *FeedService) Chat(ctx context.Context) (*FeedChatStream, error) {"));
+    assert!(code.contains("\tSend chan<- *Req\n"));
+    assert_eq!(code.matches("func dmxpRecvLoop[T any]").count(), 1);
+}
+
+#[test]
+fn test_go_bidi_stream_closes_quietly() {
+    let code = generate(STREAMING_PROTO, Language::Go);
+
+    // The receive loop closes `ended` before it cancels the context, so the
+    // send loop can tell a normal close from a caller's cancellation
+    assert!(code.contains("    go dmxpRecvLoop(ctx, cancel, stream, recv, ended, errs)\n"));
+    assert!(code.contains("    go dmxpSendLoop(ctx, stream, send, ended, errs)\n"));
+    assert!(code.contains("    defer cancel()\n    defer close(ended)\n    defer close(out)\n"));
+    assert!(code.contains("        case <-ctx.Done():\n            select {\n            case <-ended:\n"));
+    assert!(code.contains("            default:\n                errs <- ctx.Err()\n            }\n            return\n"));
+}
+
+const METHOD_OPTIONS_PROTO: &str = r#"syntax = "proto3";
+message Req { string id = 1; }
+service Feed {
+  option (dmxp_channels) = "feed";
+  option (dmxp_timeout_ms) = 1000;
+  rpc Get(Req) returns (Req) {
+    option (dmxp_method_channel) = "feed.lookup";
+    option (dmxp_timeout_ms) = 50;
+  }
+  rpc Put(Req) returns (Req) {
+    option (dmxp_async) = false;
+  }
+}
+"#;
+
+#[test]
+fn test_rust_method_options() {
+    let code = generate(METHOD_OPTIONS_PROTO, Language::Rust);
+
+    assert!(code.contains("pub async fn Get(&self, request: Req) -> Result<Req, DmxpError> {"));
+    assert!(code.contains(
+        "self.context.request::<Req, Req>(\"feed.lookup\", request, Some(std::time::Duration::from_millis(50))).await\n"
+    ));
+    assert!(code.contains("pub fn Put(&self, request: Req) -> Result<Req, DmxpError> {"));
+    assert!(code.contains(
+        "self.context.request::<Req, Req>(\"feed.Put\", request, Some(std::time::Duration::from_millis(1000)))\n"
+    ));
+}
+
+#[test]
+fn test_go_method_options() {
+    let code = generate(METHOD_OPTIONS_PROTO, Language::Go);
+
+    assert!(code.contains("\t\"time\"\n"));
+    assert!(code.contains("    ctx, cancel := context.WithTimeout(ctx, 50*time.Millisecond)\n    defer cancel()\n"));
+    assert!(code.contains("s.context.Request(ctx, \"feed.lookup\", request, response)"));
+    assert!(code.contains("context.WithTimeout(ctx, 1000*time.Millisecond)"));
+    assert!(code.contains("s.context.Request(ctx, \"feed.Put\", request, response)"));
+}
+
+#[test]
+fn test_channel_names_are_escaped() {
+    let source = r#"syntax = "proto3";
+message Event {
+  option (dmxp_channel) = "ev\"ents.all";
+  string id = 1;
+}
+service Feed {
+  option (dmxp_channels) = "fe\\ed";
+  rpc Get(Event) returns (Event);
+  rpc Watch(Event) returns (stream Event);
+}
+"#;
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("publisher.publish(\"ev\\\"ents.all\", self)"), "{}", rust);
+    assert!(rust.contains("dmxp::subscribe(\"ev\\\"ents.all\", callback)"));
+    assert!(rust.contains("pub fn publish_to_ev_ents_all(&self, publisher: &Publisher)"));
+    assert!(rust.contains("pub fn subscribe_to_ev_ents_all(callback:"));
+    assert!(rust.contains("self.context.request::<Event, Event>(\"fe\\\\ed.Get\", request, None)"));
+    assert!(rust.contains("self.context.open_stream::<Event, Event>(\"fe\\\\ed.Watch\", None)"));
+    assert!(!rust.contains("ev\"ents"));
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("publisher.Publish(\"ev\\\"ents.all\", m)"), "{}", go);
+    assert!(go.contains("dmxp.Subscribe(\"ev\\\"ents.all\", callback)"));
+    assert!(go.contains("func (m *Event) PublishToEvEntsAll(publisher *dmxp.Publisher) error {"));
+    assert!(go.contains("s.context.Request(ctx, \"fe\\\\ed.Get\", request, response)"));
+    assert!(go.contains("s.context.OpenStream(ctx, \"fe\\\\ed.Watch\")"));
+    assert!(!go.contains("ev\"ents"));
+}
+
+const PROTO2_DEFAULTS_PROTO: &str = r#"syntax = "proto2";
+message Legacy {
+  required string id = 1;
+  required int32 level = 2 [default = 7];
+  optional int32 retries = 3 [default = 3];
+  optional string label = 4 [default = "none"];
+}
+"#;
+
+#[test]
+fn test_rust_proto2_required_and_defaults() {
+    let code = generate(PROTO2_DEFAULTS_PROTO, Language::Rust);
+
+    assert!(code.contains("    pub id: String,\n    pub level: i32,\n    pub retries: Option<i32>,\n"));
+    assert!(code.contains("            id: String::new(),\n            level: 7,\n            retries: None,\n"));
+    assert!(code.contains("    pub fn retries_or_default(&self) -> i32 {\n        self.retries.unwrap_or(3)\n    }"));
+    assert!(code.contains("self.label.clone().unwrap_or_else(|| \"none\".to_string())"));
+    assert!(!code.contains("fn level_or_default"));
+}
+
+#[test]
+fn test_go_proto2_required_and_defaults() {
+    let code = generate(PROTO2_DEFAULTS_PROTO, Language::Go);
+
+    assert!(code.contains("\tId string `json:\"id\"`\n\tLevel int32 `json:\"level\"`\n\tRetries *int32 `json:\"retries\"`\n"));
+    assert!(code.contains("\t\tLevel: Default_Legacy_Level,\n"));
+    assert!(code.contains("const Default_Legacy_Level int32 = 7\n"));
+    assert!(code.contains("const Default_Legacy_Retries int32 = 3\n"));
+    assert!(code.contains("func (m *Legacy) GetRetries() int32 {"));
+    assert!(code.contains("const Default_Legacy_Label string = \"none\"\n"));
+    assert!(!code.contains("GetLevel"));
+}
+
+#[test]
+fn test_go_proto2_enum_and_bytes_fields() {
+    let source = r#"syntax = "proto2";
+import "shades.proto";
+enum Color { COLOR_RED = 0; COLOR_GREEN = 1; }
+message Paint {
+  optional Color color = 1 [default = COLOR_GREEN];
+  required Color base = 2;
+  optional bytes blob = 3 [default = "ab"];
+  optional shades.Shade shade = 4 [default = DARK];
+}
+"#;
+    let code = generate(source, Language::Go);
+
+    assert!(code.contains("\tColor *Color `json:\"color\"`\n\tBase Color `json:\"base\"`\n"), "{}", code);
+    assert!(code.contains("\tBlob []byte `json:\"blob\"`\n"));
+    assert!(code.contains("\tShade *Shade `json:\"shade\"`\n"));
+    assert!(code.contains("\t\tColor: nil,\n\t\tBase: 0,\n\t\tBlob: nil,\n"));
+    assert!(code.contains("const Default_Paint_Color Color = COLOR_COLOR_GREEN\n"));
+    assert!(code.contains("func (m *Paint) GetColor() Color {\n    if m != nil && m.Color != nil {\n        return *m.Color\n"));
+    assert!(code.contains("var Default_Paint_Blob []byte = []byte(\"ab\")\n"));
+    assert!(code.contains("func (m *Paint) GetBlob() []byte {\n    if m != nil && m.Blob != nil {\n        return m.Blob\n"));
+    assert!(code.contains("const Default_Paint_Shade Shade = SHADE_DARK\n"));
+    assert!(code.contains("func (m *Paint) GetShade() Shade {\n    if m != nil && m.Shade != nil {\n        return *m.Shade\n"));
+}
+
+#[test]
+fn test_go_package_option() {
+    use DMXP_Protobuf_Plugin::templateGen::template_generator::GeneratorOptions;
+
+    let source = "syntax = \"proto3\";\npackage acme.events;\noption go_package = \"github.com/acme/events;eventspb\";\nmessage A {}\n";
+    let code = generate(source, Language::Go);
+    assert!(code.contains("\npackage eventspb // import \"github.com/acme/events\"\n"));
+
+    let options = GeneratorOptions { package_override: Some("custom".to_string()), ..GeneratorOptions::default() };
+    let code = TemplateGenerator::new_with_options(Language::Go, options)
+        .generate(&parse_source(source))
+        .expect("Generation failed");
+    assert!(code.contains("\npackage custom // import \"github.com/acme/events\"\n"));
+
+    let code = generate("syntax = \"proto3\";\npackage acme.events;\nmessage A {}\n", Language::Go);
+    assert!(code.contains("\npackage acme_events\n"));
+}
+
+const ALIAS_ENUM_PROTO: &str = r#"syntax = "proto3";
+enum Status {
+  option allow_alias = true;
+  STATUS_UNKNOWN = 0;
+  STATUS_STARTED = 1;
+  STATUS_RUNNING = 1 [deprecated = true];
+}
+"#;
+
+#[test]
+fn test_rust_enum_aliases() {
+    let code = generate(ALIAS_ENUM_PROTO, Language::Rust);
+
+    assert!(code.contains("pub enum Status {\n    #[default]\n    STATUS_UNKNOWN,\n    STATUS_STARTED,\n}"));
+    assert!(code.contains("    /// Alias of [`Status::STATUS_STARTED`].\n    #[deprecated]\n    pub const STATUS_RUNNING: Self = Self::STATUS_STARTED;\n"));
+    assert_eq!(code.matches("1 => Some(").count(), 1);
+    assert_eq!(code.matches("=> 1,").count(), 1);
+}
+
+#[test]
+fn test_go_enum_aliases() {
+    let code = generate(ALIAS_ENUM_PROTO, Language::Go);
+
+    assert!(code.contains("\t// Deprecated: Marked as deprecated in the proto file.\n\tSTATUS_STATUS_RUNNING Status = 1\n"));
+    assert!(!code.contains("case STATUS_STATUS_RUNNING:"));
+    assert_eq!(code.matches("\tcase 1:\n").count(), 1);
+}
+
+const ESCAPED_DEFAULTS_PROTO: &str = r#"syntax = "proto2";
+message Escaped {
+  required string greeting = 1 [default = "say \"hi\"\n"];
+  required bytes magic = 2 [default = "\x01\x02"];
+  required double floor = 3 [default = -inf];
+  required bytes raw = 4 [default = "\xff\x00"];
+}
+"#;
+
+#[test]
+fn test_rust_escaped_defaults() {
+    let code = generate(ESCAPED_DEFAULTS_PROTO, Language::Rust);
+
+    assert!(code.contains(r#"greeting: "say \"hi\"\n".to_string(),"#));
+    assert!(code.contains(r#"magic: b"\x01\x02".to_vec(),"#));
+    assert!(code.contains("floor: f64::NEG_INFINITY,"));
+    assert!(code.contains(r#"raw: b"\xff\x00".to_vec(),"#));
+}
+
+#[test]
+fn test_go_escaped_defaults() {
+    let code = generate(ESCAPED_DEFAULTS_PROTO, Language::Go);
+
+    assert!(code.contains(r#"const Default_Escaped_Greeting string = "say \"hi\"\n""#));
+    assert!(code.contains(r#"var Default_Escaped_Magic []byte = []byte("\u0001\u0002")"#));
+    assert!(code.contains("var Default_Escaped_Floor float64 = float64(math.Inf(-1))"));
+    assert!(code.contains(r#"var Default_Escaped_Raw []byte = []byte("\xff\x00")"#));
+}
+
+#[test]
+fn test_exact_integer_defaults() {
+    let source = r#"syntax = "proto2";
+message Limits {
+  optional uint64 max = 1 [default = 18446744073709551615];
+  optional int64 odd = 2 [default = 9007199254740993];
+  optional int64 min = 3 [default = -9223372036854775808];
+  optional double whole = 4 [default = 2];
+}
+"#;
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("self.max.unwrap_or(18446744073709551615)"), "{}", rust);
+    assert!(rust.contains("self.odd.unwrap_or(9007199254740993)"));
+    assert!(rust.contains("self.min.unwrap_or(-9223372036854775808)"));
+    assert!(rust.contains("self.whole.unwrap_or(2.0)"));
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("const Default_Limits_Max uint64 = 18446744073709551615\n"), "{}", go);
+    assert!(go.contains("const Default_Limits_Odd int64 = 9007199254740993\n"));
+    assert!(go.contains("const Default_Limits_Min int64 = -9223372036854775808\n"));
+    assert!(go.contains("const Default_Limits_Whole float64 = 2.0\n"));
+}
+
+const EDITIONS_PROTO: &str = r#"edition = "2023";
+message Profile {
+  string name = 1 [features.field_presence = IMPLICIT];
+  int64 visits = 2;
+  string id = 3 [features.field_presence = LEGACY_REQUIRED];
+}
+enum Mood {
+  MOOD_UNSPECIFIED = 0;
+  MOOD_HAPPY = 1;
+}
+enum Legacy {
+  option features.enum_type = CLOSED;
+  LEGACY_ONE = 1;
+}
+"#;
+
+#[test]
+fn test_rust_editions_features() {
+    let code = generate(EDITIONS_PROTO, Language::Rust);
+
+    assert!(code.contains("    pub name: String,\n"));
+    assert!(code.contains("    pub visits: Option<i64>,\n"));
+    assert!(code.contains("    pub id: String,\n"));
+    assert!(code.contains("            name: String::new(),\n"));
+    assert!(code.contains("    Unrecognized(i32),\n"));
+    assert!(code.contains("            _ => Some(Mood::Unrecognized(num)),\n"));
+    assert!(code.contains("            Mood::Unrecognized(num) => *num,\n"));
+    assert_eq!(code.matches("Unrecognized(i32)").count(), 1);
+}
+
+#[test]
+fn test_go_editions_features() {
+    let code = generate(EDITIONS_PROTO, Language::Go);
+
+    assert!(code.contains("\tName string `json:\"name\"`\n"), "{}", code);
+    assert!(code.contains("\tVisits *int64 `json:\"visits\"`\n"), "{}", code);
+    assert!(code.contains("\tdefault:\n\t\treturn Mood(num)\n"));
+    assert!(code.contains("func LegacyFromNumber(num int32) (Legacy Legacy) {\n    switch num {\n\tcase 1:\n\t\treturn LEGACY_LEGACY_ONE\n\tdefault:\n\t\treturn 0\n"));
+}
+
+const PRESENCE_PROTO: &str = r#"syntax = "proto3";
+message Address { string city = 1; }
+enum Tier { TIER_UNSPECIFIED = 0; TIER_GOLD = 1; }
+message Account {
+  string name = 1;
+  int64 balance = 2;
+  optional string nickname = 3;
+  optional int64 limit = 4;
+  Address address = 5;
+  oneof contact { string email = 6; }
+  Tier tier = 7;
+  optional Tier previous_tier = 8;
+}
+"#;
+
+#[test]
+fn test_rust_proto3_presence() {
+    let code = generate(PRESENCE_PROTO, Language::Rust);
+
+    assert!(code.contains("    pub name: String,\n    pub balance: i64,\n"), "{}", code);
+    assert!(code.contains("    pub nickname: Option<String>,\n    pub limit: Option<i64>,\n"));
+    assert!(code.contains("    pub address: Option<Address>,\n"));
+    assert!(code.contains("    pub tier: Tier,\n    pub previous_tier: Option<Tier>,\n"));
+    assert!(code.contains("            name: String::new(),\n            balance: 0,\n            nickname: None,\n"));
+}
+
+#[test]
+fn test_go_proto3_presence() {
+    let code = generate(PRESENCE_PROTO, Language::Go);
+
+    assert!(code.contains("\tName string `json:\"name\"`\n\tBalance int64 `json:\"balance\"`\n"), "{}", code);
+    assert!(code.contains("\tNickname *string `json:\"nickname\"`\n\tLimit *int64 `json:\"limit\"`\n"));
+    assert!(code.contains("\tAddress *Address `json:\"address\"`\n"));
+    assert!(code.contains("\tTier Tier `json:\"tier\"`\n\tPreviousTier *Tier `json:\"previous_tier\"`\n"));
+    assert!(code.contains("\t\tName: \"\",\n\t\tBalance: 0,\n\t\tNickname: nil,\n"));
+}
+
+const WELL_KNOWN_PROTO: &str = r#"syntax = "proto3";
+import "google/protobuf/timestamp.proto";
+import "google/protobuf/duration.proto";
+import "google/protobuf/wrappers.proto";
+import "google/protobuf/empty.proto";
+import "google/protobuf/struct.proto";
+message Session {
+  google.protobuf.Timestamp started_at = 1;
+  google.protobuf.Duration ttl = 2;
+  google.protobuf.StringValue label = 3;
+  repeated .google.protobuf.Timestamp renewals = 4;
+  google.protobuf.NullValue nothing = 5;
+}
+service Sessions {
+  rpc Ping(google.protobuf.Empty) returns (google.protobuf.Timestamp);
+}
+"#;
+
+#[test]
+fn test_rust_well_known_types() {
+    let code = generate(WELL_KNOWN_PROTO, Language::Rust);
+
+    assert!(code.contains("    pub started_at: Option<std::time::SystemTime>,\n"), "{}", code);
+    assert!(code.contains("    pub ttl: Option<std::time::Duration>,\n"));
+    assert!(code.contains("    pub label: Option<String>,\n"));
+    assert!(code.contains("    pub renewals: Vec<std::time::SystemTime>,\n    pub nothing: (),\n"));
+    assert!(code.contains("(&self, request: ()) -> Result<std::time::SystemTime, DmxpError>"));
+    assert!(!code.contains("struct Timestamp"));
+}
+
+#[test]
+fn test_go_well_known_types() {
+    let code = generate(WELL_KNOWN_PROTO, Language::Go);
+
+    assert!(code.contains("\t\"time\"\n"));
+    assert!(code.contains("\tStartedAt time.Time `json:\"started_at\"`\n"), "{}", code);
+    assert!(code.contains("\tTtl time.Duration `json:\"ttl\"`\n"));
+    assert!(code.contains("\tLabel *string `json:\"label\"`\n"));
+    assert!(code.contains("\tRenewals []time.Time `json:\"renewals\"`\n"));
+    assert!(code.contains("\t\tStartedAt: time.Time{},\n\t\tTtl: 0,\n\t\tLabel: nil,\n"));
+    assert!(code.contains("\tNothing interface{} `json:\"nothing\"`\n"));
+    assert!(code.contains("\t\tRenewals: nil,\n\t\tNothing: nil,\n"));
+    assert!(code.contains("request *struct{}) (*time.Time, error)"));
+}
+
+#[test]
+fn test_well_known_null_value_enum() {
+    use DMXP_Protobuf_Plugin::ast::FieldType;
+
+    // Once the loader resolves struct.proto, NullValue is known to be an enum
+    let mut ast = parse_source(WELL_KNOWN_PROTO);
+    ast.messages[0].fields[4].field_type = FieldType::Enum("google.protobuf.NullValue".to_string());
+
+    let rust = TemplateGenerator::new(Language::Rust).generate(&ast).expect("Generation failed");
+    assert!(rust.contains("    pub nothing: (),\n"), "{}", rust);
+    assert!(rust.contains("            nothing: Default::default(),\n"));
+
+    let go = TemplateGenerator::new(Language::Go).generate(&ast).expect("Generation failed");
+    assert!(go.contains("\tNothing interface{} `json:\"nothing\"`\n"), "{}", go);
+    assert!(go.contains("\t\tNothing: nil,\n"));
+}
+
+#[test]
+fn test_qualified_type_names_in_generated_code() {
+    let source = r#"syntax = "proto3";
+package acme.v1;
+message GetRequest { string id = 1; }
+message GetResponse { .acme.v1.GetRequest echo = 1; }
+service Things {
+  rpc Get(.acme.v1.GetRequest) returns (acme.v1.GetResponse);
+}
+"#;
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("    pub echo: Option<GetRequest>,\n"));
+    assert!(rust.contains("(&self, request: GetRequest) -> Result<GetResponse, DmxpError>"));
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("request *GetRequest) (*GetResponse, error)"), "{}", go);
+}
+
+#[test]
+fn test_nested_types_with_shared_names() {
+    let source = r#"syntax = "proto3";
+package acme.v1;
+message Outer {
+  message Inner { string id = 1; }
+  enum Kind { KIND_UNSPECIFIED = 0; KIND_A = 1; }
+  Inner inner = 1;
+  Kind kind = 2;
+}
+message Other {
+  message Inner { int32 count = 1; }
+  Inner inner = 1;
+  Outer.Inner outer_inner = 2;
+  map<string, Outer.Kind> kinds = 3;
+}
+service Things {
+  rpc Get(Outer) returns (Other.Inner);
+}
+"#;
+
+    let rust = generate(source, Language::Rust);
+    assert!(rust.contains("#[allow(non_camel_case_types)]\npub struct Outer_Inner {\n    pub id: String,\n"));
+    assert!(rust.contains("#[allow(non_camel_case_types)]\npub struct Other_Inner {\n    pub count: i32,\n"));
+    assert!(rust.contains("#[allow(non_camel_case_types)]\npub enum Outer_Kind {\n"));
+    assert!(rust.contains("    pub inner: Option<Outer_Inner>,\n    pub kind: Outer_Kind,\n"));
+    assert!(rust.contains("    pub inner: Option<Other_Inner>,\n    pub outer_inner: Option<Outer_Inner>,\n"));
+    assert!(rust.contains("    pub kinds: HashMap<String, Outer_Kind>,\n"));
+    assert!(rust.contains("(&self, request: Outer) -> Result<Other_Inner, DmxpError>"));
+
+    let go = generate(source, Language::Go);
+    assert!(go.contains("type Outer_Inner struct {\n\tId string `json:\"id\"`\n"));
+    assert!(go.contains("type Other_Inner struct {\n\tCount int32 `json:\"count\"`\n"));
+    assert!(go.contains("\tOUTER_KIND_KIND_A Outer_Kind = 1\n"));
+    assert!(go.contains("\tInner *Other_Inner `json:\"inner\"`\n\tOuterInner *Outer_Inner `json:\"outer_inner\"`\n"));
+    assert!(go.contains("\tKinds map[string]Outer_Kind `json:\"kinds\"`\n"));
+    assert!(go.contains("request *Outer) (*Other_Inner, error)"), "{}", go);
+}
@@ -0,0 +1,358 @@
+use DMXP_Protobuf_Plugin::ast::{FieldType, ImportKind};
+use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError, ProtoLoader};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+fn write(dir: &Path, name: &str, content: &str) {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_load_resolves_types_across_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let shared = dir.path().join("shared");
+    write(&shared, "acme/common.proto", r#"
+        syntax = "proto3";
+        package acme.common;
+        message Empty {}
+        enum Level { LEVEL_UNSPECIFIED = 0; LEVEL_HIGH = 1; }
+    "#);
+    write(&shared, "acme/reexport.proto", r#"
+        syntax = "proto3";
+        package acme.reexport;
+        import public "acme/common.proto";
+    "#);
+    write(dir.path(), "events.proto", r#"
+        syntax = "proto3";
+        package acme.v1;
+        import "acme/reexport.proto";
+        import weak "acme/common.proto";
+
+        message Event {
+          common.Level level = 1;
+          acme.common.Empty nothing = 2;
+          map<string, common.Level> levels = 3;
+        }
+
+        service Events {
+          rpc Ping(common.Empty) returns (Event);
+        }
+    "#);
+
+    let root = dir.path().join("events.proto");
+    let set = ProtoLoader::new()
+        .with_include_path(dir.path())
+        .with_include_path(&shared)
+        .load(root.to_str().unwrap())
+        .expect("Loading failed");
+
+    let names: Vec<&str> = set.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["acme/common.proto", "acme/reexport.proto", "events.proto"]);
+    assert_eq!(set.root().unwrap().name, "events.proto");
+    let common = set.find_file("acme/common.proto").unwrap();
+    assert_eq!(common.enums[0].span.file, "acme/common.proto");
+    assert_eq!((common.enums[0].span.line, common.enums[0].span.column), (5, 9));
+
+    let events = set.find_file("events.proto").unwrap();
+    assert_eq!(events.imports[1].kind, ImportKind::Weak);
+
+    let fields = &events.messages[0].fields;
+    assert!(matches!(&fields[0].field_type, FieldType::Enum(name) if name == "common.Level"));
+    assert!(matches!(&fields[1].field_type, FieldType::Message(name) if name == "acme.common.Empty"));
+    match &fields[2].field_type {
+        FieldType::Map(_, value) => assert!(matches!(&**value, FieldType::Enum(_))),
+        other => panic!("expected map, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_load_reports_missing_import() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "a.proto", "syntax = \"proto3\";\nimport \"missing/b.proto\";\n");
+
+    let err = ProtoLoader::new()
+        .load(dir.path().join("a.proto").to_str().unwrap())
+        .expect_err("Loading should fail");
+    let message = format!("{:#}", err);
+    assert!(message.contains("Import 'missing/b.proto' (imported from 'a.proto') not found"), "{}", message);
+}
+
+#[test]
+fn test_load_with_sources_renders_included_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let shared = dir.path().join("shared");
+    let broken = "syntax = \"proto3\";\nmessage B {\n  int32 x = 1\n}\n";
+    write(&shared, "acme/b.proto", broken);
+    write(
+        dir.path(),
+        "a.proto",
+        "syntax = \"proto3\";\nimport \"acme/b.proto\";\nimport \"google/protobuf/empty.proto\";\n",
+    );
+
+    let mut sources = HashMap::new();
+    let err = ProtoLoader::new()
+        .with_include_path(dir.path())
+        .with_include_path(&shared)
+        .load_with_sources(dir.path().join("a.proto").to_str().unwrap(), &mut sources)
+        .expect_err("Loading should fail");
+    assert_eq!(sources.get("acme/b.proto").map(String::as_str), Some(broken));
+    assert!(sources.contains_key("a.proto"));
+
+    // The diagnostic's file is the import name, so its code frame comes from `sources`
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+    let diagnostic = &parse_error.diagnostics[0];
+    let file = &diagnostic.span.as_ref().unwrap().file;
+    let rendered = diagnostic.render(&sources[file]);
+    assert!(rendered.contains("--> acme/b.proto:4:1\n  |\n4 | }\n  | ^\n"), "{}", rendered);
+
+    // Bundled files are recorded too
+    write(&shared, "acme/b.proto", "syntax = \"proto3\";\nmessage B {}\n");
+    let mut sources = HashMap::new();
+    ProtoLoader::new()
+        .with_include_path(dir.path())
+        .with_include_path(&shared)
+        .load_with_sources(dir.path().join("a.proto").to_str().unwrap(), &mut sources)
+        .expect("Loading failed");
+    assert!(sources["google/protobuf/empty.proto"].contains("message Empty"));
+}
+
+#[test]
+fn test_load_reports_import_cycle() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "a.proto", "import \"b.proto\";\nmessage A {}\n");
+    write(dir.path(), "b.proto", "import \"c.proto\";\nmessage B {}\n");
+    write(dir.path(), "c.proto", "import \"a.proto\";\nmessage C {}\n");
+
+    let err = ProtoLoader::new()
+        .load(dir.path().join("a.proto").to_str().unwrap())
+        .expect_err("Loading should fail");
+    let message = format!("{:#}", err);
+    assert!(message.contains("Import cycle detected: a.proto -> b.proto -> c.proto -> a.proto"), "{}", message);
+}
+
+#[test]
+fn test_load_reports_unresolved_and_unimported_types() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "base.proto", "package base;\nmessage Shared {}\n");
+    write(dir.path(), "middle.proto", "package middle;\nimport \"base.proto\";\nmessage Mid { base.Shared s = 1; }\n");
+    write(dir.path(), "top.proto", r#"
+        package top;
+        import "middle.proto";
+        message Top {
+          middle.Mid ok = 1;
+          base.Shared hidden = 2;
+          Nowhere missing = 3;
+        }
+    "#);
+
+    let err = ProtoLoader::new()
+        .load(dir.path().join("top.proto").to_str().unwrap())
+        .expect_err("Loading should fail");
+    let message = format!("{:#}", err);
+    assert!(message.contains("top.proto: 'base.Shared' in field 'top.Top.hidden' resolves to 'base.Shared' in 'base.proto', which is not imported"), "{}", message);
+    assert!(message.contains("top.proto: unknown type 'Nowhere' in field 'top.Top.missing'"), "{}", message);
+
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+    let missing = &parse_error.diagnostics[1];
+    assert_eq!(missing.code, ErrorCode::UnresolvedType);
+    assert_eq!(missing.span.as_ref().map(|s| (s.file.as_str(), s.line)), Some(("top.proto", 7)));
+    assert!(!message.contains("middle.Mid"), "{}", message);
+}
+
+#[test]
+fn test_load_single_file_without_imports() {
+    let set = ProtoLoader::new().load("test.proto").expect("Loading failed");
+    assert_eq!(set.files.len(), 1);
+
+    let order = set.root().unwrap().find_message("OrderData").unwrap();
+    let status = order.fields.iter().find(|f| f.name == "status").unwrap();
+    assert!(matches!(&status.field_type, FieldType::Enum(name) if name == "OrderStatus"));
+}
+
+#[test]
+fn test_load_checks_enum_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "legacy.proto", r#"
+        syntax = "proto2";
+        enum Color { RED = 0; GREEN = 1; }
+        message Other {}
+        message Legacy {
+          optional Color color = 1 [default = GREEN];
+          optional Color bad = 2 [default = BLUE];
+          optional Other other = 3 [default = RED];
+        }
+    "#);
+
+    let err = ProtoLoader::new()
+        .load(&dir.path().join("legacy.proto").to_string_lossy())
+        .expect_err("Loading should fail")
+        .to_string();
+    assert!(err.contains("default value 'BLUE' of field 'Legacy.bad' is not a value of the enum"), "{}", err);
+    assert!(err.contains("field 'Legacy.other' has a message type and cannot have a default value"), "{}", err);
+    assert!(!err.contains("Legacy.color"), "{}", err);
+}
+
+#[test]
+fn test_load_from_in_memory_provider() {
+    use DMXP_Protobuf_Plugin::parser::InMemoryProvider;
+
+    let provider = InMemoryProvider::new()
+        .with_file("acme/common.proto", "syntax = \"proto3\";\npackage acme.common;\nenum Level { LEVEL_UNSPECIFIED = 0; }\n")
+        .with_file(
+            "acme/events.proto",
+            "syntax = \"proto3\";\npackage acme.v1;\nimport \"acme/common.proto\";\nmessage Event { acme.common.Level level = 1; }\n",
+        );
+    let loader = ProtoLoader::new().with_include_path("/nonexistent").with_source_provider(provider);
+
+    let set = loader.load("acme/events.proto").expect("Loading failed");
+    let names: Vec<&str> = set.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["acme/common.proto", "acme/events.proto"]);
+    assert!(matches!(&set.root().unwrap().messages[0].fields[0].field_type, FieldType::Enum(_)));
+
+    let err = loader.load("acme/missing.proto").expect_err("Loading should fail");
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+    assert_eq!(parse_error.diagnostics[0].code, ErrorCode::Import);
+    assert_eq!(
+        err.to_string(),
+        "File 'acme/missing.proto' not found in in-memory files: 'acme/common.proto', 'acme/events.proto'"
+    );
+}
+
+#[test]
+fn test_load_records_fully_qualified_types() {
+    use DMXP_Protobuf_Plugin::parser::InMemoryProvider;
+
+    let provider = InMemoryProvider::new()
+        .with_file("common.proto", "syntax = \"proto3\";\npackage acme.common;\nmessage Empty {}\n")
+        .with_file("api.proto", r#"syntax = "proto3";
+package acme.v1;
+import "common.proto";
+message Outer {
+  message Inner { string id = 1; }
+  Inner inner = 1;
+  .acme.v1.Outer.Inner absolute = 2;
+  map<string, Outer.Inner> by_id = 3;
+  common.Empty nothing = 4;
+}
+service Things {
+  rpc Get(.acme.common.Empty) returns (Outer.Inner);
+}
+"#);
+    let set = ProtoLoader::new().with_source_provider(provider).load("api.proto").expect("Loading failed");
+    let api = set.root().unwrap();
+
+    let resolved: Vec<Option<&str>> = api.messages[0].fields.iter().map(|f| f.resolved_type.as_deref()).collect();
+    assert_eq!(
+        resolved,
+        vec![
+            Some(".acme.v1.Outer.Inner"),
+            Some(".acme.v1.Outer.Inner"),
+            Some(".acme.v1.Outer.Inner"),
+            Some(".acme.common.Empty"),
+        ]
+    );
+    assert!(matches!(&api.messages[0].fields[1].field_type, FieldType::Message(name) if name == ".acme.v1.Outer.Inner"));
+
+    let method = &api.services[0].methods[0];
+    assert_eq!(method.input_type, ".acme.common.Empty");
+    assert_eq!(method.resolved_input_type.as_deref(), Some(".acme.common.Empty"));
+    assert_eq!(method.resolved_output_type.as_deref(), Some(".acme.v1.Outer.Inner"));
+
+    let provider = InMemoryProvider::new().with_file("a.proto", "syntax = \"proto3\";\nmessage A { .Missing m = 1; }\n");
+    let err = ProtoLoader::new().with_source_provider(provider).load("a.proto").expect_err("Loading should fail");
+    assert!(err.to_string().contains("unknown type '.Missing' in field 'A.m'"), "{}", err);
+}
+
+#[test]
+fn test_load_bundled_well_known_types() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "event.proto", r#"
+        syntax = "proto3";
+        package acme.v1;
+        import "google/protobuf/timestamp.proto";
+        import "google/protobuf/wrappers.proto";
+        message Event {
+          google.protobuf.Timestamp created_at = 1;
+          .google.protobuf.StringValue note = 2;
+        }
+    "#);
+    // A file on the include path wins over the bundled one
+    write(dir.path(), "google/protobuf/wrappers.proto", r#"
+        syntax = "proto3";
+        package google.protobuf;
+        message StringValue { string value = 1; string local = 2; }
+    "#);
+
+    let set = ProtoLoader::new()
+        .with_include_path(dir.path())
+        .load(&dir.path().join("event.proto").to_string_lossy())
+        .expect("Loading failed");
+    let names: Vec<&str> = set.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["google/protobuf/timestamp.proto", "google/protobuf/wrappers.proto", "event.proto"]);
+    assert_eq!(set.files[1].messages[0].fields.len(), 2);
+
+    let resolved: Vec<Option<&str>> = set.root().unwrap().messages[0]
+        .fields
+        .iter()
+        .map(|f| f.resolved_type.as_deref())
+        .collect();
+    assert_eq!(resolved, vec![Some(".google.protobuf.Timestamp"), Some(".google.protobuf.StringValue")]);
+}
+
+#[test]
+fn test_load_bundled_dmxp_options() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "feed.proto", r#"
+        syntax = "proto3";
+        import "dmxp/options.proto";
+        message Req { string id = 1; }
+        service Feed {
+          rpc Get(Req) returns (Req) { option (dmxp.dmxp_method_timeout_ms) = 50; }
+        }
+    "#);
+
+    let set = ProtoLoader::new()
+        .with_include_path(dir.path())
+        .load(&dir.path().join("feed.proto").to_string_lossy())
+        .expect("Loading failed");
+    let names: Vec<&str> = set.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["google/protobuf/descriptor.proto", "dmxp/options.proto", "feed.proto"]);
+
+    let service = &set.root().unwrap().services[0];
+    assert_eq!(service.method_timeout_ms(&service.methods[0]), Some(50));
+}
+
+#[test]
+fn test_lenient_dmxp_warnings() {
+    use DMXP_Protobuf_Plugin::parser::Severity;
+
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "common.proto", r#"syntax = "proto3";
+message Shared { option (dmxp_buffer_size) = "big"; }
+"#);
+    write(dir.path(), "api.proto", r#"syntax = "proto3";
+import "common.proto";
+message Event { option (dmxp_bufer_size) = 64; }
+"#);
+    let root = dir.path().join("api.proto").to_string_lossy().to_string();
+
+    let err = ProtoLoader::new().load(&root).expect_err("Strict mode should reject the options");
+    assert!(err.downcast_ref::<ParseError>().is_some());
+
+    let set = ProtoLoader::new().with_strict_dmxp(false).load(&root).expect("Loading failed");
+    let warnings: Vec<(&str, &str)> = set.warnings
+        .iter()
+        .map(|w| (w.span.as_ref().unwrap().file.as_str(), w.message.as_str()))
+        .collect();
+    assert_eq!(warnings, vec![
+        ("api.proto", "Unknown DMXP option '(dmxp_bufer_size)' in MessageOptions"),
+        (
+            "common.proto",
+            "Invalid value for option '(dmxp_buffer_size)': expected an unsigned integer, found string \"big\"",
+        ),
+    ]);
+    assert!(set.warnings.iter().all(|w| w.severity == Severity::Warning));
+}
@@ -1,4 +1,4 @@
-use DMXP_Protobuf_Plugin::ast::AstBuilder;
+use DMXP_Protobuf_Plugin::ast::ProtoFile;
 use DMXP_Protobuf_Plugin::parser::parser::ProtoParser;
 use DMXP_Protobuf_Plugin::utils::LoadFile;
 
@@ -71,4 +71,1331 @@ fn test_parse_proto_with_dmxp_options() {
     assert_eq!(order_dmxp.channel, Some("order_events".to_string()));
     
     println!("AST generated successfully: {:#?}", ast);
-}
\ No newline at end of file
+}
+
+fn parse_source(content: &str) -> ProtoFile {
+    let mut parser = ProtoParser::new(content.to_string());
+    parser.parse().expect("Parsing failed")
+}
+
+#[test]
+fn test_layout_does_not_change_parse_result() {
+    let one_per_line = parse_source(
+        "syntax = \"proto3\";\npackage demo;\nmessage A {\n  int32 x = 1;\n  string y = 2;\n}\n",
+    );
+    let brace_on_next_line = parse_source(
+        "syntax = \"proto3\";\npackage demo;\nmessage A\n{\n  int32 x = 1;\n  string y = 2;\n}\n",
+    );
+    let single_line = parse_source("syntax = \"proto3\"; package demo; message A { int32 x = 1; string y = 2; }");
+    let split_field = parse_source("syntax = \"proto3\";\npackage demo;\nmessage A {\n  int32\n    x =\n    1;\n  string y = 2; }");
+
+    for ast in [&one_per_line, &brace_on_next_line, &single_line, &split_field] {
+        assert_eq!(ast.package, "demo");
+        assert_eq!(ast.messages.len(), 1);
+        let message = &ast.messages[0];
+        assert_eq!(message.name, "A");
+        let fields: Vec<(&str, i32)> = message.fields.iter().map(|f| (f.name.as_str(), f.number)).collect();
+        assert_eq!(fields, vec![("x", 1), ("y", 2)]);
+    }
+}
+
+#[test]
+fn test_nested_declarations_on_one_line() {
+    let ast = parse_source(
+        "message Outer { message Inner { bool ok = 1; } enum Kind { A = 0; B = 1; } Inner inner = 1; repeated Kind kinds = 2; }\n\
+         service S { option (dmxp_channels) = \"s\"; rpc Call(Outer) returns (Outer.Inner) {} rpc Ping(Outer) returns (Outer); }",
+    );
+
+    let outer = ast.find_message("Outer").expect("Outer not found");
+    assert_eq!(outer.nested_messages.len(), 1);
+    assert_eq!(outer.nested_messages[0].fields.len(), 1);
+    assert_eq!(outer.nested_enums.len(), 1);
+    assert_eq!(outer.nested_enums[0].values.len(), 2);
+    assert_eq!(outer.fields.len(), 2);
+
+    let service = ast.find_service("S").expect("S not found");
+    assert_eq!(service.methods.len(), 2);
+    assert_eq!(service.methods[0].output_type, "Outer.Inner");
+    assert_eq!(service.get_dmxp_channels(), vec!["s"]);
+}
+
+#[test]
+fn test_syntax_error_reports_location() {
+    let mut parser = ProtoParser::new("message A {\n  int32 x = ;\n}".to_string());
+    let err = parser.parse().expect_err("Parsing should fail").to_string();
+    assert!(err.contains("line 2"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_comments_in_every_scope() {
+    let ast = parse_source(
+        r#"// file header { not a message }
+/* block comment at file scope
+   message Ghost { int32 x = 1; }
+*/
+syntax = "proto3"; // trailing comment
+package /* inline */ demo;
+
+message A { // opening comment with = and {
+  // string old = 7;
+  /* int32 also_old = 8; */
+  int32 x = 1; // trailing comment }
+  string /* type and name split */ y = /* number */ 2;
+  /*
+   * multi-line block
+   * enum Nope { A = 0; }
+   */
+}
+
+enum E {
+  // E_OLD = 3;
+  E_ZERO = 0; /* trailing = 5; */
+  E_ONE = 1;
+}
+
+service S {
+  // rpc Old(A) returns (A);
+  /* option (dmxp_channels) = "ghost"; */
+  option (dmxp_channels) = "live"; // option (dmxp_channels) = "ghost2";
+  rpc Call(/* request */ A) returns (A /* response */) {
+    // comment inside rpc body }
+  }
+  rpc Ping(A) /* between */ returns (A); // trailing
+}
+"#,
+    );
+
+    assert_eq!(ast.package, "demo");
+    assert_eq!(ast.messages.len(), 1);
+    let fields: Vec<&str> = ast.messages[0].fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(fields, vec!["x", "y"]);
+
+    let values: Vec<&str> = ast.enums[0].values.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(values, vec!["E_ZERO", "E_ONE"]);
+
+    let service = &ast.services[0];
+    assert_eq!(service.get_dmxp_channels(), vec!["live"]);
+    let methods: Vec<&str> = service.methods.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(methods, vec!["Call", "Ping"]);
+}
+
+#[test]
+fn test_unterminated_block_comment_is_an_error() {
+    let mut parser = ProtoParser::new("message A {\n  /* never closed\n  int32 x = 1;\n}".to_string());
+    let err = parser.parse().expect_err("Parsing should fail").to_string();
+    assert!(err.contains("Unterminated block comment"), "unexpected error: {}", err);
+    assert!(err.contains("line 2"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_comments_attached_to_declarations() {
+    let ast = parse_source(
+        r#"syntax = "proto3";
+
+// Detached note about the file layout
+
+/* License header style block */
+
+// User data message that will be published to a channel
+// Second line of the message docs
+message UserData { // trailing on message
+  // Unique user id
+  string user_id = 1; // trailing on field
+
+  string name = 2;
+}
+
+// Status of an order
+enum Status {
+  /* Not set */
+  STATUS_UNSPECIFIED = 0;
+  STATUS_DONE = 1; // finished
+}
+
+// Users over DMXP
+service UserService {
+  // Look up a user
+  rpc GetUser(UserData) returns (UserData); // cheap call
+}
+"#,
+    );
+
+    let message = &ast.messages[0];
+    assert_eq!(
+        message.comments.leading.as_deref(),
+        Some("User data message that will be published to a channel\nSecond line of the message docs")
+    );
+    assert_eq!(message.comments.trailing.as_deref(), Some("trailing on message"));
+    assert_eq!(
+        message.comments.detached,
+        vec!["Detached note about the file layout", "License header style block"]
+    );
+
+    let user_id = &message.fields[0];
+    assert_eq!(user_id.comments.leading.as_deref(), Some("Unique user id"));
+    assert_eq!(user_id.comments.trailing.as_deref(), Some("trailing on field"));
+    let name = &message.fields[1];
+    assert!(name.comments.leading.is_none());
+    assert!(name.comments.trailing.is_none());
+
+    let status = &ast.enums[0];
+    assert_eq!(status.comments.leading.as_deref(), Some("Status of an order"));
+    assert_eq!(status.values[0].comments.leading.as_deref(), Some("Not set"));
+    assert_eq!(status.values[1].comments.trailing.as_deref(), Some("finished"));
+
+    let service = &ast.services[0];
+    assert_eq!(service.comments.leading.as_deref(), Some("Users over DMXP"));
+    assert_eq!(service.methods[0].comments.leading.as_deref(), Some("Look up a user"));
+    assert_eq!(service.methods[0].comments.trailing.as_deref(), Some("cheap call"));
+}
+
+#[test]
+fn test_oneof_fields_are_grouped() {
+    let ast = parse_source(
+        "message Event { string id = 1; oneof payload { option (dmxp_key) = true; string text = 2; bytes raw = 3; } int64 at = 4; }",
+    );
+
+    let event = &ast.messages[0];
+    let fields: Vec<&str> = event.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(fields, vec!["id", "at"]);
+
+    assert_eq!(event.oneofs.len(), 1);
+    let payload = &event.oneofs[0];
+    assert_eq!(payload.name, "payload");
+    assert_eq!(payload.options.len(), 1);
+    let members: Vec<(&str, i32)> = payload.fields.iter().map(|f| (f.name.as_str(), f.number)).collect();
+    assert_eq!(members, vec![("text", 2), ("raw", 3)]);
+}
+
+#[test]
+fn test_oneof_rejects_labels() {
+    let mut parser = ProtoParser::new("message E { oneof p { repeated string a = 1; } }".to_string());
+    let err = parser.parse().expect_err("Parsing should fail").to_string();
+    assert!(err.contains("cannot be 'repeated'"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_map_fields() {
+    use DMXP_Protobuf_Plugin::ast::{FieldLabel, FieldType};
+
+    let ast = parse_source(
+        "message Stats { map<string, int64> counters = 3; map < int32 , Stats.Entry > entries = 4 ; message Entry {} }",
+    );
+
+    let stats = &ast.messages[0];
+    assert_eq!(stats.fields.len(), 2);
+    let counters = &stats.fields[0];
+    assert_eq!(counters.name, "counters");
+    assert_eq!(counters.number, 3);
+    assert!(matches!(counters.label, FieldLabel::Repeated));
+    match &counters.field_type {
+        FieldType::Map(key, value) => {
+            assert!(matches!(**key, FieldType::String));
+            assert!(matches!(**value, FieldType::Int64));
+        }
+        other => panic!("expected map, got {:?}", other),
+    }
+    match &stats.fields[1].field_type {
+        FieldType::Map(key, value) => {
+            assert!(matches!(**key, FieldType::Int32));
+            assert!(matches!(&**value, FieldType::Message(name) if name == "Stats.Entry"));
+        }
+        other => panic!("expected map, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_invalid_map_fields() {
+    for (source, expected) in [
+        ("message M { map<double, string> m = 1; }", "Invalid map key type 'double'"),
+        ("message M { map<bytes, string> m = 1; }", "Invalid map key type 'bytes'"),
+        ("message M { map<M, string> m = 1; }", "Invalid map key type 'M'"),
+        ("message M { repeated map<string, string> m = 1; }", "cannot be repeated"),
+        ("message M { oneof o { map<string, string> m = 1; } }", "not allowed in oneof"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_field_options() {
+    use DMXP_Protobuf_Plugin::ast::OptionValue;
+
+    let ast = parse_source(
+        r#"message User {
+  string user_id = 1 [json_name = "uid", (dmxp_key) = true];
+  string legacy = 2 [deprecated = true];
+  repeated int32 scores = 3 [packed = false];
+  repeated int64 ids = 4;
+}"#,
+    );
+
+    let fields = &ast.messages[0].fields;
+    let user_id = &fields[0];
+    assert_eq!(user_id.options.len(), 2);
+    assert_eq!(user_id.json_name(), "uid");
+    assert!(matches!(user_id.find_option("(dmxp_key)"), Some(OptionValue::Boolean(true))));
+    assert_eq!(user_id.number, 1);
+
+    assert!(fields[1].is_deprecated());
+    assert_eq!(fields[1].json_name(), "legacy");
+
+    assert!(!fields[2].is_packed("proto3"));
+    assert!(fields[3].is_packed("proto3"));
+    assert!(!fields[3].is_packed("proto2"));
+}
+
+#[test]
+fn test_invalid_field_options() {
+    for (source, expected) in [
+        ("message M { string s = 1 [packed = true]; }", "Option 'packed' is only allowed"),
+        ("message M { repeated string s = 1 [packed = true]; }", "Option 'packed' is only allowed"),
+        ("message M { int32 s = 1 [deprecated = \"yes\"]; }", "Invalid value for option 'deprecated'"),
+        ("message M { int32 s = 1 [json_name = 5]; }", "Invalid value for option 'json_name'"),
+        (r#"message M { int32 s = 1 [json_name = "a\"b"]; }"#, "Option 'json_name' on field 's' may not contain"),
+        (r#"message M { int32 s = 1 [json_name = "a\\b"]; }"#, "Option 'json_name' on field 's' may not contain"),
+        ("message M { int32 s = 1 [json_name = \"a`b\"]; }", "Option 'json_name' on field 's' may not contain"),
+        ("message M { int32 s = 1 [deprecated = true; }", "Expected ','"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_streaming_rpcs() {
+    let ast = parse_source(
+        r#"syntax = "proto3";
+message stream { string id = 1; }
+service Feed {
+  rpc Get(stream) returns (stream);
+  rpc Watch(stream) returns (stream stream);
+  rpc Upload(stream stream) returns (stream);
+  rpc Chat(stream stream) returns (stream stream) {}
+}"#,
+    );
+
+    let flags: Vec<(bool, bool)> = ast.services[0]
+        .methods
+        .iter()
+        .map(|m| (m.client_streaming, m.server_streaming))
+        .collect();
+    assert_eq!(flags, vec![(false, false), (false, true), (true, false), (true, true)]);
+    assert!(ast.services[0].methods.iter().all(|m| m.input_type == "stream" && m.output_type == "stream"));
+}
+
+#[test]
+fn test_rpc_option_block() {
+    let ast = parse_source(
+        r#"syntax = "proto3";
+message Req { string id = 1; }
+service Feed {
+  option (dmxp_channels) = "feed";
+  option (dmxp_timeout_ms) = 1000;
+  rpc Get(Req) returns (Req) {
+    option (dmxp_method_channel) = "feed.lookup";
+    option (dmxp_timeout_ms) = 50;
+    option (dmxp_async) = false;
+    option deprecated = true;
+  }
+  rpc Put(Req) returns (Req) {}
+  rpc Watch(Req) returns (Req) { option (dmxp_method_timeout_ms) = 75; }
+}"#,
+    );
+
+    let service = &ast.services[0];
+    let get = &service.methods[0];
+    let dmxp = get.dmxp_options.as_ref().expect("missing dmxp options");
+    assert_eq!(dmxp.channel.as_deref(), Some("feed.lookup"));
+    assert_eq!(dmxp.timeout_ms, Some(50));
+    assert_eq!(dmxp.is_async, Some(false));
+    assert_eq!(get.options.len(), 4);
+    assert_eq!(service.method_channel(get), "feed.lookup");
+    assert_eq!(service.method_timeout_ms(get), Some(50));
+    assert!(!get.is_async(true));
+
+    let put = &service.methods[1];
+    assert!(put.dmxp_options.is_none());
+    assert_eq!(service.method_channel(put), "feed.Put");
+    assert_eq!(service.method_timeout_ms(put), Some(1000));
+    assert!(put.is_async(true));
+
+    assert_eq!(service.method_timeout_ms(&service.methods[2]), Some(75));
+
+    let mut parser = ProtoParser::new("service S { rpc A(B) returns (C) { rpc D(E) returns (F); } }".to_string());
+    let err = parser.parse().expect_err("Parsing should fail").to_string();
+    assert!(err.contains("Expected 'option'"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_reserved_numbers_and_names() {
+    use DMXP_Protobuf_Plugin::ast::ReservedRange;
+
+    let ast = parse_source(
+        r#"syntax = "proto3";
+message User {
+  reserved 2, 15, 9 to 11;
+  reserved 100 to max;
+  reserved "email", "phone";
+  string name = 1;
+  int32 age = 3;
+}
+enum Status {
+  reserved -5 to -1, 7;
+  reserved "RETIRED";
+  STATUS_UNKNOWN = 0;
+}"#,
+    );
+
+    let user = &ast.messages[0];
+    assert_eq!(
+        user.reserved_ranges,
+        vec![
+            ReservedRange { start: 2, end: 2 },
+            ReservedRange { start: 15, end: 15 },
+            ReservedRange { start: 9, end: 11 },
+            ReservedRange { start: 100, end: 536_870_911 },
+        ]
+    );
+    assert_eq!(user.reserved_names, vec!["email", "phone"]);
+    assert!(user.is_reserved_number(10));
+    assert!(!user.is_reserved_number(12));
+
+    let status = &ast.enums[0];
+    assert_eq!(status.reserved_ranges, vec![ReservedRange { start: -5, end: -1 }, ReservedRange { start: 7, end: 7 }]);
+    assert!(status.is_reserved_name("RETIRED"));
+}
+
+#[test]
+fn test_reserved_collisions() {
+    for (source, expected) in [
+        ("message M { reserved 9 to 11; int32 a = 10; }", "Field 'a' uses reserved field number 10 in message 'M'"),
+        ("message M { reserved \"a\"; int32 a = 1; }", "Field name 'a' is reserved in message 'M'"),
+        ("message M { int32 a = 10; reserved 9 to 11; }", "Reservation conflicts with field 'a' = 10 in message 'M'"),
+        ("message M { oneof o { int32 a = 3; } reserved 3; }", "Reservation conflicts with field 'a' = 3"),
+        ("enum E { reserved 1; A = 1; }", "Enum value 'A' uses reserved number 1 in enum 'E'"),
+        ("enum E { A = 0; reserved \"A\"; }", "Reservation conflicts with value 'A' = 0 in enum 'E'"),
+        ("message M { reserved 11 to 9; }", "Reserved range 11 to 9 ends before it starts"),
+        ("message M { reserved 0; }", "Reserved number '0' is out of range"),
+        ("message M { reserved 1, \"a\"; }", "Expected an integer"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_duplicate_fields() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError};
+
+    for (source, expected) in [
+        ("message M { int32 x = 3; string x = 4; }", "Field 'x' is already defined in message 'M'"),
+        ("message M { int32 x = 3; string y = 3; }", "Field number 3 of 'y' is already used by field 'x'"),
+        ("message M { int32 x = 1; oneof o { string x = 2; } }", "Field 'x' is already defined"),
+        ("message M { oneof o { int32 a = 1; string b = 1; } }", "Field number 1 of 'b' is already used by field 'a'"),
+        ("message M { oneof o { int32 a = 1; } oneof p { string b = 1; } }", "Field number 1 of 'b'"),
+    ] {
+        let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail");
+        let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+        assert_eq!(parse_error.diagnostics[0].code, ErrorCode::Duplicate, "{}", source);
+        assert!(err.to_string().contains(expected), "unexpected error for {}: {}", source, err);
+    }
+
+    // Names and numbers only need to be unique within a message
+    let ast = parse_source("message A { int32 x = 1; message B { int32 x = 1; } }");
+    assert_eq!(ast.messages[0].nested_messages[0].fields[0].name, "x");
+}
+
+#[test]
+fn test_extension_ranges() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError};
+
+    let ast = parse_source(
+        "syntax = \"proto2\"; message M { optional int32 a = 1; extensions 100 to 199, 500, 1000 to max [verification = UNVERIFIED]; }",
+    );
+    let ranges: Vec<(i32, i32)> = ast.messages[0].extension_ranges.iter().map(|r| (r.start, r.end)).collect();
+    assert_eq!(ranges, vec![(100, 199), (500, 500), (1000, 536_870_911)]);
+    assert!(ast.messages[0].is_extension_number(150));
+
+    for (source, code, expected) in [
+        ("syntax = \"proto3\"; message M { extensions 100 to 199; }", ErrorCode::InvalidType,
+            "Extension ranges are not allowed in proto3"),
+        ("syntax = \"proto2\"; message M { optional int32 a = 150; extensions 100 to 199; }", ErrorCode::InvalidNumber,
+            "Extension range includes field 'a' = 150 in message 'M'"),
+        ("syntax = \"proto2\"; message M { extensions 100 to 199; optional int32 a = 150; }", ErrorCode::InvalidNumber,
+            "Field 'a' uses number 150, which is an extension number of message 'M'"),
+        ("syntax = \"proto2\"; message M { extensions 9 to 1; }", ErrorCode::InvalidNumber,
+            "Extension range 9 to 1 ends before it starts"),
+    ] {
+        let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail");
+        let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+        assert_eq!(parse_error.diagnostics[0].code, code, "{}", source);
+        assert!(err.to_string().contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_implementation_reserved_field_numbers() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError};
+
+    for (source, expected) in [
+        ("message M { int32 a = 19000; }", "Field number 19000 is reserved for the protobuf implementation"),
+        ("message M { oneof o { int32 a = 19999; } }", "Field number 19999 is reserved"),
+        ("message M { map<string, int32> m = 19500; }", "Field number 19500 is reserved"),
+    ] {
+        let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail");
+        let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+        assert_eq!(parse_error.diagnostics[0].code, ErrorCode::InvalidNumber, "{}", source);
+        assert!(err.to_string().contains(expected), "unexpected error for {}: {}", source, err);
+    }
+
+    // Only field numbers are restricted; reservations may overlap the block
+    let ast = parse_source("message M { reserved 18000 to 20000; int32 a = 17999; int32 b = 20001; }");
+    assert_eq!(ast.messages[0].fields.len(), 2);
+    for (source, start, end) in [
+        ("message M { reserved 19500; }", 19_500, 19_500),
+        ("message M { reserved 1 to max; }", 1, 536_870_911),
+    ] {
+        let ast = parse_source(source);
+        let range = &ast.messages[0].reserved_ranges[0];
+        assert_eq!((range.start, range.end), (start, end), "{}", source);
+    }
+}
+
+#[test]
+fn test_proto2_labels_defaults_and_groups() {
+    use DMXP_Protobuf_Plugin::ast::{FieldLabel, FieldType, OptionValue};
+
+    let ast = parse_source(
+        r#"syntax = "proto2";
+message Legacy {
+  required string id = 1;
+  optional int32 retries = 2 [default = 3, deprecated = true];
+  repeated string tags = 3;
+  optional string label = 4 [default = "none"];
+  repeated group Result = 5 {
+    required string url = 6;
+  }
+  oneof choice {
+    string name = 7;
+    group Extra = 8 { optional int32 x = 9; }
+  }
+}"#,
+    );
+
+    let legacy = &ast.messages[0];
+    let labels: Vec<_> = legacy.fields.iter().map(|f| f.label.clone()).collect();
+    assert!(matches!(
+        labels.as_slice(),
+        [FieldLabel::Required, FieldLabel::Optional, FieldLabel::Repeated, FieldLabel::Optional, FieldLabel::Repeated]
+    ));
+
+    let retries = &legacy.fields[1];
+    assert!(matches!(retries.default_value, Some(OptionValue::UInt(3))));
+    assert!(retries.find_option("default").is_none());
+    assert!(retries.is_deprecated());
+    assert!(matches!(&legacy.fields[3].default_value, Some(OptionValue::String(s)) if s == "none"));
+
+    let result = &legacy.fields[4];
+    assert_eq!(result.name, "result");
+    assert!(matches!(&result.field_type, FieldType::Message(name) if name == "Result"));
+    let nested: Vec<&str> = legacy.nested_messages.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(nested, vec!["Result", "Extra"]);
+    assert_eq!(legacy.nested_messages[0].fields[0].name, "url");
+
+    let choice: Vec<&str> = legacy.oneofs[0].fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(choice, vec!["name", "extra"]);
+    assert_eq!(legacy.nested_messages[1].fields[0].name, "x");
+}
+
+#[test]
+fn test_bytes_default_not_utf8() {
+    use DMXP_Protobuf_Plugin::ast::OptionValue;
+
+    let ast = parse_source(r#"syntax = "proto2"; message M { optional bytes raw = 1 [default = "\xff" "\x00"]; }"#);
+    let raw = &ast.messages[0].fields[0];
+    assert!(matches!(&raw.default_value, Some(OptionValue::Bytes(bytes)) if bytes == &[0xff, 0x00]));
+
+    // Only bytes may hold a literal that is not UTF-8
+    for source in [
+        r#"syntax = "proto2"; message M { optional string s = 1 [default = "\xff\x00"]; }"#,
+        r#"syntax = "proto3"; message M { string s = 1 [json_name = "\xff"]; }"#,
+        r#"syntax = "proto3"; import "\xff";"#,
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains("not valid UTF-8"), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_proto3_optional_label() {
+    use DMXP_Protobuf_Plugin::ast::FieldLabel;
+
+    let ast = parse_source("syntax = \"proto3\"; message optional { optional string a = 1; optional b = 2; }");
+    let fields = &ast.messages[0].fields;
+    assert!(matches!(fields[0].label, FieldLabel::Optional));
+    assert_eq!(fields[1].name, "b");
+}
+
+#[test]
+fn test_invalid_proto2_constructs() {
+    for (source, expected) in [
+        ("syntax = \"proto3\"; message M { required string a = 1; }", "Required fields are not allowed in proto3"),
+        ("syntax = \"proto2\"; message M { string a = 1; }", "Fields in proto2 must be 'required', 'optional' or 'repeated'"),
+        ("syntax = \"proto3\"; message M { optional int32 a = 1 [default = 5]; }", "Explicit default values are not allowed in proto3"),
+        ("syntax = \"proto2\"; message M { repeated int32 a = 1 [default = 5]; }", "Repeated field 'a' cannot have a default value"),
+        ("syntax = \"proto2\"; message M { optional int32 a = 1 [default = \"x\"]; }", "Invalid default value for field 'a'"),
+        ("syntax = \"proto2\"; message M { optional uint32 a = 1 [default = -1]; }", "Invalid default value for field 'a'"),
+        (
+            "syntax = \"proto2\"; message M { optional int32 a = 1 [default = 99999999999]; }",
+            "99999999999 is out of range for int32 (-2147483648 to 2147483647)",
+        ),
+        ("syntax = \"proto2\"; message M { optional sint32 a = 1 [default = -2147483649]; }", "out of range for sint32"),
+        ("syntax = \"proto2\"; message M { optional fixed32 a = 1 [default = 4294967296]; }", "out of range for fixed32"),
+        (
+            "syntax = \"proto2\"; message M { optional int64 a = 1 [default = 9223372036854775808]; }",
+            "9223372036854775808 is out of range for int64",
+        ),
+        ("syntax = \"proto2\"; message M { optional uint64 a = 1 [default = -1]; }", "-1 is out of range for uint64"),
+        ("syntax = \"proto2\"; message M { optional int32 a = 1 [default = 1, default = 2]; }", "Option 'default' is set more than once"),
+        ("syntax = \"proto3\"; message M { optional group G = 1 {} }", "Groups are not supported in proto3"),
+        ("syntax = \"proto2\"; message M { optional group g = 1 {} }", "Group name 'g' must start with a capital letter"),
+        ("syntax = \"proto2\"; message M { optional map<string, string> m = 1; }", "Map fields cannot be optional"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_extend_blocks() {
+    use DMXP_Protobuf_Plugin::ast::{FieldLabel, FieldType};
+
+    let ast = parse_source(
+        r#"syntax = "proto3";
+package acme;
+extend google.protobuf.MessageOptions {
+  // Audit topic
+  string topic = 50500;
+  repeated int32 shards = 50501;
+}
+message Outer {
+  extend google.protobuf.FieldOptions {
+    bool secret = 50600;
+  }
+  option (acme.topic) = "audit";
+  option (dmxp_channel) = "outer";
+}"#,
+    );
+
+    let names: Vec<(&str, &str, &str)> = ast
+        .extensions
+        .iter()
+        .map(|e| (e.name.as_str(), e.extendee.as_str(), e.scope.as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("topic", "google.protobuf.MessageOptions", ""),
+            ("shards", "google.protobuf.MessageOptions", ""),
+            ("secret", "google.protobuf.FieldOptions", "Outer"),
+        ]
+    );
+    assert_eq!(ast.extensions[0].field_type, FieldType::String);
+    assert_eq!(ast.extensions[0].number, 50500);
+    assert_eq!(ast.extensions[0].comments.leading.as_deref(), Some("Audit topic"));
+    assert!(matches!(ast.extensions[1].label, FieldLabel::Repeated));
+
+    let topic = ast.find_option_extension("google.protobuf.MessageOptions", "(acme.topic)");
+    assert_eq!(topic.map(|e| e.number), Some(50500));
+    assert!(ast.find_option_extension("google.protobuf.ServiceOptions", "(topic)").is_none());
+
+    let outer = &ast.messages[0];
+    assert_eq!(outer.options.len(), 2);
+    assert_eq!(outer.get_dmxp_channel().map(String::as_str), Some("outer"));
+}
+
+#[test]
+fn test_dmxp_options_recognized_by_declaration() {
+    use DMXP_Protobuf_Plugin::parser::bundled;
+
+    let bundled = bundled::dmxp_options();
+    assert!(bundled.find_option_extension("google.protobuf.MessageOptions", "(dmxp_channel)").is_some());
+    assert!(bundled.find_option_extension("google.protobuf.MethodOptions", "(dmxp_method_timeout_ms)").is_some());
+    // Option names are unique in the package, as protoc requires
+    assert!(bundled.find_option_extension("google.protobuf.MethodOptions", "(dmxp_timeout_ms)").is_none());
+
+    let source = r#"syntax = "proto3";
+extend google.protobuf.MessageOptions {
+  int32 dmxp_priority = 50006;
+}
+message A {
+  option (dmxp_channel_name) = "a";
+  option (dmxp_priority) = 5;
+}
+message B {
+  option (dmxp_channel) = "b";
+  option (dmxp_buffer_size) = 64;
+}"#;
+    let (ast, diagnostics) = ProtoParser::new(source.to_string()).with_strict_dmxp(false).parse_partial();
+
+    // Neither an undeclared name nor a redeclaration with another type is a DMXP option
+    assert!(ast.messages[0].dmxp_options.is_none());
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert!(diagnostics[0].message.contains("Unknown DMXP option '(dmxp_channel_name)'"));
+    assert_eq!(ast.messages[0].options.len(), 2);
+
+    let b = ast.messages[1].dmxp_options.as_ref().expect("missing dmxp options");
+    assert_eq!(b.channel.as_deref(), Some("b"));
+    assert_eq!(b.buffer_size, Some(64));
+}
+
+#[test]
+fn test_invalid_extend_blocks() {
+    for (source, expected) in [
+        ("syntax = \"proto2\"; extend Foo { required int32 a = 100; }", "Extensions cannot be required"),
+        ("syntax = \"proto2\"; extend Foo { int32 a = 100; }", "Extensions in proto2 must be 'optional' or 'repeated'"),
+        ("extend Foo { map<string, int32> a = 100; }", "Extensions cannot be map fields"),
+        ("extend Foo { int32 a = 100; int32 b = 100; }", "Extension number 100 on 'Foo' is already used by 'a'"),
+        ("extend Foo { int32 a = 100;", "Unexpected end of file while parsing extend"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_file_options() {
+    use DMXP_Protobuf_Plugin::ast::{GoPackage, OptionValue};
+
+    let ast = parse_source(
+        r#"syntax = "proto3";
+package acme.events;
+option go_package = "github.com/acme/events;eventspb";
+option java_package = "com.acme.events";
+option java_multiple_files = true;
+option optimize_for = SPEED;
+option (acme.owner) = "platform";
+message A {}"#,
+    );
+
+    assert_eq!(ast.options.len(), 5);
+    assert!(matches!(ast.find_option("java_package"), Some(OptionValue::String(s)) if s == "com.acme.events"));
+    assert!(matches!(ast.find_option("java_multiple_files"), Some(OptionValue::Boolean(true))));
+    assert!(matches!(ast.find_option("(acme.owner)"), Some(OptionValue::String(s)) if s == "platform"));
+    assert_eq!(
+        ast.go_package(),
+        Some(GoPackage { import_path: "github.com/acme/events".to_string(), name: "eventspb".to_string() })
+    );
+
+    let bare = parse_source("option go_package = \"github.com/acme/user-events\";");
+    assert_eq!(bare.go_package().map(|p| p.name), Some("user_events".to_string()));
+}
+
+#[test]
+fn test_invalid_file_options() {
+    for (source, expected) in [
+        ("option go_package = 5;", "Invalid value for file option 'go_package'"),
+        ("option java_multiple_files = \"yes\";", "Invalid value for file option 'java_multiple_files'"),
+        ("option optimize_for = FAST;", "Invalid value for file option 'optimize_for'"),
+        ("option go_package = \"a\"; option go_package = \"b\";", "File option 'go_package' is set more than once"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_enum_value_forms_and_aliases() {
+    use DMXP_Protobuf_Plugin::ast::OptionValue;
+
+    let ast = parse_source(
+        r#"syntax = "proto3";
+enum Status {
+  option allow_alias = true;
+  STATUS_UNKNOWN = 0;
+  STATUS_STARTED = 0x1;
+  STATUS_RUNNING = 1 [deprecated = true, (acme.label) = "run"];
+  STATUS_FAILED = -1;
+  STATUS_OCTAL = 017;
+  STATUS_NEG_HEX = -0x10;
+}"#,
+    );
+
+    let status = &ast.enums[0];
+    assert!(status.allows_alias());
+    let numbers: Vec<i32> = status.values.iter().map(|v| v.number).collect();
+    assert_eq!(numbers, vec![0, 1, 1, -1, 15, -16]);
+
+    let running = &status.values[2];
+    assert!(running.is_deprecated());
+    assert!(matches!(running.find_option("(acme.label)"), Some(OptionValue::String(s)) if s == "run"));
+
+    let aliases: Vec<(&str, &str)> = status.aliases().map(|(a, c)| (a.name.as_str(), c.name.as_str())).collect();
+    assert_eq!(aliases, vec![("STATUS_RUNNING", "STATUS_STARTED")]);
+    assert_eq!(status.canonical_values().count(), 5);
+}
+
+#[test]
+fn test_invalid_enums() {
+    for (source, expected) in [
+        ("enum E { A = 0; B = 0; }", "Enum value 'B' reuses number 0 of 'A' in enum 'E'"),
+        ("enum E { option allow_alias = true; A = 0; B = 1; }", "Enum 'E' sets allow_alias but has no aliases"),
+        ("enum E { option allow_alias = 1; A = 0; }", "Invalid value for enum option 'allow_alias'"),
+        ("enum E { A = 0; A = 1; }", "Enum value 'A' is already defined in enum 'E'"),
+        ("enum E { A = 0 [deprecated = \"yes\"]; }", "Invalid value for option 'deprecated' on enum value 'A'"),
+        ("syntax = \"proto3\"; enum E { A = 1; }", "The first value of enum 'E' must be zero in proto3"),
+        ("enum E { A = 0x; }", "Invalid hex number '0x'"),
+        ("enum E { A = 09; }", "Invalid octal number '09'"),
+        ("enum E { A = 0x80000000; }", "Enum value '2147483648' is out of range"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_constant_literals() {
+    use DMXP_Protobuf_Plugin::ast::OptionValue;
+
+    let ast = parse_source(
+        r#"syntax = "proto2";
+option java_package = 'com.acme' ".events";
+message Literals {
+  optional string quoted = 1 [default = "say \"hi\"\n\t\x41\101é"];
+  optional string single = 2 [default = 'it\'s'];
+  optional double low = 3 [default = -inf];
+  optional float missing = 4 [default = nan];
+  optional int64 hex = 5 [default = -0x10];
+}"#,
+    );
+
+    assert!(matches!(ast.find_option("java_package"), Some(OptionValue::String(s)) if s == "com.acme.events"));
+
+    let defaults: Vec<&OptionValue> = ast.messages[0].fields.iter().filter_map(|f| f.default_value.as_ref()).collect();
+    assert!(matches!(defaults[0], OptionValue::String(s) if s == "say \"hi\"\n\tAAé"));
+    assert!(matches!(defaults[1], OptionValue::String(s) if s == "it's"));
+    assert!(matches!(defaults[2], OptionValue::Number(n) if *n == f64::NEG_INFINITY));
+    assert!(matches!(defaults[3], OptionValue::Number(n) if n.is_nan()));
+    assert!(matches!(defaults[4], OptionValue::Int(-16)));
+}
+
+#[test]
+fn test_aggregate_option_values() {
+    use DMXP_Protobuf_Plugin::ast::OptionValue;
+
+    let ast = parse_source(
+        r#"syntax = "proto3";
+message Event {
+  option (acme.routing) = {
+    name: "events"
+    retry { count: 3, backoff_ms: 250 }
+    tags: ["a", "b"];
+    [acme.ext]: true
+    limits < max: -1 >
+  };
+}"#,
+    );
+
+    let Some(OptionValue::Aggregate(fields)) = ast.messages[0].options.first().map(|o| &o.value) else {
+        panic!("Expected an aggregate option value");
+    };
+    let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["name", "retry", "tags", "[acme.ext]", "limits"]);
+    assert!(matches!(&fields[0].value, OptionValue::String(s) if s == "events"));
+    assert!(matches!(&fields[1].value, OptionValue::Aggregate(retry) if retry.len() == 2
+        && matches!(retry[1].value, OptionValue::UInt(250))));
+    assert!(matches!(&fields[2].value, OptionValue::List(tags) if tags.len() == 2));
+    assert!(matches!(fields[3].value, OptionValue::Boolean(true)));
+    assert!(matches!(&fields[4].value, OptionValue::Aggregate(limits)
+        if matches!(limits[0].value, OptionValue::Int(-1))));
+}
+
+#[test]
+fn test_invalid_literals() {
+    for (source, expected) in [
+        (r#"option java_package = "a\q";"#, "Invalid escape sequence '\\q'"),
+        (r#"option java_package = "\u12";"#, "Invalid escape sequence '\\u12'"),
+        (r#"option java_package = "\xff";"#, "is not valid UTF-8"),
+        ("option java_package = \"abc\n\";", "Unterminated string literal"),
+        ("option (a) = { name \"x\" };", "Expected ':'"),
+        ("option (a) = { name: \"x\" ", "Expected an identifier, found end of file"),
+    ] {
+        let mut parser = ProtoParser::new(source.to_string());
+        let err = parser.parse().expect_err("Parsing should fail").to_string();
+        assert!(err.contains(expected), "unexpected error for {}: {}", source, err);
+    }
+}
+
+#[test]
+fn test_source_spans() {
+    use DMXP_Protobuf_Plugin::ast::Span;
+
+    let source = "syntax = \"proto3\";\n// Café\nmessage User {\n  option (dmxp_channel) = \"users\";\n  string name = 1 [deprecated = true];\n}\n\nenum Role { ROLE_UNSPECIFIED = 0; }\nservice Users {\n  rpc Get(User) returns (User);\n}\n";
+    let mut parser = ProtoParser::new(source.to_string()).with_file_name("acme/user.proto");
+    let ast = parser.parse().expect("Parsing failed");
+    assert_eq!(ast.name, "acme/user.proto");
+
+    let at = |needle: &str| {
+        let offset = source.find(needle).unwrap();
+        let line = source[..offset].matches('\n').count() + 1;
+        let column = source[..offset].rsplit('\n').next().unwrap().chars().count() + 1;
+        Span { file: "acme/user.proto".to_string(), offset, line, column }
+    };
+
+    let user = &ast.messages[0];
+    assert_eq!(user.span, at("message User"));
+    assert_eq!(user.options[0].span, at("option (dmxp_channel)"));
+    assert_eq!(user.fields[0].span, at("string name"));
+    assert_eq!(user.fields[0].options[0].span, at("deprecated = true"));
+    assert_eq!(ast.enums[0].span, at("enum Role"));
+    assert_eq!(ast.enums[0].values[0].span, at("ROLE_UNSPECIFIED"));
+    assert_eq!(ast.services[0].span, at("service Users"));
+    assert_eq!(ast.services[0].methods[0].span, at("rpc Get"));
+    assert_eq!(user.span.line, 3);
+}
+
+#[test]
+fn test_parse_error_diagnostics() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError, Severity};
+
+    let source = "syntax = \"proto3\";\nmessage User {\n  required string name = 1;\n}\n";
+    let mut parser = ProtoParser::new(source.to_string()).with_file_name("user.proto");
+    let err = parser.parse().expect_err("Parsing should fail");
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+
+    let diagnostic = &parse_error.diagnostics[0];
+    assert_eq!(diagnostic.code, ErrorCode::InvalidLabel);
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.span.as_ref().map(|s| (s.line, s.column)), Some((3, 3)));
+    assert_eq!(
+        err.to_string(),
+        "user.proto: Required fields are not allowed in proto3 at line 3, column 3"
+    );
+
+    assert_eq!(
+        parse_error.render(source),
+        "error[E0005]: Required fields are not allowed in proto3\n \
+         --> user.proto:3:3\n  \
+         |\n\
+         3 |   required string name = 1;\n  \
+         |   ^^^^^^^^\n  \
+         |\n  \
+         = help: remove 'required'; proto3 fields are optional by default\n"
+    );
+
+    let json = parse_error.to_json();
+    assert!(json.starts_with(r#"{"diagnostics":[{"code":"E0005","severity":"error","#), "{}", json);
+    assert!(json.contains(r#""span":{"file":"user.proto","offset":36,"line":3,"column":3},"length":8"#), "{}", json);
+}
+
+#[test]
+fn test_lexer_errors_are_diagnostics() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError};
+
+    let source = "message A {\n  string s = 1 [json_name = \"abc\n}";
+    let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail");
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+    assert_eq!(parse_error.diagnostics[0].code, ErrorCode::InvalidToken);
+    assert!(parse_error.render(source).contains(" --> <input>:2:29\n"), "{}", parse_error.render(source));
+}
+
+#[test]
+fn test_out_of_range_default_is_invalid_number() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError};
+
+    let source = "syntax = \"proto2\";\nmessage M {\n  optional uint32 a = 1 [default = -1];\n}\n";
+    let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail");
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+    assert_eq!(parse_error.diagnostics[0].code, ErrorCode::InvalidNumber);
+}
+
+#[test]
+fn test_error_recovery_reports_every_problem() {
+    let source = r#"syntax = "proto3";
+message User {
+  string name = ;
+  int32 age = 2;
+  required string email = 3;
+  oneof kind {
+    repeated string tags = 4;
+    string nick = 5;
+  }
+}
+enum Role { ROLE_ADMIN = 1; }
+service Users {
+  rpc Get(User) returns User;
+  rpc List(User) returns (User);
+}
+messge Typo { }
+message After { string x = 1; }
+"#;
+
+    let (ast, diagnostics) = ProtoParser::new(source.to_string()).parse_partial();
+    let lines: Vec<usize> = diagnostics.iter().filter_map(|d| d.span.as_ref().map(|s| s.line)).collect();
+    assert_eq!(lines, vec![3, 5, 7, 11, 13, 16]);
+
+    let user = ast.find_message("User").expect("User should be kept");
+    assert_eq!(user.fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["age"]);
+    assert_eq!(user.oneofs[0].fields[0].name, "nick");
+    assert_eq!(ast.enums[0].values.len(), 1);
+    assert_eq!(ast.services[0].methods[0].name, "List");
+    assert!(ast.find_message("After").is_some());
+
+    let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail").to_string();
+    assert_eq!(err.lines().count(), 6, "{}", err);
+}
+
+#[test]
+fn test_error_recovery_at_end_of_file() {
+    let (ast, diagnostics) = ProtoParser::new("message A {\n  message B {\n    string x = 1;".to_string()).parse_partial();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Unexpected end of file while parsing message");
+    assert_eq!(ast.messages[0].nested_messages[0].fields[0].name, "x");
+}
+
+#[test]
+fn test_editions_resolve_features() {
+    use DMXP_Protobuf_Plugin::ast::{EnumType, FieldLabel, FieldPresence, RepeatedFieldEncoding};
+
+    let source = r#"edition = "2023";
+package acme;
+option features.field_presence = IMPLICIT;
+
+message User {
+  string name = 1;
+  int32 age = 2 [features.field_presence = EXPLICIT];
+  string id = 3 [features.field_presence = LEGACY_REQUIRED];
+  repeated int32 scores = 4 [features.repeated_field_encoding = EXPANDED];
+  string nick = 5 [default = "anon", features = { field_presence: EXPLICIT }];
+
+  message Inner {
+    option features.field_presence = EXPLICIT;
+    string note = 1;
+  }
+}
+
+enum Color {
+  option features.enum_type = CLOSED;
+  COLOR_RED = 1;
+}
+enum Shade { SHADE_UNSPECIFIED = 0; }
+"#;
+
+    let ast = ProtoParser::new(source.to_string()).parse().expect("Failed to parse editions file");
+    assert_eq!(ast.syntax, "editions");
+    assert_eq!(ast.edition.as_deref(), Some("2023"));
+    assert_eq!(ast.features.map(|f| f.field_presence), Some(FieldPresence::Implicit));
+
+    let user = ast.find_message("User").expect("User message not found");
+    let presence = |name: &str| user.fields.iter().find(|f| f.name == name).and_then(|f| f.features).map(|f| f.field_presence);
+    assert_eq!(presence("name"), Some(FieldPresence::Implicit));
+    assert_eq!(presence("age"), Some(FieldPresence::Explicit));
+    assert_eq!(presence("nick"), Some(FieldPresence::Explicit));
+    assert!(matches!(user.fields[2].label, FieldLabel::Required));
+    assert!(user.fields[0].has_implicit_presence());
+    assert!(!user.fields[3].is_packed(&ast.syntax));
+    assert_eq!(user.fields[3].features.map(|f| f.repeated_field_encoding), Some(RepeatedFieldEncoding::Expanded));
+    assert_eq!(
+        user.nested_messages[0].fields[0].features.map(|f| f.field_presence),
+        Some(FieldPresence::Explicit)
+    );
+
+    assert_eq!(ast.find_enum("Color").and_then(|e| e.features).map(|f| f.enum_type), Some(EnumType::Closed));
+    assert!(ast.find_enum("Shade").is_some_and(|e| e.generates_open_enum()));
+
+    let proto3 = ProtoParser::new("syntax = \"proto3\";\nmessage A { string a = 1; }".to_string())
+        .parse()
+        .expect("Failed to parse proto3 file");
+    assert!(proto3.features.is_none() && proto3.messages[0].fields[0].features.is_none());
+}
+
+#[test]
+fn test_invalid_editions() {
+    use DMXP_Protobuf_Plugin::parser::ErrorCode;
+
+    let source = r#"edition = "2023";
+message A {
+  optional string a = 1;
+  required string b = 2;
+  repeated int32 c = 3 [packed = true];
+  string d = 4 [features.field_presence = SOMETIMES];
+  string e = 5 [features.colour = RED];
+  repeated string f = 6 [features.field_presence = IMPLICIT];
+  string g = 7 [default = "x", features.field_presence = IMPLICIT];
+  optional group H = 8 { }
+}
+enum Open { OPEN_ONE = 1; }
+"#;
+    let (_, diagnostics) = ProtoParser::new(source.to_string()).parse_partial();
+    let found: Vec<(usize, ErrorCode)> = diagnostics
+        .iter()
+        .map(|d| (d.span.as_ref().map_or(0, |s| s.line), d.code))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, ErrorCode::InvalidLabel),
+            (4, ErrorCode::InvalidLabel),
+            (5, ErrorCode::InvalidOption),
+            (6, ErrorCode::InvalidFeature),
+            (7, ErrorCode::InvalidFeature),
+            (8, ErrorCode::InvalidFeature),
+            (9, ErrorCode::InvalidDefault),
+            (10, ErrorCode::InvalidLabel),
+            (12, ErrorCode::InvalidEnum),
+        ]
+    );
+    assert_eq!(diagnostics[3].message, "Invalid value 'SOMETIMES' for feature 'field_presence'");
+    assert_eq!(diagnostics[4].message, "Unknown feature 'colour'");
+
+    let err = ProtoParser::new("edition = \"1999\";".to_string()).parse().expect_err("Parsing should fail");
+    assert!(err.to_string().contains("Edition '1999' is not supported"), "{}", err);
+
+    let source = "syntax = \"proto3\";\noption features.field_presence = IMPLICIT;\n";
+    let err = ProtoParser::new(source.to_string()).parse().expect_err("Parsing should fail");
+    assert!(err.to_string().contains("Features are only allowed in editions files"), "{}", err);
+}
+
+#[test]
+fn test_editions_reserved_names() {
+    use DMXP_Protobuf_Plugin::parser::{ErrorCode, ParseError};
+
+    let ast = parse_source(
+        "edition = \"2023\"; message M { reserved foo, bar; reserved 2 to 4; string a = 1; } enum E { E_ZERO = 0; reserved OLD; }",
+    );
+    assert_eq!(ast.messages[0].reserved_names, vec!["foo", "bar"]);
+    assert_eq!(ast.messages[0].reserved_ranges.len(), 1);
+    assert_eq!(ast.enums[0].reserved_names, vec!["OLD"]);
+
+    let err = ProtoParser::new("edition = \"2023\"; message M { reserved \"foo\"; }".to_string())
+        .parse()
+        .expect_err("Parsing should fail");
+    let parse_error = err.downcast_ref::<ParseError>().expect("Expected a ParseError");
+    assert_eq!(parse_error.diagnostics[0].code, ErrorCode::UnexpectedToken);
+    assert!(err.to_string().contains("Reserved names must be identifiers in editions"), "{}", err);
+
+    // proto3 keeps string literals and rejects identifiers
+    let ast = parse_source("syntax = \"proto3\"; message M { reserved \"foo\"; }");
+    assert_eq!(ast.messages[0].reserved_names, vec!["foo"]);
+    let err = ProtoParser::new("syntax = \"proto3\"; message M { reserved foo; }".to_string())
+        .parse()
+        .expect_err("Parsing should fail");
+    assert!(err.to_string().contains("Expected an integer, found 'foo'"), "{}", err);
+}
+
+#[test]
+fn test_parse_from_memory() {
+    use DMXP_Protobuf_Plugin::parser::{parse_bytes, parse_reader, parse_str};
+
+    let source = "syntax = \"proto3\";\npackage acme;\nmessage Ping { string id = 1; }\n";
+
+    let from_str = parse_str("acme/ping.proto", source).expect("Failed to parse str");
+    assert_eq!(from_str.name, "acme/ping.proto");
+    assert_eq!(from_str.messages[0].span.file, "acme/ping.proto");
+
+    let from_bytes = parse_bytes("ping.proto", source.as_bytes()).expect("Failed to parse bytes");
+    assert_eq!(from_bytes.messages[0].name, "Ping");
+
+    let from_reader = parse_reader("ping.proto", std::io::Cursor::new(source)).expect("Failed to parse reader");
+    assert_eq!(from_reader.package, "acme");
+
+    let err = parse_bytes("bad.proto", b"syntax = \"proto3\"; \xff").expect_err("Parsing should fail");
+    assert_eq!(err.to_string(), "'bad.proto' is not valid UTF-8");
+
+    let err = parse_str("bad.proto", "message {").expect_err("Parsing should fail");
+    assert!(err.to_string().starts_with("bad.proto: "), "{}", err);
+}
+
+#[test]
+fn test_qualified_type_references() {
+    use DMXP_Protobuf_Plugin::ast::{FieldLabel, FieldType};
+
+    let source = r#"syntax = "proto3";
+package acme.v1;
+message Outer {
+  message Inner { string id = 1; }
+  Inner inner = 1;
+  .acme.v1.Outer.Inner absolute = 2;
+  repeated .acme.v1.Outer.Inner many = 3;
+  map<string, Outer.Inner> by_id = 4;
+  oneof pick { .acme.v1.Outer.Inner chosen = 5; }
+}
+service Things {
+  rpc Get(.acme.v1.Outer) returns (Outer.Inner);
+  rpc Watch(stream .acme.v1.Outer) returns (stream Outer.Inner);
+}
+"#;
+
+    let ast = ProtoParser::new(source.to_string()).parse().expect("Failed to parse qualified types");
+    let outer = ast.find_message("Outer").expect("Outer message not found");
+    assert_eq!(outer.fields[1].field_type, FieldType::Message(".acme.v1.Outer.Inner".to_string()));
+    assert!(matches!(outer.fields[2].label, FieldLabel::Repeated));
+    assert_eq!(outer.fields[2].field_type, FieldType::Message(".acme.v1.Outer.Inner".to_string()));
+    assert_eq!(outer.oneofs[0].fields[0].field_type, FieldType::Message(".acme.v1.Outer.Inner".to_string()));
+    assert!(outer.fields.iter().all(|f| f.resolved_type.as_deref() == Some(".acme.v1.Outer.Inner")));
+
+    let methods = &ast.services[0].methods;
+    assert_eq!((methods[0].input_type.as_str(), methods[0].output_type.as_str()), (".acme.v1.Outer", "Outer.Inner"));
+    assert!(methods[1].client_streaming && methods[1].server_streaming);
+    assert_eq!(methods[1].input_type, ".acme.v1.Outer");
+    assert_eq!(methods[0].resolved_output_type.as_deref(), Some(".acme.v1.Outer.Inner"));
+}
+
+#[test]
+fn test_aggregate_dmxp_options() {
+    let ast = parse_source(
+        r#"syntax = "proto3";
+message UserUpdate {
+  option (dmxp.channel) = { name: "user_updates" buffer_size: 4096 persistent: true wal_enabled: true priority: 2 };
+  string id = 1;
+}
+service Users {
+  option (dmxp.service) = { channels: ["user_updates", "audit"] timeout_ms: 500 retry_count: 3 };
+  rpc Update(UserUpdate) returns (UserUpdate) {
+    option (dmxp.method) = { channel: "user_updates.write" async: true };
+  }
+}"#,
+    );
+
+    let message = ast.find_message("UserUpdate").expect("UserUpdate message not found");
+    let dmxp = message.dmxp_options.as_ref().expect("missing message dmxp options");
+    assert_eq!(dmxp.channel.as_deref(), Some("user_updates"));
+    assert_eq!((dmxp.buffer_size, dmxp.priority), (Some(4096), Some(2)));
+    assert_eq!((dmxp.persistent, dmxp.wal_enabled, dmxp.swap_enabled), (Some(true), Some(true), None));
+
+    let service = &ast.services[0];
+    let service_dmxp = service.dmxp_options.as_ref().expect("missing service dmxp options");
+    assert_eq!(service_dmxp.channels, vec!["user_updates", "audit"]);
+    assert_eq!((service_dmxp.timeout_ms, service_dmxp.retry_count), (Some(500), Some(3)));
+
+    let method_dmxp = service.methods[0].dmxp_options.as_ref().expect("missing method dmxp options");
+    assert_eq!(method_dmxp.channel.as_deref(), Some("user_updates.write"));
+    assert_eq!((method_dmxp.timeout_ms, method_dmxp.is_async), (None, Some(true)));
+}
+
+#[test]
+fn test_invalid_aggregate_dmxp_options() {
+    let unknown = r#"syntax = "proto3";
+message M {
+  option (dmxp.channel) = { name: "m" buffer: 10 };
+}"#;
+    let err = ProtoParser::new(unknown.to_string()).parse().unwrap_err().to_string();
+    assert!(err.contains("Unknown field 'buffer' in option '(dmxp.channel)'"), "{}", err);
+    assert!(err.contains("line 3, column 39"), "{}", err);
+
+    let scalar = r#"syntax = "proto3";
+message M {
+  option (dmxp.channel) = "m";
+}"#;
+    let err = ProtoParser::new(scalar.to_string()).parse().unwrap_err().to_string();
+    assert!(err.contains("Option '(dmxp.channel)' must be set to a message literal"), "{}", err);
+}
+
+#[test]
+fn test_strict_dmxp_option_validation() {
+    use DMXP_Protobuf_Plugin::parser::diagnostic::{ErrorCode, Severity};
+
+    let source = r#"syntax = "proto3";
+message M {
+  option (dmxp_bufer_size) = 64;
+  option (dmxp_buffer_size) = "5000";
+  option (dmxp_priority) = -1;
+  option (dmxp_persistent) = 1;
+  option (dmxp.channel) = { nme: "m" wal_enabled: "yes" };
+}
+service S {
+  option (dmxp_channels) = 7;
+  rpc Call(M) returns (M) { option (dmxp_channel) = "c"; }
+}"#;
+
+    let (_, diagnostics) = ProtoParser::new(source.to_string()).parse_partial();
+    let found: Vec<(ErrorCode, &str, Option<&str>)> = diagnostics
+        .iter()
+        .map(|d| (d.code, d.message.as_str(), d.help.as_deref()))
+        .collect();
+    assert_eq!(found, vec![
+        (ErrorCode::InvalidOption, "Unknown DMXP option '(dmxp_bufer_size)' in MessageOptions",
+            Some("did you mean '(dmxp_buffer_size)'?")),
+        (ErrorCode::InvalidOption,
+            "Invalid value for option '(dmxp_buffer_size)': expected an unsigned integer, found string \"5000\"", None),
+        (ErrorCode::InvalidNumber,
+            "Invalid value for option '(dmxp_priority)': -1 is out of range for uint32 (0 to 4294967295)", None),
+        (ErrorCode::InvalidOption, "Invalid value for option '(dmxp_persistent)': expected true or false, found number 1",
+            None),
+        (ErrorCode::InvalidOption, "Unknown field 'nme' in option '(dmxp.channel)'", Some("did you mean 'name'?")),
+        (ErrorCode::InvalidOption,
+            "Invalid value for field 'wal_enabled' of option '(dmxp.channel)': expected true or false, found string \"yes\"",
+            None),
+        (ErrorCode::InvalidOption, "Invalid value for option '(dmxp_channels)': expected a string, found number 7", None),
+        (ErrorCode::InvalidOption, "Unknown DMXP option '(dmxp_channel)' in MethodOptions", None),
+    ]);
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+    assert_eq!(diagnostics[0].span.as_ref().map(|s| (s.line, s.column)), Some((3, 10)));
+    // Values spanning several tokens are underlined whole
+    assert_eq!(diagnostics[2].span.as_ref().map(|s| (s.line, s.column)), Some((5, 28)));
+    assert_eq!(diagnostics[2].length, 2);
+    let (_, concatenated) =
+        ProtoParser::new("message M {\n  option (dmxp_priority) = \"a\" \"b\";\n}".to_string()).parse_partial();
+    assert_eq!(concatenated[0].span.as_ref().map(|s| (s.line, s.column)), Some((2, 28)));
+    assert_eq!(concatenated[0].length, "\"a\" \"b\"".len());
+    assert_eq!(diagnostics[7].notes, vec!["'(dmxp_channel)' is declared for MessageOptions"]);
+
+    // Without strict mode the same problems are warnings, and readable values still apply
+    let ast = ProtoParser::new(source.to_string())
+        .with_strict_dmxp(false)
+        .parse()
+        .expect("lenient mode should only warn");
+    let (_, diagnostics) = ProtoParser::new(source.to_string()).with_strict_dmxp(false).parse_partial();
+    assert!(diagnostics.len() == 8 && diagnostics.iter().all(|d| d.severity == Severity::Warning));
+    let dmxp = ast.messages[0].dmxp_options.as_ref().expect("missing dmxp options");
+    assert_eq!((dmxp.buffer_size, dmxp.priority, dmxp.persistent), (Some(5000), None, None));
+}
+
+#[test]
+fn test_field_presence() {
+    use DMXP_Protobuf_Plugin::ast::{FieldLabel, FieldPresence, FieldType};
+
+    let proto3 = parse_source(
+        r#"syntax = "proto3";
+message M {
+  string plain = 1;
+  optional string explicit = 2;
+  repeated string many = 3;
+  M child = 4;
+  oneof pick { string chosen = 5; }
+  Kind kind = 6;
+  enum Kind { KIND_UNSPECIFIED = 0; }
+}"#,
+    );
+    let message = &proto3.messages[0];
+    let presence: Vec<FieldPresence> = message.fields.iter().map(|f| f.presence).collect();
+    assert_eq!(presence[..2], [FieldPresence::Implicit, FieldPresence::Explicit]);
+    assert!(matches!(message.fields[1].label, FieldLabel::Optional));
+    assert!(message.fields[0].has_implicit_presence() && !message.fields[1].has_implicit_presence());
+    // Repeated and message fields never have implicit presence, nor do oneof members
+    assert!(!message.fields[2].has_implicit_presence() && !message.fields[3].has_implicit_presence());
+    assert_eq!(message.oneofs[0].fields[0].presence, FieldPresence::Explicit);
+    // Enums declared in the file are recognized without the loader
+    assert!(matches!(&message.fields[4].field_type, FieldType::Enum(name) if name == "Kind"));
+    assert!(message.fields[4].has_implicit_presence());
+
+    let proto2 = parse_source("syntax = \"proto2\"; message M { optional string a = 1; required string b = 2; }");
+    let presence: Vec<FieldPresence> = proto2.messages[0].fields.iter().map(|f| f.presence).collect();
+    assert_eq!(presence, vec![FieldPresence::Explicit, FieldPresence::LegacyRequired]);
+
+    let editions = parse_source(
+        "edition = \"2023\"; message M { string a = 1; string b = 2 [features.field_presence = IMPLICIT]; }",
+    );
+    let presence: Vec<FieldPresence> = editions.messages[0].fields.iter().map(|f| f.presence).collect();
+    assert_eq!(presence, vec![FieldPresence::Explicit, FieldPresence::Implicit]);
+}